@@ -8,6 +8,8 @@ module Client
 , getExpClient
 , createExpClient
 , getApplicableVariants
+, getVariantAssignments
+, setForcedVariants
 , getSatisfiedExperiments
 , getRunningExperiments
 ) where
@@ -52,6 +54,12 @@ foreign import ccall unsafe "start_polling_update"
 foreign import ccall unsafe "get_applicable_variant"
     c_get_applicable_variants :: Ptr ExpClient -> CString -> CShort -> IO CString
 
+foreign import ccall unsafe "get_variant_assignments"
+    c_get_variant_assignments :: Ptr ExpClient -> CString -> CShort -> IO CString
+
+foreign import ccall unsafe "set_forced_variants"
+    c_set_forced_variants :: Ptr ExpClient -> CString -> IO CInt
+
 foreign import ccall unsafe "get_satisfied_experiments"
     c_get_satisfied_experiments :: Ptr ExpClient -> CString -> IO CString
 
@@ -107,6 +115,27 @@ getApplicableVariants client query toss = do
                     -- Error s     -> Left s
                     -- Success vec -> Right vec
 
+getVariantAssignments :: ForeignPtr ExpClient -> String -> Integer -> IO (Either Error String)
+getVariantAssignments client query toss = do
+    context     <- newCAString query
+    assignments <- withForeignPtr client (\c -> c_get_variant_assignments c context (fromInteger toss))
+    _           <- cleanup [context]
+    if assignments == nullPtr
+        then Left <$> getError
+        else do
+            fptrAssignments <- newForeignPtr c_free_string assignments
+            Right <$> withForeignPtr fptrAssignments peekCAString
+
+-- | takes a JSON object of experiment ids to the variant ids to force
+setForcedVariants :: ForeignPtr ExpClient -> String -> IO (Either Error ())
+setForcedVariants client forcedVariants = do
+    cForcedVariants <- newCAString forcedVariants
+    resp            <- withForeignPtr client (`c_set_forced_variants` cForcedVariants)
+    _               <- cleanup [cForcedVariants]
+    case resp of
+        0 -> pure $ Right ()
+        _ -> Left <$> getError
+
 getSatisfiedExperiments :: ForeignPtr ExpClient -> String -> IO (Either Error Value)
 getSatisfiedExperiments client query = do
     context     <- newCAString query
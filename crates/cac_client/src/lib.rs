@@ -17,9 +17,7 @@ use std::{
 use strum_macros;
 use utils::core::MapError;
 
-use service_utils::{
-    helpers::extract_dimensions, result as superposition, unexpected_error,
-};
+use service_utils::{helpers::parse_context, result as superposition, unexpected_error};
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Context {
@@ -353,12 +351,9 @@ pub fn filter_config_by_dimensions(
      -> superposition::Result<Vec<Context>> {
         let mut filtered_context: Vec<Context> = Vec::new();
         for context in contexts.iter() {
-            let dimension = extract_dimensions(&context.condition)?;
-            let should_add_ctx = dimension.iter().all(|(key, value)| {
-                query_params_map.get(key).map_or(true, |val| {
-                    val == value || val.as_array().unwrap_or(&vec![]).contains(value)
-                })
-            });
+            let should_add_ctx = parse_context(&context.condition)?
+                .evaluate(query_params_map)
+                != Some(false);
             if should_add_ctx {
                 filtered_context.push(context.clone());
             }
@@ -0,0 +1,25 @@
+-- Your SQL goes here
+CREATE TABLE public.config_versions (
+    id bigserial PRIMARY KEY,
+    contexts json NOT NULL,
+    default_configs json NOT NULL,
+    description text,
+    created_at timestamp with time zone DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    created_by character varying NOT NULL
+);
+
+CREATE INDEX config_versions_created_at_index ON public.config_versions(created_at);
+
+-- the configuration present at the time of migration becomes the first version
+INSERT INTO public.config_versions (contexts, default_configs, description, created_by)
+SELECT
+    COALESCE(
+        (SELECT json_agg(row_to_json(c) ORDER BY c.priority, c.created_at) FROM public.contexts c),
+        '[]'::json
+    ),
+    COALESCE(
+        (SELECT json_agg(row_to_json(d) ORDER BY d.key) FROM public.default_configs d),
+        '[]'::json
+    ),
+    'Initial version',
+    'user@superposition.io';
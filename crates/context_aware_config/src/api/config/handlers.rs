@@ -2,25 +2,41 @@ use std::collections::HashSet;
 use std::{collections::HashMap, str::FromStr};
 
 use super::helpers::{
+    add_config_version, build_config, config_at_version, diff_from_events,
     filter_config_by_dimensions, filter_config_by_prefix, filter_context,
+    parse_config_version,
 };
 
-use super::types::Config;
+use super::types::{
+    Config, ConfigDiff, ConfigVersionResponse, DiffQuery, RollbackQuery,
+    VersionListFilters,
+};
+use crate::api::{
+    context::validate_dimensions_and_calculate_priority,
+    dimension::{condition_uses_dimension, get_all_dimension_schema_map},
+};
+use crate::db::models::{ConfigVersion, Context, EventLog};
 use crate::db::schema::{
-    contexts::dsl as ctxt, default_configs::dsl as def_conf, event_log::dsl as event_log,
+    config_versions::dsl as config_versions, contexts::dsl as ctxt,
+    default_configs::dsl as def_conf, event_log::dsl as event_log,
 };
 use actix_http::header::{HeaderName, HeaderValue};
-use actix_web::{get, web::Query, HttpRequest, HttpResponse, Scope};
+use actix_web::{
+    get, put,
+    web::{Json, Path, Query},
+    HttpRequest, HttpResponse, Scope,
+};
 use cac_client::{eval_cac, eval_cac_with_reasoning, MergeStrategy};
 use chrono::{DateTime, NaiveDateTime, TimeZone, Timelike, Utc};
 use diesel::{
     dsl::max,
     r2d2::{ConnectionManager, PooledConnection},
-    ExpressionMethods, PgConnection, QueryDsl, RunQueryDsl,
+    Connection, ExpressionMethods, PgConnection, QueryDsl, RunQueryDsl,
 };
 use serde_json::{json, Map, Value};
 use service_utils::service::types::DbConnection;
-use service_utils::{bad_argument, db_error, unexpected_error};
+use service_utils::{bad_argument, db_error, not_found, unexpected_error};
+use superposition_types::{SuperpositionUser, User};
 
 use service_utils::result as superposition;
 use uuid::Uuid;
@@ -30,6 +46,9 @@ pub fn endpoints() -> Scope {
         .service(get)
         .service(get_resolved_config)
         .service(get_filtered_config)
+        .service(list_versions)
+        .service(get_config_diff)
+        .service(rollback)
 }
 
 pub fn add_audit_header(
@@ -98,7 +117,7 @@ fn is_not_modified(max_created_at: Option<NaiveDateTime>, req: &HttpRequest) ->
     max_created_at.is_some() && parsed_max <= last_modified
 }
 
-async fn generate_cac(
+fn generate_cac(
     conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
 ) -> superposition::Result<Config> {
     let contexts_vec = ctxt::contexts
@@ -110,20 +129,6 @@ async fn generate_cac(
             db_error!(err)
         })?;
 
-    let (contexts, overrides) = contexts_vec.into_iter().fold(
-        (Vec::new(), Map::new()),
-        |(mut ctxts, mut overrides), (id, condition, override_id, override_)| {
-            let ctxt = super::types::Context {
-                id,
-                condition,
-                override_with_keys: [override_id.to_owned()],
-            };
-            ctxts.push(ctxt);
-            overrides.insert(override_id, override_);
-            (ctxts, overrides)
-        },
-    );
-
     let default_config_vec = def_conf::default_configs
         .select((def_conf::key, def_conf::value))
         .load::<(String, Value)>(conn)
@@ -132,19 +137,36 @@ async fn generate_cac(
             db_error!(err)
         })?;
 
-    let default_configs =
-        default_config_vec
-            .into_iter()
-            .fold(Map::new(), |mut acc, item| {
-                acc.insert(item.0, item.1);
-                acc
-            });
+    Ok(build_config(contexts_vec, default_config_vec))
+}
 
-    Ok(Config {
-        contexts,
-        overrides,
-        default_configs,
-    })
+fn fetch_config_version(
+    version: i64,
+    conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+) -> superposition::Result<ConfigVersion> {
+    config_versions::config_versions
+        .find(version)
+        .get_result::<ConfigVersion>(conn)
+        .map_err(|err| match err {
+            diesel::NotFound => not_found!("Config version {} doesn't exist", version),
+            err => {
+                log::error!("failed to fetch config version {version}: {err}");
+                db_error!(err)
+            }
+        })
+}
+
+fn add_config_version_header(
+    version: Option<i64>,
+    mut res: HttpResponse,
+) -> superposition::Result<HttpResponse> {
+    let header_name = HeaderName::from_static("x-config-version");
+    if let Some(version) = version {
+        if let Ok(header_value) = HeaderValue::from_str(&version.to_string()) {
+            res.headers_mut().insert(header_name, header_value);
+        }
+    }
+    Ok(res)
 }
 
 #[get("")]
@@ -154,18 +176,6 @@ async fn get(
 ) -> superposition::Result<HttpResponse> {
     let DbConnection(mut conn) = db_conn;
 
-    let max_created_at = get_max_created_at(&mut conn)
-        .map_err(|e| log::error!("failed to fetch max timestamp from event_log: {e}"))
-        .ok();
-
-    log::info!("Max created at: {max_created_at:?}");
-
-    let is_not_modified = is_not_modified(max_created_at, &req);
-
-    if is_not_modified {
-        return Ok(HttpResponse::NotModified().finish());
-    }
-
     let params = Query::<HashMap<String, String>>::from_query(req.query_string())
         .map_err(|err| {
             log::error!("Failed to parse query params with err: {}", err);
@@ -182,7 +192,44 @@ async fn get(
         );
     }
 
-    let mut config = generate_cac(&mut conn).await?;
+    let version = query_params_map
+        .remove("version")
+        .map(|version| {
+            version
+                .as_i64()
+                .ok_or_else(|| bad_argument!("Param 'version' has to be an integer."))
+        })
+        .transpose()?;
+
+    let max_created_at = match version {
+        // versions are immutable, so there is nothing to compare against
+        Some(_) => None,
+        None => {
+            let max_created_at = get_max_created_at(&mut conn)
+                .map_err(|e| {
+                    log::error!("failed to fetch max timestamp from event_log: {e}")
+                })
+                .ok();
+            log::info!("Max created at: {max_created_at:?}");
+            if is_not_modified(max_created_at, &req) {
+                return Ok(HttpResponse::NotModified().finish());
+            }
+            max_created_at
+        }
+    };
+
+    let (mut config, served_version) = match version {
+        Some(version) => {
+            let config_version = fetch_config_version(version, &mut conn)?;
+            (config_at_version(&config_version)?, Some(version))
+        }
+        None => {
+            let latest_version = config_versions::config_versions
+                .select(max(config_versions::id))
+                .first::<Option<i64>>(&mut conn)?;
+            (generate_cac(&mut conn)?, latest_version)
+        }
+    };
     if let Some(prefix) = query_params_map.get("prefix") {
         let prefix_list: HashSet<&str> = prefix
             .as_str()
@@ -203,8 +250,199 @@ async fn get(
 
     let resp = HttpResponse::Ok().json(config);
     let audit_resp = add_audit_header(&mut conn, resp)?;
+    let version_resp = add_config_version_header(served_version, audit_resp)?;
 
-    add_last_modified_header(max_created_at, audit_resp)
+    add_last_modified_header(max_created_at, version_resp)
+}
+
+#[get("/versions")]
+async fn list_versions(
+    filters: Query<VersionListFilters>,
+    db_conn: DbConnection,
+) -> superposition::Result<HttpResponse> {
+    let DbConnection(mut conn) = db_conn;
+
+    let limit = filters.count.unwrap_or(10);
+    let page = filters.page.unwrap_or(1);
+    if page < 1 {
+        return Err(bad_argument!("Param 'page' has to be at least 1."));
+    } else if limit < 1 {
+        return Err(bad_argument!("Param 'count' has to be at least 1."));
+    }
+
+    let version_count: i64 = config_versions::config_versions
+        .count()
+        .get_result(&mut conn)?;
+    let versions = config_versions::config_versions
+        .select((
+            config_versions::id,
+            config_versions::description,
+            config_versions::created_at,
+            config_versions::created_by,
+        ))
+        .order(config_versions::id.desc())
+        .limit(limit)
+        .offset((page - 1) * limit)
+        .load::<(i64, Option<String>, DateTime<Utc>, String)>(&mut conn)?
+        .into_iter()
+        .map(
+            |(version, description, created_at, created_by)| ConfigVersionResponse {
+                version,
+                description,
+                created_at,
+                created_by,
+            },
+        )
+        .collect::<Vec<ConfigVersionResponse>>();
+
+    let total_pages = (version_count as f64 / limit as f64).ceil() as i64;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "total_items": version_count,
+        "total_pages": total_pages,
+        "data": versions
+    })))
+}
+
+/// Resolves a point of a diff, given either as a config version or as a
+/// timestamp.
+fn parse_diff_point(
+    point: &str,
+    conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+) -> superposition::Result<DateTime<Utc>> {
+    if let Ok(version) = point.parse::<i64>() {
+        return fetch_config_version(version, conn).map(|version| version.created_at);
+    }
+    DateTime::parse_from_rfc3339(point)
+        .map(|datetime| datetime.with_timezone(&Utc))
+        .or_else(|_| {
+            NaiveDateTime::from_str(point)
+                .map(|datetime| TimeZone::from_utc_datetime(&Utc, &datetime))
+        })
+        .map_err(|_| {
+            bad_argument!(
+                "`{}` is neither a config version nor a RFC 3339 timestamp",
+                point
+            )
+        })
+}
+
+#[get("/diff")]
+async fn get_config_diff(
+    query: Query<DiffQuery>,
+    db_conn: DbConnection,
+) -> superposition::Result<Json<ConfigDiff>> {
+    let DbConnection(mut conn) = db_conn;
+    let DiffQuery { from, to } = query.into_inner();
+
+    let from = parse_diff_point(&from, &mut conn)?;
+    let to = match to {
+        Some(to) => parse_diff_point(&to, &mut conn)?,
+        None => Utc::now(),
+    };
+    if from > to {
+        return Err(bad_argument!("Param 'from' has to be before 'to'."));
+    }
+
+    let events = event_log::event_log
+        .filter(event_log::table_name.eq_any(vec!["contexts", "default_configs"]))
+        .filter(event_log::timestamp.gt(from.naive_utc()))
+        .filter(event_log::timestamp.le(to.naive_utc()))
+        .order_by(event_log::timestamp.asc())
+        .load::<EventLog>(&mut conn)?;
+    let (default_configs, contexts, overrides) = diff_from_events(&events);
+
+    Ok(Json(ConfigDiff {
+        from,
+        to,
+        default_configs,
+        contexts,
+        overrides,
+    }))
+}
+
+#[put("/versions/{version}/rollback")]
+async fn rollback(
+    path: Path<i64>,
+    query: Query<RollbackQuery>,
+    db_conn: DbConnection,
+    user: User,
+) -> superposition::Result<Json<ConfigVersionResponse>> {
+    let DbConnection(mut conn) = db_conn;
+    let target = path.into_inner();
+    let force = query.into_inner().force;
+    let config_version = fetch_config_version(target, &mut conn)?;
+    let (contexts, default_configs) = parse_config_version(&config_version)?;
+    let description = format!("Rollback to version {target}");
+
+    let version = conn.transaction::<_, superposition::AppError, _>(|conn| {
+        // dimensions are not versioned, so the restored contexts are checked
+        // against, and prioritised by, the dimensions as they are now
+        let dimension_schema_map = get_all_dimension_schema_map(conn)?;
+        let contexts = contexts
+            .into_iter()
+            .map(|context| {
+                let priority = validate_dimensions_and_calculate_priority(
+                    "context",
+                    &context.value,
+                    &dimension_schema_map,
+                )?;
+                Ok(Context {
+                    priority,
+                    ..context
+                })
+            })
+            .collect::<superposition::Result<Vec<Context>>>()?;
+
+        if !force {
+            let restored_ids: HashSet<&str> =
+                contexts.iter().map(|context| context.id.as_str()).collect();
+            let removed_experiment_contexts = ctxt::contexts
+                .load::<Context>(conn)?
+                .into_iter()
+                .filter(|context| {
+                    !restored_ids.contains(context.id.as_str())
+                        && condition_uses_dimension(&context.value, "variantIds")
+                })
+                .map(|context| context.id)
+                .collect::<Vec<String>>();
+            if !removed_experiment_contexts.is_empty() {
+                return Err(bad_argument!(
+                    "rolling back to version {} removes the contexts {} of running experiments, pass force=true to roll back anyway",
+                    target,
+                    removed_experiment_contexts.join(", ")
+                ));
+            }
+        }
+
+        diesel::delete(ctxt::contexts).execute(conn)?;
+        diesel::delete(def_conf::default_configs).execute(conn)?;
+        diesel::insert_into(def_conf::default_configs)
+            .values(&default_configs)
+            .execute(conn)?;
+        diesel::insert_into(ctxt::contexts)
+            .values(&contexts)
+            .execute(conn)?;
+        add_config_version(conn, Some(description), &user)
+    })?;
+    log::info!(
+        "config rolled back to version {target} by {}, new version {version}",
+        user.get_email()
+    );
+
+    let ConfigVersion {
+        id,
+        description,
+        created_at,
+        created_by,
+        ..
+    } = fetch_config_version(version, &mut conn)?;
+    Ok(Json(ConfigVersionResponse {
+        version: id,
+        description,
+        created_at,
+        created_by,
+    }))
 }
 
 #[get("/resolve")]
@@ -240,7 +478,7 @@ async fn get_resolved_config(
         return Ok(HttpResponse::NotModified().finish());
     }
 
-    let res = generate_cac(&mut conn).await?;
+    let res = generate_cac(&mut conn)?;
 
     let cac_client_contexts = res
         .contexts
@@ -313,7 +551,7 @@ async fn get_filtered_config(
                 .map_or_else(|_| json!(value), |int_val| json!(int_val)),
         );
     }
-    let config = generate_cac(&mut conn).await?;
+    let config = generate_cac(&mut conn)?;
     let contexts = config.contexts;
 
     let filtered_context = filter_context(&contexts, &query_params_map)?;
@@ -1,11 +1,24 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 
-use super::types::{Config, Context};
+use super::types::{ChangeSet, Config, Context, DiffEntry};
+use crate::db::{
+    models::{self, ConfigVersion, DefaultConfig, EventLog},
+    schema::{
+        config_versions::dsl as cv, contexts::dsl as ctxt,
+        default_configs::dsl as def_conf,
+    },
+};
 
-use serde_json::{Map, Value};
-use service_utils::{
-    helpers::extract_dimensions, result as superposition, unexpected_error,
+use chrono::Utc;
+use diesel::{
+    r2d2::{ConnectionManager, PooledConnection},
+    Connection, ExpressionMethods, PgConnection, QueryDsl, RunQueryDsl,
 };
+use serde_json::{Map, Value};
+use service_utils::{helpers::parse_context, result as superposition, unexpected_error};
+use superposition_types::{SuperpositionUser, User};
+
+type DBConnection = PooledConnection<ConnectionManager<PgConnection>>;
 
 pub fn filter_context(
     contexts: &Vec<Context>,
@@ -24,12 +37,9 @@ fn should_add_ctx(
     context: &Context,
     query_params_map: &Map<String, Value>,
 ) -> superposition::Result<bool> {
-    let dimension = extract_dimensions(&context.condition)?;
-    Ok(dimension.iter().all(|(key, value)| {
-        query_params_map.get(key).map_or(true, |val| {
-            val == value || val.as_array().unwrap_or(&vec![]).contains(value)
-        })
-    }))
+    // contexts that may still apply, depending on dimensions not given, are kept
+    let expression = parse_context(&context.condition)?;
+    Ok(expression.evaluate(query_params_map) != Some(false))
 }
 
 pub fn filter_config_by_prefix(
@@ -108,3 +118,373 @@ pub fn filter_config_by_dimensions(
 
     Ok(filtered_config)
 }
+
+/// Builds the served configuration out of context rows, given in the order
+/// they are evaluated, and default config key/value pairs.
+pub fn build_config(
+    contexts: Vec<(String, Value, String, Value)>,
+    default_configs: Vec<(String, Value)>,
+) -> Config {
+    let (contexts, overrides) = contexts.into_iter().fold(
+        (Vec::new(), Map::new()),
+        |(mut ctxts, mut overrides), (id, condition, override_id, override_)| {
+            let ctxt = Context {
+                id,
+                condition,
+                override_with_keys: [override_id.to_owned()],
+            };
+            ctxts.push(ctxt);
+            overrides.insert(override_id, override_);
+            (ctxts, overrides)
+        },
+    );
+
+    let default_configs =
+        default_configs
+            .into_iter()
+            .fold(Map::new(), |mut acc, item| {
+                acc.insert(item.0, item.1);
+                acc
+            });
+
+    Config {
+        contexts,
+        overrides,
+        default_configs,
+    }
+}
+
+/// Reads back the context and default config rows stored in a version.
+pub fn parse_config_version(
+    version: &ConfigVersion,
+) -> superposition::Result<(Vec<models::Context>, Vec<DefaultConfig>)> {
+    let contexts =
+        serde_json::from_value::<Vec<models::Context>>(version.contexts.clone());
+    let default_configs =
+        serde_json::from_value::<Vec<DefaultConfig>>(version.default_configs.clone());
+    match (contexts, default_configs) {
+        (Ok(contexts), Ok(default_configs)) => Ok((contexts, default_configs)),
+        (Err(err), _) | (_, Err(err)) => {
+            log::error!("failed to parse config version {}: {}", version.id, err);
+            Err(unexpected_error!("Something went wrong"))
+        }
+    }
+}
+
+/// Builds the configuration that was served at `version`.
+pub fn config_at_version(version: &ConfigVersion) -> superposition::Result<Config> {
+    let (contexts, default_configs) = parse_config_version(version)?;
+    Ok(build_config(
+        contexts
+            .into_iter()
+            .map(|ctx| (ctx.id, ctx.value, ctx.override_id, ctx.override_))
+            .collect(),
+        default_configs
+            .into_iter()
+            .map(|default_config| (default_config.key, default_config.value))
+            .collect(),
+    ))
+}
+
+/// Records the current contexts and default configs as a new, immutable
+/// config version and returns its number. It has to run on the connection
+/// that made the change, inside the same transaction where there is one, so
+/// that the version never misses or outlives the change it records.
+pub fn add_config_version(
+    conn: &mut DBConnection,
+    description: Option<String>,
+    user: &User,
+) -> superposition::Result<i64> {
+    conn.transaction(|conn| snapshot_config_version(conn, description, user))
+}
+
+fn snapshot_config_version(
+    conn: &mut DBConnection,
+    description: Option<String>,
+    user: &User,
+) -> superposition::Result<i64> {
+    // versions are taken one at a time, held until the change being recorded
+    // commits. Otherwise two concurrent changes could each snapshot the rows
+    // without the other's change, leaving the later version without the
+    // change the earlier one recorded
+    diesel::sql_query("LOCK TABLE config_versions IN EXCLUSIVE MODE").execute(conn)?;
+    let contexts = ctxt::contexts
+        .order_by((ctxt::priority.asc(), ctxt::created_at.asc()))
+        .load::<models::Context>(conn)?;
+    let default_configs = def_conf::default_configs
+        .order_by(def_conf::key.asc())
+        .load::<DefaultConfig>(conn)?;
+
+    let (contexts, default_configs) = serde_json::to_value(contexts)
+        .and_then(|contexts| Ok((contexts, serde_json::to_value(default_configs)?)))
+        .map_err(|err| {
+            log::error!("failed to serialize config version: {}", err);
+            unexpected_error!("Something went wrong")
+        })?;
+
+    let version = diesel::insert_into(cv::config_versions)
+        .values((
+            cv::contexts.eq(contexts),
+            cv::default_configs.eq(default_configs),
+            cv::description.eq(description),
+            cv::created_at.eq(Utc::now()),
+            cv::created_by.eq(user.get_email()),
+        ))
+        .returning(cv::id)
+        .get_result::<i64>(conn)?;
+    log::info!("config version {version} created by {}", user.get_email());
+    Ok(version)
+}
+
+type RowChange = (Option<Value>, Option<Value>);
+type RowTransitions = (Vec<Option<Value>>, Vec<Option<Value>>);
+
+/// Works out the net change of every row of `table` touched by `events`, as
+/// the row before the first and after the last event, keyed by the `key`
+/// column. Events written in one transaction share a timestamp, so instead of
+/// relying on their order the states each row moved out of and into are
+/// matched against each other, leaving only where the row started and ended.
+fn net_row_changes(
+    events: &[EventLog],
+    table: &str,
+    key: &str,
+) -> BTreeMap<String, RowChange> {
+    let row_key = |row: &Option<Value>| {
+        row.as_ref()
+            .and_then(|row| row.get(key))
+            .and_then(Value::as_str)
+            .map(String::from)
+    };
+
+    let mut transitions: BTreeMap<String, RowTransitions> = BTreeMap::new();
+    for event in events.iter().filter(|event| event.table_name == table) {
+        let mut record = |key: String, old: Option<Value>, new: Option<Value>| {
+            let (olds, news) = transitions.entry(key).or_default();
+            olds.push(old);
+            news.push(new);
+        };
+        match (row_key(&event.original_data), row_key(&event.new_data)) {
+            (Some(old_key), Some(new_key)) if old_key == new_key => {
+                record(old_key, event.original_data.clone(), event.new_data.clone())
+            }
+            // a row whose key changed leaves its old key and shows up at the new one
+            (old_key, new_key) => {
+                if let Some(old_key) = old_key {
+                    record(old_key, event.original_data.clone(), None);
+                }
+                if let Some(new_key) = new_key {
+                    record(new_key, None, event.new_data.clone());
+                }
+            }
+        }
+    }
+
+    transitions
+        .into_iter()
+        .filter_map(|(key, (mut olds, news))| {
+            let mut unmatched_news = Vec::new();
+            for new in news {
+                match olds.iter().position(|old| *old == new) {
+                    Some(idx) => {
+                        olds.swap_remove(idx);
+                    }
+                    None => unmatched_news.push(new),
+                }
+            }
+            match (olds.into_iter().next(), unmatched_news.into_iter().next()) {
+                (Some(before), Some(after)) => Some((key, (before, after))),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+fn change_set<F>(changes: &BTreeMap<String, RowChange>, view: F) -> ChangeSet
+where
+    F: Fn(&Value) -> Option<Value>,
+{
+    let mut change_set = ChangeSet::default();
+    for (id, (before, after)) in changes {
+        let before = before.as_ref().and_then(&view);
+        let after = after.as_ref().and_then(&view);
+        let entry = DiffEntry {
+            id: id.to_owned(),
+            before: before.clone(),
+            after: after.clone(),
+        };
+        match (before, after) {
+            (None, Some(_)) => change_set.added.push(entry),
+            (Some(_), None) => change_set.removed.push(entry),
+            (Some(before), Some(after)) if before != after => {
+                change_set.modified.push(entry)
+            }
+            _ => (),
+        }
+    }
+    change_set
+}
+
+/// Reconstructs what changed in default configs, contexts and overrides from
+/// the audit `events` of a period.
+pub fn diff_from_events(events: &[EventLog]) -> (ChangeSet, ChangeSet, ChangeSet) {
+    let default_config_changes = net_row_changes(events, "default_configs", "key");
+    let context_changes = net_row_changes(events, "contexts", "id");
+
+    let default_configs = change_set(&default_config_changes, |row| {
+        let mut row = row.as_object()?.clone();
+        for audit_field in ["key", "created_at", "created_by"] {
+            row.remove(audit_field);
+        }
+        Some(Value::Object(row))
+    });
+    let (contexts, overrides) = context_change_sets(&context_changes);
+    (default_configs, contexts, overrides)
+}
+
+fn context_change_sets(changes: &BTreeMap<String, RowChange>) -> (ChangeSet, ChangeSet) {
+    (
+        change_set(changes, |row| row.get("value").cloned()),
+        change_set(changes, |row| row.get("override").cloned()),
+    )
+}
+
+/// Compares two states of the contexts table, returning the contexts and the
+/// overrides that differ between them.
+pub fn diff_contexts(
+    before: &[models::Context],
+    after: &[models::Context],
+) -> superposition::Result<(ChangeSet, ChangeSet)> {
+    let to_rows = |contexts: &[models::Context]| {
+        contexts
+            .iter()
+            .map(|context| Ok((context.id.clone(), serde_json::to_value(context)?)))
+            .collect::<serde_json::Result<BTreeMap<String, Value>>>()
+            .map_err(|err| {
+                log::error!("failed to serialize contexts: {}", err);
+                unexpected_error!("Something went wrong")
+            })
+    };
+    let (mut before, after) = (to_rows(before)?, to_rows(after)?);
+
+    let mut changes: BTreeMap<String, RowChange> = BTreeMap::new();
+    for (id, row) in after {
+        let previous = before.remove(&id);
+        changes.insert(id, (previous, Some(row)));
+    }
+    for (id, row) in before {
+        changes.insert(id, (Some(row), None));
+    }
+    Ok(context_change_sets(&changes))
+}
+
+// ************ Tests *************
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDateTime;
+    use serde_json::json;
+
+    fn context_row(id: &str, value: &str) -> Value {
+        json!({
+            "id": id,
+            "value": {"==": [{"var": "os"}, "android"]},
+            "override_id": format!("override-{value}"),
+            "override": {"key": value},
+            "priority": 10
+        })
+    }
+
+    // events written in one transaction share a timestamp
+    fn event(original_data: Option<Value>, new_data: Option<Value>) -> EventLog {
+        let action = match (&original_data, &new_data) {
+            (None, _) => "INSERT",
+            (_, None) => "DELETE",
+            _ => "UPDATE",
+        };
+        EventLog {
+            id: uuid::Uuid::new_v4(),
+            table_name: "contexts".to_string(),
+            user_name: "postgres".to_string(),
+            timestamp: NaiveDateTime::default(),
+            action: action.to_string(),
+            original_data,
+            new_data,
+            query: String::new(),
+        }
+    }
+
+    fn net_context_changes(events: &[EventLog]) -> Vec<(String, RowChange)> {
+        net_row_changes(events, "contexts", "id")
+            .into_iter()
+            .collect()
+    }
+
+    #[test]
+    fn test_insert_then_update_is_an_insert() {
+        let (a, b) = (context_row("ctx", "a"), context_row("ctx", "b"));
+        let mut events = vec![
+            event(None, Some(a.clone())),
+            event(Some(a.clone()), Some(b.clone())),
+        ];
+        let expected = vec![("ctx".to_string(), (None, Some(b)))];
+        assert_eq!(net_context_changes(&events), expected);
+
+        events.reverse();
+        assert_eq!(net_context_changes(&events), expected);
+    }
+
+    #[test]
+    fn test_change_and_change_back_is_no_change() {
+        let (a, b) = (context_row("ctx", "a"), context_row("ctx", "b"));
+        let mut events = vec![
+            event(Some(a.clone()), Some(b.clone())),
+            event(Some(b), Some(a)),
+        ];
+        assert_eq!(net_context_changes(&events), vec![]);
+
+        events.reverse();
+        assert_eq!(net_context_changes(&events), vec![]);
+    }
+
+    #[test]
+    fn test_delete_then_insert_is_an_update() {
+        let (a, b) = (context_row("ctx", "a"), context_row("ctx", "b"));
+        let mut events = vec![event(Some(a.clone()), None), event(None, Some(b.clone()))];
+        let expected = vec![("ctx".to_string(), (Some(a.clone()), Some(b)))];
+        assert_eq!(net_context_changes(&events), expected);
+
+        events.reverse();
+        assert_eq!(net_context_changes(&events), expected);
+
+        // putting the same row back is no change at all
+        let events = vec![event(Some(a.clone()), None), event(None, Some(a))];
+        assert_eq!(net_context_changes(&events), vec![]);
+    }
+
+    #[test]
+    fn test_move_removes_the_old_id_and_adds_the_new_one() {
+        let (before, after) = (context_row("old", "a"), context_row("new", "a"));
+        let events = vec![event(Some(before.clone()), Some(after.clone()))];
+        assert_eq!(
+            net_context_changes(&events),
+            vec![
+                ("new".to_string(), (None, Some(after))),
+                ("old".to_string(), (Some(before), None)),
+            ]
+        );
+
+        let (_, contexts, overrides) = diff_from_events(&events);
+        let ids = |entries: &[DiffEntry]| {
+            entries
+                .iter()
+                .map(|entry| entry.id.clone())
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(ids(&contexts.added), vec!["new"]);
+        assert_eq!(ids(&contexts.removed), vec!["old"]);
+        assert!(contexts.modified.is_empty());
+        assert_eq!(ids(&overrides.added), vec!["new"]);
+        assert_eq!(ids(&overrides.removed), vec!["old"]);
+    }
+}
@@ -2,3 +2,5 @@ mod handlers;
 mod types;
 pub use handlers::endpoints;
 mod helpers;
+pub use helpers::{add_config_version, diff_contexts};
+pub use types::ChangeSet;
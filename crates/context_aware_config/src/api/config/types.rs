@@ -1,4 +1,5 @@
-use serde::Serialize;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
 
 #[derive(Serialize)]
@@ -14,3 +15,53 @@ pub struct Context {
     pub condition: Value,
     pub override_with_keys: [String; 1],
 }
+
+#[derive(Deserialize)]
+pub struct VersionListFilters {
+    pub page: Option<i64>,
+    pub count: Option<i64>,
+}
+
+#[derive(Deserialize)]
+pub struct RollbackQuery {
+    // rolls back even if contexts of running experiments would be removed
+    #[serde(default)]
+    pub force: bool,
+}
+
+#[derive(Serialize)]
+pub struct ConfigVersionResponse {
+    pub version: i64,
+    pub description: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub created_by: String,
+}
+
+#[derive(Deserialize)]
+pub struct DiffQuery {
+    pub from: String,
+    pub to: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct DiffEntry {
+    pub id: String,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+#[derive(Serialize, Default)]
+pub struct ChangeSet {
+    pub added: Vec<DiffEntry>,
+    pub removed: Vec<DiffEntry>,
+    pub modified: Vec<DiffEntry>,
+}
+
+#[derive(Serialize)]
+pub struct ConfigDiff {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub default_configs: ChangeSet,
+    pub contexts: ChangeSet,
+    pub overrides: ChangeSet,
+}
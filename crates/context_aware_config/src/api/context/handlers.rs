@@ -3,12 +3,16 @@ use std::str;
 
 use crate::helpers::{
     calculate_context_priority, json_to_sorted_string, validate_context_jsonschema,
+    validate_if_match,
 };
 use crate::{
     api::{
+        config::{add_config_version, diff_contexts},
         context::types::{
-            ContextAction, ContextBulkResponse, DimensionCondition, MoveReq,
-            PaginationParams, PriorityRecomputeResponse, PutReq, PutResp,
+            BulkOperationsDryRunResponse, BulkOperationsQuery, ContextAction,
+            ContextAnalysis, ContextBulkResponse, ContextSortOn, DimensionCondition,
+            ListContextFilters, ListContextsResponse, MoveReq, PriorityRecomputeResponse,
+            PutReq, PutResp, SortBy,
         },
         dimension::get_all_dimension_schema_map,
     },
@@ -21,17 +25,23 @@ use crate::{
     },
 };
 use actix_web::{
-    delete, get, put,
+    delete, get,
+    http::header::{self, ETag, EntityTag},
+    put,
     web::{Json, Path, Query},
-    HttpResponse, Responder, Scope,
+    HttpRequest, HttpResponse, Responder, Scope,
 };
 use chrono::Utc;
 use diesel::{
     delete,
+    dsl::sql,
+    pg::Pg,
     r2d2::{ConnectionManager, PooledConnection},
     result::{DatabaseErrorKind::*, Error::DatabaseError},
+    sql_types::{Array, Bool, Jsonb, Text},
     upsert::excluded,
-    Connection, ExpressionMethods, PgConnection, QueryDsl, RunQueryDsl,
+    BoxableExpression, Connection, ExpressionMethods, OptionalExtension, PgConnection,
+    QueryDsl, RunQueryDsl,
 };
 use jsonschema::{Draft, JSONSchema, ValidationError};
 use serde_json::{from_value, json, Map, Value};
@@ -42,7 +52,7 @@ use std::collections::HashMap;
 use superposition_types::{SuperpositionUser, User};
 
 use super::helpers::{
-    validate_condition_with_functions, validate_override_with_functions,
+    analyze_contexts, validate_condition_with_functions, validate_override_with_functions,
 };
 
 use service_utils::{bad_argument, result as superposition};
@@ -55,13 +65,14 @@ pub fn endpoints() -> Scope {
         .service(delete_context)
         .service(bulk_operations)
         .service(list_contexts)
+        .service(get_context_analysis)
         .service(get_context)
         .service(priority_recompute)
 }
 
 type DBConnection = PooledConnection<ConnectionManager<PgConnection>>;
 
-fn validate_dimensions_and_calculate_priority(
+pub fn validate_dimensions_and_calculate_priority(
     object_key: &str,
     cond: &Value,
     dimension_schema_map: &HashMap<String, (JSONSchema, i32)>,
@@ -89,25 +100,21 @@ fn validate_dimensions_and_calculate_priority(
             get_priority(key, val).map(|res| res + acc)
         }),
         Value::Array(arr) => {
-            let mut val: Option<Value> = None;
+            // every operand besides the dimension is a value to compare
+            // against, e.g. both bounds of a `between`
+            let mut vals: Vec<&Value> = Vec::new();
             let mut condition: Option<DimensionCondition> = None;
             for i in arr {
                 if let (None, Ok(x)) =
                     (&condition, from_value::<DimensionCondition>(json!(i)))
                 {
                     condition = Some(x);
-                } else if val == None {
-                    val = Some(i.clone());
-                }
-
-                if let (Some(_dimension_value), Some(_dimension_condition)) =
-                    (&val, &condition)
-                {
-                    break;
+                } else {
+                    vals.push(i);
                 }
             }
 
-            if let (Some(dimension_value), Some(dimension_condition)) = (val, condition) {
+            if let Some(dimension_condition) = condition {
                 let expected_dimension_name = dimension_condition.var;
                 let (dimension_value_schema, _) = dimension_schema_map
                     .get(&expected_dimension_name)
@@ -116,20 +123,31 @@ fn validate_dimensions_and_calculate_priority(
                         expected_dimension_name
                     ))?;
 
-                validate_context_jsonschema(
-                    object_key,
-                    &dimension_value,
-                    &dimension_value_schema,
-                )?;
+                for dimension_value in vals {
+                    validate_context_jsonschema(
+                        object_key,
+                        dimension_value,
+                        dimension_value_schema,
+                    )?;
+                }
+            }
+            let priorities = arr
+                .iter()
+                .map(|item| {
+                    validate_dimensions_and_calculate_priority(
+                        object_key,
+                        item,
+                        dimension_schema_map,
+                    )
+                })
+                .collect::<superposition::Result<Vec<i32>>>()?;
+            // an `or` applies as soon as any of its branches does, so it is
+            // only as specific as its least specific branch
+            if object_key == "or" {
+                Ok(priorities.into_iter().min().unwrap_or_default())
+            } else {
+                Ok(priorities.into_iter().sum())
             }
-            arr.iter().try_fold(0, |acc, item| {
-                validate_dimensions_and_calculate_priority(
-                    object_key,
-                    item,
-                    dimension_schema_map,
-                )
-                .map(|res| res + acc)
-            })
         }
         _ => Ok(0),
     }
@@ -181,7 +199,9 @@ fn validate_override_with_default_configs(
     Ok(())
 }
 
-fn create_ctx_from_put_req(
+/// Validates a context and its overrides against the dimensions, default
+/// configs and functions, building the row to be stored for it.
+pub fn create_ctx_from_put_req(
     req: Json<PutReq>,
     conn: &mut DBConnection,
     user: &User,
@@ -217,6 +237,26 @@ fn create_ctx_from_put_req(
     })
 }
 
+/// Checks the `If-Match` header of `req` against the `override_id` of the
+/// context, which serves as its entity tag. The row is locked so that it
+/// cannot change before the caller's transaction ends.
+fn validate_context_if_match(
+    req: &HttpRequest,
+    ctx_id: &str,
+    conn: &mut DBConnection,
+) -> superposition::Result<()> {
+    if !req.headers().contains_key(header::IF_MATCH) {
+        return Ok(());
+    }
+    let current_tag = contexts::dsl::contexts
+        .filter(id.eq(ctx_id))
+        .select(contexts::override_id)
+        .for_update()
+        .first::<String>(conn)
+        .optional()?;
+    validate_if_match(req, current_tag.as_deref())
+}
+
 fn hash(val: &Value) -> String {
     let sorted_str: String = json_to_sorted_string(val);
     blake3::hash(sorted_str.as_bytes()).to_string()
@@ -303,11 +343,19 @@ fn put(
 
 #[put("")]
 async fn put_handler(
+    http_req: HttpRequest,
     req: Json<PutReq>,
     mut db_conn: DbConnection,
     user: User,
 ) -> superposition::Result<Json<PutResp>> {
-    put(req, &mut db_conn, false, &user)
+    db_conn
+        .transaction::<_, superposition::AppError, _>(|conn| {
+            let ctx_id = hash(&Value::Object(req.context.clone()));
+            validate_context_if_match(&http_req, &ctx_id, conn)?;
+            let resp = put(req, conn, true, &user)?;
+            add_config_version(conn, None, &user)?;
+            Ok(resp)
+        })
         .map(|resp| Json(resp))
         .map_err(|err: superposition::AppError| {
             log::info!("context put failed with error: {:?}", err);
@@ -318,16 +366,23 @@ async fn put_handler(
 fn override_helper(
     req: Json<PutReq>,
     conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+    already_under_txn: bool,
     user: &User,
 ) -> superposition::Result<Json<PutResp>> {
     use contexts::dsl::contexts;
     let new_ctx = create_ctx_from_put_req(req, conn, user)?;
 
+    if already_under_txn {
+        diesel::sql_query("SAVEPOINT override_ctx_savepoint").execute(conn)?;
+    }
     let insert = diesel::insert_into(contexts).values(&new_ctx).execute(conn);
 
     match insert {
         Ok(_) => Ok(Json(get_put_resp(new_ctx))),
         Err(DatabaseError(UniqueViolation, _)) => {
+            if already_under_txn {
+                diesel::sql_query("ROLLBACK TO override_ctx_savepoint").execute(conn)?;
+            }
             replace_override_of_existing_ctx(conn, new_ctx) // no need for .map(Json)
         }
         Err(e) => {
@@ -339,14 +394,23 @@ fn override_helper(
 
 #[put("/overrides")]
 async fn update_override_handler(
+    http_req: HttpRequest,
     req: Json<PutReq>,
     mut db_conn: DbConnection,
     user: User,
 ) -> superposition::Result<Json<PutResp>> {
-    override_helper(req, &mut db_conn, &user).map_err(|err: superposition::AppError| {
-        log::info!("context put failed with error: {:?}", err);
-        err
-    })
+    db_conn
+        .transaction::<_, superposition::AppError, _>(|conn| {
+            let ctx_id = hash(&Value::Object(req.context.clone()));
+            validate_context_if_match(&http_req, &ctx_id, conn)?;
+            let resp = override_helper(req, conn, true, &user)?;
+            add_config_version(conn, None, &user)?;
+            Ok(resp)
+        })
+        .map_err(|err: superposition::AppError| {
+            log::info!("context put failed with error: {:?}", err);
+            err
+        })
 }
 
 fn r#move(
@@ -431,12 +495,20 @@ fn r#move(
 
 #[put("/move/{ctx_id}")]
 async fn move_handler(
+    http_req: HttpRequest,
     path: Path<String>,
     req: Json<MoveReq>,
     mut db_conn: DbConnection,
     user: User,
 ) -> superposition::Result<Json<PutResp>> {
-    r#move(path.into_inner(), req, &mut db_conn, false, &user)
+    db_conn
+        .transaction::<_, superposition::AppError, _>(|conn| {
+            let ctx_id = path.into_inner();
+            validate_context_if_match(&http_req, &ctx_id, conn)?;
+            let resp = r#move(ctx_id, req, conn, true, &user)?;
+            add_config_version(conn, None, &user)?;
+            Ok(resp)
+        })
         .map(|resp| Json(resp))
         .map_err(|err| {
             log::info!("move api failed with error: {:?}", err);
@@ -444,6 +516,25 @@ async fn move_handler(
         })
 }
 
+#[get("/analysis")]
+async fn get_context_analysis(
+    db_conn: DbConnection,
+) -> superposition::Result<Json<ContextAnalysis>> {
+    use contexts::dsl as ctxt;
+    let DbConnection(mut conn) = db_conn;
+
+    let contexts = ctxt::contexts
+        .order_by((ctxt::priority.asc(), ctxt::created_at.asc()))
+        .load::<Context>(&mut conn)?;
+    let default_configs = dsl::default_configs
+        .select((dsl::key, dsl::value))
+        .load::<(String, Value)>(&mut conn)?
+        .into_iter()
+        .collect::<Map<String, Value>>();
+
+    Ok(Json(analyze_contexts(&contexts, &default_configs)))
+}
+
 #[get("/{ctx_id}")]
 async fn get_context(
     path: Path<String>,
@@ -458,43 +549,146 @@ async fn get_context(
         .filter(id.eq(ctx_id))
         .get_result::<Context>(&mut conn)?;
 
-    Ok(Json(ctx))
+    Ok(HttpResponse::Ok()
+        .insert_header(ETag(EntityTag::new_strong(ctx.override_id.clone())))
+        .json(ctx))
 }
 
 #[get("/list")]
 async fn list_contexts(
-    qparams: Query<PaginationParams>,
+    filters: Query<ListContextFilters>,
     db_conn: DbConnection,
-) -> superposition::Result<impl Responder> {
-    use crate::db::schema::contexts::dsl::*;
+) -> superposition::Result<Json<ListContextsResponse>> {
+    use crate::db::schema::contexts::dsl as ctxt;
     let DbConnection(mut conn) = db_conn;
+    let filters = filters.into_inner();
 
-    let PaginationParams {
-        page: opt_page,
-        size: opt_size,
-    } = qparams.into_inner();
-    let default_page = 1;
-    let page = opt_page.unwrap_or(default_page);
-    let default_size = 20;
-    let size = opt_size.unwrap_or(default_size);
-
+    let page = filters.page.unwrap_or(1);
+    let size = filters.size.unwrap_or(20);
     if page < 1 {
         return Err(bad_argument!("Param 'page' has to be at least 1."));
     } else if size < 1 {
         return Err(bad_argument!("Param 'size' has to be at least 1."));
     }
 
-    let result: Vec<Context> = contexts
-        .order(created_at)
+    let dimensions = filters
+        .dimensions
+        .clone()
+        .map(|dimensions| {
+            dimensions
+                .0
+                .iter()
+                .map(|dimension| parse_dimension_filter(dimension))
+                .collect::<Vec<(String, Option<Value>)>>()
+        })
+        .unwrap_or_default();
+
+    let query_builder = |filters: &ListContextFilters| {
+        let mut builder = ctxt::contexts.into_boxed();
+        if let Some(created_by) = filters.created_by.clone() {
+            builder = builder.filter(ctxt::created_by.eq(created_by));
+        }
+        if let Some(min_priority) = filters.min_priority {
+            builder = builder.filter(ctxt::priority.ge(min_priority));
+        }
+        if let Some(max_priority) = filters.max_priority {
+            builder = builder.filter(ctxt::priority.le(max_priority));
+        }
+        if let Some(keys) = filters.override_keys.clone() {
+            builder = builder.filter(overrides_keys(keys.0));
+        }
+        for (dimension, value) in dimensions.iter() {
+            builder = builder.filter(condition_has_dimension(dimension, value));
+        }
+        builder
+    };
+    let base_query = query_builder(&filters);
+    let count_query = query_builder(&filters);
+
+    let base_query = match (
+        filters.sort_on.unwrap_or_default(),
+        filters.sort_by.unwrap_or_default(),
+    ) {
+        (ContextSortOn::CreatedAt, SortBy::Asc) => {
+            base_query.order((ctxt::created_at.asc(), ctxt::id.asc()))
+        }
+        (ContextSortOn::CreatedAt, SortBy::Desc) => {
+            base_query.order((ctxt::created_at.desc(), ctxt::id.asc()))
+        }
+        (ContextSortOn::Priority, SortBy::Asc) => {
+            base_query.order((ctxt::priority.asc(), ctxt::created_at.asc()))
+        }
+        (ContextSortOn::Priority, SortBy::Desc) => {
+            base_query.order((ctxt::priority.desc(), ctxt::created_at.asc()))
+        }
+    };
+
+    let total_items: i64 = count_query.count().get_result(&mut conn)?;
+    let data: Vec<Context> = base_query
         .limit(i64::from(size))
-        .offset(i64::from(size * (page - 1)))
+        .offset(i64::from(size) * (i64::from(page) - 1))
         .load(&mut conn)?;
+    let total_pages = (total_items as f64 / f64::from(size)).ceil() as i64;
 
-    Ok(Json(result))
+    Ok(Json(ListContextsResponse {
+        total_pages,
+        total_items,
+        data,
+    }))
+}
+
+fn parse_dimension_filter(filter: &str) -> (String, Option<Value>) {
+    match filter.split_once('=') {
+        // values are compared as JSON so that numbers and booleans in the
+        // condition match, anything that isn't valid JSON is a string
+        Some((dimension, value)) => (
+            dimension.to_string(),
+            Some(
+                serde_json::from_str(value)
+                    .unwrap_or_else(|_| Value::String(value.to_string())),
+            ),
+        ),
+        None => (filter.to_string(), None),
+    }
+}
+
+// Matches contexts with a condition on `dimension`, and when given whose
+// operands include `value`, be it an `==` comparison or one of the values of
+// an `in`. The path is strict as lax mode unwraps the arrays being looked for
+fn condition_has_dimension(
+    dimension: &str,
+    value: &Option<Value>,
+) -> Box<dyn BoxableExpression<contexts::table, Pg, SqlType = Bool>> {
+    match value {
+        Some(value) => Box::new(
+            sql::<Bool>(
+                "jsonb_path_exists(value::jsonb, 'strict $.** ? (@.type() == \"array\" && exists(@[*] ? (@.var == $name)) && exists(@[*] ? (@ == $value || (@.type() == \"array\" && exists(@[*] ? (@ == $value))))))', jsonb_build_object('name', ",
+            )
+            .bind::<Text, _>(dimension.to_string())
+            .sql(", 'value', ")
+            .bind::<Jsonb, _>(value.clone())
+            .sql("))"),
+        ),
+        None => Box::new(
+            sql::<Bool>(
+                "jsonb_path_exists(value::jsonb, '$.** ? (@.var == $name)', jsonb_build_object('name', ",
+            )
+            .bind::<Text, _>(dimension.to_string())
+            .sql("))"),
+        ),
+    }
+}
+
+// Matches contexts overriding every one of `keys`
+fn overrides_keys(
+    keys: Vec<String>,
+) -> Box<dyn BoxableExpression<contexts::table, Pg, SqlType = Bool>> {
+    Box::new(sql::<Bool>("\"override\"::jsonb ?& ").bind::<Array<Text>, _>(keys))
 }
 
 #[delete("/{ctx_id}")]
 async fn delete_context(
+    http_req: HttpRequest,
     path: Path<String>,
     db_conn: DbConnection,
     user: User,
@@ -503,36 +697,100 @@ async fn delete_context(
     let DbConnection(mut conn) = db_conn;
 
     let ctx_id = path.into_inner();
-    let deleted_row =
-        delete(dsl::contexts.filter(dsl::id.eq(&ctx_id))).execute(&mut conn);
-    match deleted_row {
-        Ok(0) => Err(not_found!("Context Id `{}` doesn't exists", ctx_id)),
-        Ok(_) => {
-            log::info!("{ctx_id} context deleted by {}", user.get_email());
-            Ok(HttpResponse::NoContent().finish())
-        }
-        Err(e) => {
-            log::error!("context delete query failed with error: {e}");
-            Err(unexpected_error!("Something went wrong."))
+    conn.transaction::<_, superposition::AppError, _>(|conn| {
+        validate_context_if_match(&http_req, &ctx_id, conn)?;
+        let deleted_row = delete(dsl::contexts.filter(dsl::id.eq(&ctx_id))).execute(conn);
+        match deleted_row {
+            Ok(0) => Err(not_found!("Context Id `{}` doesn't exists", ctx_id)),
+            Ok(_) => {
+                log::info!("{ctx_id} context deleted by {}", user.get_email());
+                add_config_version(conn, None, &user)?;
+                Ok(HttpResponse::NoContent().finish())
+            }
+            Err(e) => {
+                log::error!("context delete query failed with error: {e}");
+                Err(unexpected_error!("Something went wrong."))
+            }
         }
-    }
+    })
 }
 
 #[put("/bulk-operations")]
 async fn bulk_operations(
     reqs: Json<Vec<ContextAction>>,
+    query: Query<BulkOperationsQuery>,
     db_conn: DbConnection,
     user: User,
-) -> superposition::Result<Json<Vec<ContextBulkResponse>>> {
-    use contexts::dsl::contexts;
+) -> superposition::Result<HttpResponse> {
     let DbConnection(mut conn) = db_conn;
+    if query.dry_run {
+        let response = dry_run_bulk_operations(reqs.into_inner(), &mut conn, &user)?;
+        return Ok(HttpResponse::Ok().json(response));
+    }
+    let response = execute_bulk_operations(reqs.into_inner(), &mut conn, &user)?;
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// Applies `actions` in order inside a single transaction and records the
+/// result as a new config version. When `conn` is already in a transaction
+/// this becomes a savepoint, so callers can couple the context changes with
+/// writes of their own.
+pub fn execute_bulk_operations(
+    actions: Vec<ContextAction>,
+    conn: &mut DBConnection,
+    user: &User,
+) -> superposition::Result<Vec<ContextBulkResponse>> {
+    conn.transaction::<_, superposition::AppError, _>(|transaction_conn| {
+        let response = apply_bulk_operations(actions, transaction_conn, user)?;
+        add_config_version(transaction_conn, None, user)?;
+        Ok(response)
+    })
+}
+
+/// Runs `actions` exactly like [`execute_bulk_operations`] but rolls every
+/// change back, returning the responses along with the contexts and
+/// overrides the actions would have changed.
+fn dry_run_bulk_operations(
+    actions: Vec<ContextAction>,
+    conn: &mut DBConnection,
+    user: &User,
+) -> superposition::Result<BulkOperationsDryRunResponse> {
+    use contexts::dsl::contexts;
+
+    let mut dry_run = None;
+    let result = conn.transaction::<(), superposition::AppError, _>(|transaction_conn| {
+        let before = contexts.load::<Context>(transaction_conn)?;
+        let operations = apply_bulk_operations(actions, transaction_conn, user)?;
+        let after = contexts.load::<Context>(transaction_conn)?;
+        let (contexts_diff, overrides_diff) = diff_contexts(&before, &after)?;
+        dry_run = Some(BulkOperationsDryRunResponse {
+            operations,
+            contexts: contexts_diff,
+            overrides: overrides_diff,
+        });
+        // failing the transaction is what rolls the changes back
+        Err(diesel::result::Error::RollbackTransaction.into())
+    });
+    match (dry_run, result) {
+        (Some(response), _) => Ok(response),
+        (None, Err(err)) => Err(err),
+        (None, Ok(())) => Err(unexpected_error!("Something went wrong.")),
+    }
+}
+
+fn apply_bulk_operations(
+    actions: Vec<ContextAction>,
+    conn: &mut DBConnection,
+    user: &User,
+) -> superposition::Result<Vec<ContextBulkResponse>> {
+    use contexts::dsl::contexts;
 
     let mut response = Vec::<ContextBulkResponse>::new();
     conn.transaction::<_, superposition::AppError, _>(|transaction_conn| {
-        for action in reqs.into_inner().into_iter() {
+        for action in actions.into_iter() {
             match action {
                 ContextAction::PUT(put_req) => {
-                    let put_resp = put(Json(put_req), transaction_conn, true, &user)
+                    let put_resp = put(Json(put_req), transaction_conn, true, user)
                         .map_err(|err| {
                             log::error!(
                                 "Failed at insert into contexts due to {:?}",
@@ -568,7 +826,7 @@ async fn bulk_operations(
                 }
                 ContextAction::MOVE((old_ctx_id, move_req)) => {
                     let move_context_resp =
-                        r#move(old_ctx_id, Json(move_req), transaction_conn, true, &user)
+                        r#move(old_ctx_id, Json(move_req), transaction_conn, true, user)
                             .map_err(|err| {
                                 log::error!(
                                     "Failed at moving context reponse due to {:?}",
@@ -582,22 +840,37 @@ async fn bulk_operations(
         }
         Ok(()) // Commit the transaction
     })?;
-    Ok(Json(response))
+    Ok(response)
 }
 
 #[put("/priority/recompute")]
 async fn priority_recompute(
     db_conn: DbConnection,
+    user: User,
 ) -> superposition::Result<HttpResponse> {
-    use crate::db::schema::contexts::dsl::*;
     let DbConnection(mut conn) = db_conn;
 
-    let result: Vec<Context> = contexts.load(&mut conn).map_err(|err| {
+    let response = conn.transaction::<_, superposition::AppError, _>(|conn| {
+        recompute_context_priorities(conn, &user)
+    })?;
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// Recomputes the priority of every context from the current dimension
+/// priorities, adding a config version if any of them changed. Meant to be
+/// run inside a transaction.
+pub fn recompute_context_priorities(
+    conn: &mut DBConnection,
+    user: &User,
+) -> superposition::Result<Vec<PriorityRecomputeResponse>> {
+    use crate::db::schema::contexts::dsl::*;
+
+    let result: Vec<Context> = contexts.load(conn).map_err(|err| {
         log::error!("failed to fetch contexts with error: {}", err);
         unexpected_error!("Something went wrong")
     })?;
 
-    let dimension_schema_map = get_all_dimension_schema_map(&mut conn)?;
+    let dimension_schema_map = get_all_dimension_schema_map(conn)?;
     let mut response: Vec<PriorityRecomputeResponse> = vec![];
 
     let update_contexts = result
@@ -632,20 +905,23 @@ async fn priority_recompute(
         })
         .collect::<superposition::Result<Vec<Context>>>()?;
 
-    let insert = diesel::insert_into(contexts)
+    let priority_changed = response
+        .iter()
+        .any(|context| context.old_priority != context.new_priority);
+    diesel::insert_into(contexts)
         .values(&update_contexts)
         .on_conflict(id)
         .do_update()
         .set(priority.eq(excluded(priority)))
-        .execute(&mut conn);
-
-    match insert {
-        Ok(_) => Ok(HttpResponse::Ok().json(response)),
-        Err(err) => {
+        .execute(conn)
+        .map_err(|err| {
             log::error!(
                 "Failed to execute query while recomputing priority, error: {err}"
             );
-            Err(db_error!(err))
-        }
+            db_error!(err)
+        })?;
+    if priority_changed {
+        add_config_version(conn, None, user)?;
     }
+    Ok(response)
 }
@@ -1,13 +1,17 @@
 extern crate base64;
 use base64::prelude::*;
-use service_utils::helpers::extract_dimensions;
+use service_utils::helpers::{extract_conditions, ConditionOperator};
 use service_utils::{result as superposition, unexpected_error, validation_error};
 use std::str;
 
 use crate::api::functions::helpers::get_published_functions_by_names;
 use crate::validation_functions::execute_fn;
 use crate::{
-    api::context::types::FunctionsInfo,
+    api::context::types::{
+        AmbiguousOrder, ContextAnalysis, DefaultValueOverride, FunctionsInfo,
+        ShadowedOverride,
+    },
+    db::models::Context,
     db::schema::{
         default_configs::dsl,
         dimensions::{self},
@@ -26,8 +30,11 @@ pub fn validate_condition_with_functions(
     context: &Value,
 ) -> superposition::Result<()> {
     use dimensions::dsl;
-    let context = extract_dimensions(&context)?;
-    let dimensions_list: Vec<String> = context.keys().cloned().collect();
+    let conditions = extract_conditions(context)?;
+    let dimensions_list: Vec<&String> = conditions
+        .iter()
+        .map(|condition| &condition.dimension)
+        .collect();
     let keys_function_array: Vec<(String, Option<String>)> = dsl::dimensions
         .filter(dsl::dimension.eq_any(dimensions_list))
         .select((dsl::dimension, dsl::function_name))
@@ -38,12 +45,27 @@ pub fn validate_condition_with_functions(
         .collect();
 
     let dimension_functions_map = get_functions_map(conn, new_keys_function_array)?;
-    for (key, value) in context.iter() {
+    for condition in conditions.iter() {
+        let key = &condition.dimension;
         if let Some(functions_map) = dimension_functions_map.get(key) {
             if let (function_name, Some(function_code)) =
                 (functions_map.name.clone(), functions_map.code.clone())
             {
-                validate_value_with_function(&function_name, &function_code, key, value)?;
+                // `in` lists are validated as a whole, as they always have been
+                let values = match condition.operator {
+                    ConditionOperator::In | ConditionOperator::NotIn => {
+                        vec![Value::Array(condition.values.clone())]
+                    }
+                    _ => condition.values.clone(),
+                };
+                for value in values.iter() {
+                    validate_value_with_function(
+                        &function_name,
+                        &function_code,
+                        key,
+                        value,
+                    )?;
+                }
             }
         }
     }
@@ -134,3 +156,201 @@ pub fn validate_value_with_function(
     }
     Ok(())
 }
+
+/// What a condition requires of a single dimension.
+#[derive(PartialEq)]
+enum DimensionConstraint {
+    /// the dimension has to take one of these values
+    OneOf(Vec<Value>),
+    /// any other comparison, which is only known to equal itself
+    Opaque(Value),
+}
+
+type Constraints = HashMap<String, DimensionConstraint>;
+
+/// Breaks a condition into its per-dimension constraints, `None` when the
+/// condition is not a conjunction of single-dimension comparisons.
+fn condition_constraints(condition: &Value) -> Option<Constraints> {
+    let conditions = match condition.get("and") {
+        Some(Value::Array(conditions)) => conditions.iter().collect::<Vec<&Value>>(),
+        Some(_) => return None,
+        None => vec![condition],
+    };
+
+    let mut constraints = HashMap::new();
+    for condition in conditions {
+        let (operator, operands) = match condition.as_object()?.iter().next() {
+            Some((operator, Value::Array(operands)))
+                if condition.as_object()?.len() == 1 =>
+            {
+                (operator, operands)
+            }
+            _ => return None,
+        };
+        let dimension = operands
+            .iter()
+            .find_map(|operand| operand.get("var")?.as_str())?;
+        let values = operands
+            .iter()
+            .filter(|operand| operand.get("var").is_none())
+            .collect::<Vec<&Value>>();
+        let constraint = match (operator.as_str(), values.as_slice()) {
+            ("==", [value]) => DimensionConstraint::OneOf(vec![(*value).clone()]),
+            ("in", [Value::Array(items)]) => DimensionConstraint::OneOf(items.clone()),
+            _ => DimensionConstraint::Opaque(condition.clone()),
+        };
+        // more than one comparison on a dimension is left alone
+        if constraints
+            .insert(dimension.to_string(), constraint)
+            .is_some()
+        {
+            return None;
+        }
+    }
+    Some(constraints)
+}
+
+/// Whether every context matching `narrower` also matches `wider`.
+fn implies(narrower: &Constraints, wider: &Constraints) -> bool {
+    wider
+        .iter()
+        .all(|(dimension, wide)| match (narrower.get(dimension), wide) {
+            (
+                Some(DimensionConstraint::OneOf(narrow)),
+                DimensionConstraint::OneOf(wide),
+            ) => narrow.iter().all(|value| wide.contains(value)),
+            (Some(narrow), wide) => narrow == wide,
+            (None, _) => false,
+        })
+}
+
+/// Whether some context could match both `first` and `second`.
+fn overlaps(first: &Constraints, second: &Constraints) -> bool {
+    first.iter().all(|(dimension, constraint)| {
+        match (constraint, second.get(dimension)) {
+            (
+                DimensionConstraint::OneOf(first),
+                Some(DimensionConstraint::OneOf(second)),
+            ) => first.iter().any(|value| second.contains(value)),
+            _ => true,
+        }
+    })
+}
+
+/// Looks for overrides that can never take effect or are ambiguous.
+/// `contexts` have to be in the order they are applied, by priority and then
+/// by `created_at`, as every context overrides the ones before it.
+///
+/// Conditions that cannot be broken down into per-dimension constraints are
+/// reported as skipped, and are assumed to possibly overlap with every other
+/// context so that nothing is reported on their account.
+pub fn analyze_contexts(
+    contexts: &[Context],
+    default_configs: &Map<String, Value>,
+) -> ContextAnalysis {
+    let mut analysis = ContextAnalysis::default();
+    let empty_overrides = Map::new();
+    let analysed = contexts
+        .iter()
+        .map(|context| {
+            let overrides = context.override_.as_object();
+            let constraints = overrides.and(condition_constraints(&context.value));
+            if constraints.is_none() {
+                analysis.skipped_contexts.push(context.id.clone());
+            }
+            (context, constraints, overrides.unwrap_or(&empty_overrides))
+        })
+        .collect::<Vec<_>>();
+    let could_overlap = |first: &Constraints, second: &Option<Constraints>| {
+        second
+            .as_ref()
+            .map_or(true, |second| overlaps(first, second))
+    };
+
+    for (idx, (context, constraints, overrides)) in analysed.iter().enumerate() {
+        let Some(constraints) = constraints else {
+            continue;
+        };
+        let (earlier, later) = (&analysed[..idx], &analysed[idx + 1..]);
+
+        let mut shadowed_keys = 0;
+        for key in overrides.keys() {
+            let shadowed_by =
+                later
+                    .iter()
+                    .find(|(_, later_constraints, later_overrides)| {
+                        later_overrides.contains_key(key)
+                            && later_constraints.as_ref().is_some_and(
+                                |later_constraints| {
+                                    implies(constraints, later_constraints)
+                                },
+                            )
+                    });
+            if let Some((later_context, _, _)) = shadowed_by {
+                shadowed_keys += 1;
+                analysis.shadowed_overrides.push(ShadowedOverride {
+                    context_id: context.id.clone(),
+                    key: key.clone(),
+                    shadowed_by: later_context.id.clone(),
+                });
+            }
+        }
+        if shadowed_keys > 0 && shadowed_keys == overrides.len() {
+            analysis.unreachable_contexts.push(context.id.clone());
+        }
+
+        for (later_context, later_constraints, later_overrides) in later {
+            let same_priority = later_context.priority == context.priority;
+            let overlapping =
+                later_constraints.as_ref().is_some_and(|later_constraints| {
+                    overlaps(constraints, later_constraints)
+                });
+            if !same_priority || !overlapping {
+                continue;
+            }
+            let mut keys = overrides
+                .iter()
+                .filter(|(key, value)| {
+                    later_overrides
+                        .get(*key)
+                        .is_some_and(|other| other != *value)
+                })
+                .map(|(key, _)| key.clone())
+                .collect::<Vec<String>>();
+            if !keys.is_empty() {
+                keys.sort();
+                analysis.ambiguous_orders.push(AmbiguousOrder {
+                    priority: context.priority,
+                    context_ids: [context.id.clone(), later_context.id.clone()],
+                    keys,
+                });
+            }
+        }
+
+        for (key, value) in overrides.iter() {
+            if default_configs.get(key) != Some(value) {
+                continue;
+            }
+            // resetting a key to its default matters when an earlier context
+            // may have changed it
+            let resets_earlier =
+                earlier
+                    .iter()
+                    .any(|(_, earlier_constraints, earlier_overrides)| {
+                        earlier_overrides
+                            .get(key)
+                            .is_some_and(|other| other != value)
+                            && could_overlap(constraints, earlier_constraints)
+                    });
+            if !resets_earlier {
+                analysis.default_value_overrides.push(DefaultValueOverride {
+                    context_id: context.id.clone(),
+                    key: key.clone(),
+                    value: value.clone(),
+                });
+            }
+        }
+    }
+
+    analysis
+}
@@ -1,4 +1,7 @@
 mod handlers;
 pub mod helpers;
-mod types;
-pub use handlers::endpoints;
+pub mod types;
+pub use handlers::{
+    create_ctx_from_put_req, endpoints, execute_bulk_operations,
+    recompute_context_priorities, validate_dimensions_and_calculate_priority,
+};
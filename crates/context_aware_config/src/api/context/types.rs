@@ -1,5 +1,7 @@
+use crate::{api::config::ChangeSet, db::models::Context};
 use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
+use service_utils::helpers::deserialize_stringified_list;
 
 #[derive(Deserialize, Clone)]
 pub struct PutReq {
@@ -24,10 +26,48 @@ pub struct PutResp {
     pub priority: i32,
 }
 
+#[derive(Deserialize, Debug, Clone)]
+pub struct StringArgs(
+    #[serde(deserialize_with = "deserialize_stringified_list")] pub Vec<String>,
+);
+
+#[derive(Deserialize, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ContextSortOn {
+    #[default]
+    CreatedAt,
+    Priority,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SortBy {
+    #[default]
+    Asc,
+    Desc,
+}
+
 #[derive(Deserialize)]
-pub struct PaginationParams {
+pub struct ListContextFilters {
     pub page: Option<u32>,
     pub size: Option<u32>,
+    // `dimension` or `dimension=value` entries, all of which have to be part
+    // of the context's condition
+    pub dimensions: Option<StringArgs>,
+    // keys all of which have to be overridden by the context
+    pub override_keys: Option<StringArgs>,
+    pub created_by: Option<String>,
+    pub min_priority: Option<i32>,
+    pub max_priority: Option<i32>,
+    pub sort_on: Option<ContextSortOn>,
+    pub sort_by: Option<SortBy>,
+}
+
+#[derive(Serialize)]
+pub struct ListContextsResponse {
+    pub total_pages: i64,
+    pub total_items: i64,
+    pub data: Vec<Context>,
 }
 
 #[derive(serde::Deserialize)]
@@ -44,6 +84,19 @@ pub enum ContextBulkResponse {
     MOVE(PutResp),
 }
 
+#[derive(Deserialize)]
+pub struct BulkOperationsQuery {
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Serialize)]
+pub struct BulkOperationsDryRunResponse {
+    pub operations: Vec<ContextBulkResponse>,
+    pub contexts: ChangeSet,
+    pub overrides: ChangeSet,
+}
+
 #[derive(Deserialize, Clone)]
 pub struct FunctionsInfo {
     pub name: String,
@@ -57,3 +110,36 @@ pub struct PriorityRecomputeResponse {
     pub old_priority: i32,
     pub new_priority: i32,
 }
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct ShadowedOverride {
+    pub context_id: String,
+    pub key: String,
+    pub shadowed_by: String,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct AmbiguousOrder {
+    pub priority: i32,
+    // in the order they are applied today, by `created_at`
+    pub context_ids: [String; 2],
+    pub keys: Vec<String>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct DefaultValueOverride {
+    pub context_id: String,
+    pub key: String,
+    pub value: Value,
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct ContextAnalysis {
+    pub shadowed_overrides: Vec<ShadowedOverride>,
+    // contexts all of whose overrides are shadowed
+    pub unreachable_contexts: Vec<String>,
+    pub ambiguous_orders: Vec<AmbiguousOrder>,
+    pub default_value_overrides: Vec<DefaultValueOverride>,
+    // contexts with conditions too complex to be analysed
+    pub skipped_contexts: Vec<String>,
+}
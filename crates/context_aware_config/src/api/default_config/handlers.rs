@@ -1,5 +1,5 @@
 extern crate base64;
-use super::types::CreateReq;
+use super::types::{CreateDryRunResponse, CreateQuery, CreateReq, InvalidOverride};
 use service_utils::helpers::validation_err_to_str;
 use service_utils::{
     bad_argument, db_error, not_found, unexpected_error, validation_error,
@@ -7,6 +7,7 @@ use service_utils::{
 
 use superposition_types::{SuperpositionUser, User};
 
+use crate::api::config::add_config_version;
 use crate::api::context::helpers::validate_value_with_function;
 use crate::{
     api::functions::helpers::get_published_function_code,
@@ -15,17 +16,20 @@ use crate::{
         models::{Context, DefaultConfig},
         schema::{contexts::dsl::contexts, default_configs::dsl::default_configs},
     },
-    helpers::validate_jsonschema,
+    helpers::{json_to_sorted_string, validate_if_match, validate_jsonschema},
 };
 use actix_web::{
-    delete, get, put,
-    web::{self, Data, Json, Path},
-    HttpResponse, Scope,
+    delete, get,
+    http::header::{self, ETag, EntityTag},
+    put,
+    web::{self, Data, Json, Path, Query},
+    HttpRequest, HttpResponse, Scope,
 };
 use chrono::Utc;
 use diesel::{
     r2d2::{ConnectionManager, PooledConnection},
-    ExpressionMethods, PgConnection, QueryDsl, RunQueryDsl,
+    Connection, ExpressionMethods, OptionalExtension, PgConnection, QueryDsl,
+    RunQueryDsl,
 };
 use jsonschema::{Draft, JSONSchema, ValidationError};
 use serde_json::{from_value, json, Map, Value};
@@ -35,20 +39,27 @@ use service_utils::{
 };
 
 pub fn endpoints() -> Scope {
-    Scope::new("").service(create).service(get).service(delete)
+    Scope::new("")
+        .service(create)
+        .service(get)
+        .service(get_key)
+        .service(delete)
 }
 
 #[put("/{key}")]
 async fn create(
+    http_req: HttpRequest,
     state: Data<AppState>,
     key: web::Path<String>,
     request: web::Json<CreateReq>,
+    query: Query<CreateQuery>,
     db_conn: DbConnection,
     user: User,
 ) -> superposition::Result<HttpResponse> {
     let DbConnection(mut conn) = db_conn;
     let req = request.into_inner();
     let key = key.into_inner();
+    let dry_run = query.into_inner().dry_run;
 
     if req.value.is_none() && req.schema.is_none() && req.function_name.is_none() {
         log::error!("No data provided in the request body for {key}");
@@ -67,20 +78,24 @@ async fn create(
 
     let result = fetch_default_key(&key, &mut conn);
 
-    let (value, schema, function_name) = match result {
-        Ok((val, schema, f_name)) => {
+    let (value, schema, function_name, schema_changed) = match result {
+        Ok((val, old_schema, f_name)) => {
             let val = req.value.unwrap_or_else(|| val);
-            let schema = req.schema.map_or_else(|| schema, Value::Object);
+            let schema = req.schema.map_or_else(|| old_schema.clone(), Value::Object);
             let f_name = if req.function_name == Some(Value::Null) {
                 None
             } else {
                 func_name.or(f_name)
             };
-            (val, schema, f_name)
+            let schema_changed = schema != old_schema;
+            (val, schema, f_name, schema_changed)
         }
+        // a new key cannot have been overridden yet
         Err(superposition::AppError::DbError(diesel::NotFound)) => {
             match (req.value, req.schema) {
-                (Some(val), Some(schema)) => (val, Value::Object(schema), func_name),
+                (Some(val), Some(schema)) => {
+                    (val, Value::Object(schema), func_name, false)
+                }
                 _ => {
                     log::error!("No record found for {key}.");
                     return Err(bad_argument!("No record found for {}", key));
@@ -102,6 +117,104 @@ async fn create(
         created_at: Utc::now(),
     };
 
+    validate_default_config(&state, &default_config, &mut conn)?;
+
+    conn.transaction::<_, superposition::AppError, _>(|conn| {
+        validate_default_config_if_match(&http_req, &key, conn)?;
+        let invalid_overrides = if schema_changed {
+            find_invalid_overrides(&key, &default_config.schema, conn)?
+        } else {
+            Vec::new()
+        };
+
+        if dry_run {
+            return Ok(HttpResponse::Ok().json(CreateDryRunResponse {
+                default_config,
+                invalid_overrides,
+            }));
+        }
+        if !invalid_overrides.is_empty() {
+            let context_ids = invalid_overrides
+                .iter()
+                .map(|invalid| invalid.context_id.as_str())
+                .collect::<Vec<&str>>();
+            log::info!(
+                "schema change of {key} rejected, invalid overrides in contexts: {context_ids:?}"
+            );
+            return Err(validation_error!(
+                "The new schema does not match the overrides of {} in contexts: {}",
+                key,
+                context_ids.join(",")
+            ));
+        }
+
+        diesel::insert_into(default_configs)
+            .values(&default_config)
+            .on_conflict(db::schema::default_configs::key)
+            .do_update()
+            .set(&default_config)
+            .execute(conn)
+            .map_err(|e| {
+                log::info!("DefaultConfig creation failed with error: {e}");
+                unexpected_error!("Something went wrong, failed to create DefaultConfig")
+            })?;
+        add_config_version(conn, None, &user)?;
+        Ok(HttpResponse::Ok()
+            .insert_header(ETag(EntityTag::new_strong(default_config_tag(
+                &default_config.value,
+                &default_config.schema,
+                &default_config.function_name,
+            ))))
+            .json(json!({
+                "message": "DefaultConfig created/updated successfully."
+            })))
+    })
+}
+
+/// Lists the contexts overriding `key` with a value that does not match
+/// `schema`.
+pub fn find_invalid_overrides(
+    key: &str,
+    schema: &Value,
+    conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+) -> superposition::Result<Vec<InvalidOverride>> {
+    let jschema = JSONSchema::options()
+        .with_draft(Draft::Draft7)
+        .compile(schema)
+        .map_err(|e| {
+            log::info!("Failed to compile as a Draft-7 JSON schema: {e}");
+            bad_argument!("Invalid JSON schema (failed to compile)")
+        })?;
+
+    let result: Vec<Context> = contexts.load(conn).map_err(|err| {
+        log::error!("failed to fetch contexts with error: {}", err);
+        db_error!(err)
+    })?;
+
+    let invalid_overrides = result
+        .iter()
+        .filter_map(|context| {
+            let value = context.override_.get(key)?;
+            let verrors = jschema.validate(value).err()?.collect::<Vec<_>>();
+            Some(InvalidOverride {
+                context_id: context.id.to_owned(),
+                error: validation_err_to_str(verrors)
+                    .first()
+                    .cloned()
+                    .unwrap_or_default(),
+            })
+        })
+        .collect();
+    Ok(invalid_overrides)
+}
+
+/// Validates the schema of `default_config`, and its value against that
+/// schema and the key's validation function.
+pub fn validate_default_config(
+    state: &AppState,
+    default_config: &DefaultConfig,
+    conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+) -> superposition::Result<()> {
     validate_jsonschema(
         &state.default_config_validation_schema,
         &default_config.schema,
@@ -133,7 +246,7 @@ async fn create(
     }
 
     if let Some(f_name) = &default_config.function_name {
-        let function_code = get_published_function_code(&mut conn, f_name.to_string())
+        let function_code = get_published_function_code(conn, f_name.to_string())
             .map_err(|e| {
                 log::info!("Function not found with error : {e}");
                 bad_argument!("Function {} doesn't exists.", f_name)
@@ -147,25 +260,48 @@ async fn create(
             )?;
         }
     }
+    Ok(())
+}
 
-    let upsert = diesel::insert_into(default_configs)
-        .values(&default_config)
-        .on_conflict(db::schema::default_configs::key)
-        .do_update()
-        .set(&default_config)
-        .execute(&mut conn);
-
-    match upsert {
-        Ok(_) => Ok(HttpResponse::Ok().json(json!({
-            "message": "DefaultConfig created/updated successfully."
-        }))),
-        Err(e) => {
-            log::info!("DefaultConfig creation failed with error: {e}");
-            Err(unexpected_error!(
-                "Something went wrong, failed to create DefaultConfig"
-            ))
-        }
+/// Replaces the values of existing default config keys, validating each one
+/// against the key's schema, and records the result as a new config version.
+/// Nothing is written unless every value is valid, callers wanting this to be
+/// atomic with other changes run it in their own transaction.
+pub fn update_default_config_values(
+    state: &AppState,
+    values: &Map<String, Value>,
+    conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+    user: &User,
+) -> superposition::Result<Vec<DefaultConfig>> {
+    let mut updated_configs = Vec::with_capacity(values.len());
+    for (key, value) in values {
+        let (_, schema, function_name) =
+            fetch_default_key(key, conn).map_err(|err| match err {
+                superposition::AppError::DbError(diesel::NotFound) => {
+                    bad_argument!("No record found for {}", key)
+                }
+                err => err,
+            })?;
+        let default_config = DefaultConfig {
+            key: key.to_owned(),
+            value: value.to_owned(),
+            schema,
+            function_name,
+            created_by: user.get_email(),
+            created_at: Utc::now(),
+        };
+        validate_default_config(state, &default_config, conn)?;
+        updated_configs.push(default_config);
+    }
+
+    for default_config in &updated_configs {
+        diesel::update(default_configs)
+            .filter(db::schema::default_configs::key.eq(&default_config.key))
+            .set(default_config)
+            .execute(conn)?;
     }
+    add_config_version(conn, None, user)?;
+    Ok(updated_configs)
 }
 
 fn fetch_default_key(
@@ -191,6 +327,73 @@ async fn get(db_conn: DbConnection) -> superposition::Result<Json<Vec<DefaultCon
     Ok(Json(result))
 }
 
+#[get("/{key}")]
+async fn get_key(
+    path: Path<String>,
+    db_conn: DbConnection,
+) -> superposition::Result<HttpResponse> {
+    let DbConnection(mut conn) = db_conn;
+    let key = path.into_inner();
+
+    let default_config = default_configs
+        .filter(db::schema::default_configs::key.eq(&key))
+        .get_result::<DefaultConfig>(&mut conn)
+        .map_err(|err| match err {
+            diesel::NotFound => not_found!("default config key `{}` doesn't exists", key),
+            err => db_error!(err),
+        })?;
+    let tag = default_config_tag(
+        &default_config.value,
+        &default_config.schema,
+        &default_config.function_name,
+    );
+    Ok(HttpResponse::Ok()
+        .insert_header(ETag(EntityTag::new_strong(tag)))
+        .json(default_config))
+}
+
+/// The entity tag of a default config, a hash over everything a write can
+/// change about the key.
+fn default_config_tag(
+    value: &Value,
+    schema: &Value,
+    function_name: &Option<String>,
+) -> String {
+    let content = json!({
+        "value": value,
+        "schema": schema,
+        "function_name": function_name,
+    });
+    blake3::hash(json_to_sorted_string(&content).as_bytes()).to_string()
+}
+
+/// Checks the `If-Match` header of `req` against the current tag of `key`,
+/// locking the row so that it cannot change before the caller's transaction
+/// ends.
+fn validate_default_config_if_match(
+    req: &HttpRequest,
+    key: &String,
+    conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+) -> superposition::Result<()> {
+    if !req.headers().contains_key(header::IF_MATCH) {
+        return Ok(());
+    }
+    let current_tag = default_configs
+        .filter(db::schema::default_configs::key.eq(key))
+        .select((
+            db::schema::default_configs::value,
+            db::schema::default_configs::schema,
+            db::schema::default_configs::function_name,
+        ))
+        .for_update()
+        .first::<(Value, Value, Option<String>)>(conn)
+        .optional()?
+        .map(|(value, schema, function_name)| {
+            default_config_tag(&value, &schema, &function_name)
+        });
+    validate_if_match(req, current_tag.as_deref())
+}
+
 pub fn get_key_usage_context_ids(
     key: &str,
     conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
@@ -215,6 +418,7 @@ pub fn get_key_usage_context_ids(
 
 #[delete("/{key}")]
 async fn delete(
+    http_req: HttpRequest,
     path: Path<String>,
     db_conn: DbConnection,
     user: User,
@@ -226,21 +430,28 @@ async fn delete(
     let context_ids = get_key_usage_context_ids(&key, &mut conn)
         .map_err(|_| unexpected_error!("Something went wrong"))?;
     if context_ids.is_empty() {
-        let deleted_row = diesel::delete(
-            default_configs.filter(db::schema::default_configs::key.eq(&key)),
-        )
-        .execute(&mut conn);
-        match deleted_row {
-            Ok(0) => Err(not_found!("default config key `{}` doesn't exists", key)),
-            Ok(_) => {
-                log::info!("default config key: {key} deleted by {}", user.get_email());
-                Ok(HttpResponse::NoContent().finish())
-            }
-            Err(e) => {
-                log::error!("default config delete query failed with error: {e}");
-                Err(unexpected_error!("Something went wrong."))
+        conn.transaction::<_, superposition::AppError, _>(|conn| {
+            validate_default_config_if_match(&http_req, &key, conn)?;
+            let deleted_row = diesel::delete(
+                default_configs.filter(db::schema::default_configs::key.eq(&key)),
+            )
+            .execute(conn);
+            match deleted_row {
+                Ok(0) => Err(not_found!("default config key `{}` doesn't exists", key)),
+                Ok(_) => {
+                    log::info!(
+                        "default config key: {key} deleted by {}",
+                        user.get_email()
+                    );
+                    add_config_version(conn, None, &user)?;
+                    Ok(HttpResponse::NoContent().finish())
+                }
+                Err(e) => {
+                    log::error!("default config delete query failed with error: {e}");
+                    Err(unexpected_error!("Something went wrong."))
+                }
             }
-        }
+        })
     } else {
         Err(bad_argument!(
             "Given key already in use in contexts: {}",
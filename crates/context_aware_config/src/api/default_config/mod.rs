@@ -1,3 +1,6 @@
 mod handlers;
 mod types;
-pub use handlers::endpoints;
+pub use handlers::{
+    endpoints, find_invalid_overrides, update_default_config_values,
+    validate_default_config,
+};
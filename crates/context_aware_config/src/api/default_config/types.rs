@@ -1,6 +1,8 @@
-use serde::{Deserialize, Deserializer};
+use serde::{Deserialize, Deserializer, Serialize};
 use serde_json::{Map, Value};
 
+use crate::db::models::DefaultConfig;
+
 #[derive(Debug, Deserialize)]
 pub struct CreateReq {
     #[serde(default, deserialize_with = "deserialize_option")]
@@ -10,6 +12,25 @@ pub struct CreateReq {
     pub function_name: Option<Value>,
 }
 
+#[derive(Deserialize)]
+pub struct CreateQuery {
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Serialize)]
+pub struct InvalidOverride {
+    pub context_id: String,
+    pub error: String,
+}
+
+#[derive(Serialize)]
+pub struct CreateDryRunResponse {
+    pub default_config: DefaultConfig,
+    /// overrides of the key that would no longer match its schema
+    pub invalid_overrides: Vec<InvalidOverride>,
+}
+
 fn deserialize_option<'de, D>(deserializer: D) -> Result<Option<Value>, D::Error>
 where
     D: Deserializer<'de>,
@@ -1,27 +1,63 @@
 use crate::{
-    api::dimension::types::CreateReq,
-    db::{models::Dimension, schema::dimensions::dsl::*},
+    api::{
+        config::add_config_version,
+        context::{
+            helpers::validate_condition_with_functions, recompute_context_priorities,
+            validate_dimensions_and_calculate_priority,
+        },
+        dimension::{
+            types::{CreateReq, DeleteQuery, DeleteResp, UpdateReq, UpdateResp},
+            utils::{get_all_dimension_schema_map, get_dimension_usage_contexts},
+        },
+    },
+    db::{
+        models::Dimension,
+        schema::{contexts::dsl as ctxt, dimensions::dsl::*},
+    },
     helpers::validate_jsonschema,
 };
 use actix_web::{
-    get, put,
-    web::{self, Data, Json},
+    delete, get, put,
+    web::{self, Data, Json, Path, Query},
     HttpResponse, Scope,
 };
 use chrono::Utc;
-use diesel::RunQueryDsl;
+use diesel::{
+    r2d2::{ConnectionManager, PooledConnection},
+    Connection, ExpressionMethods, OptionalExtension, PgConnection, QueryDsl,
+    RunQueryDsl,
+};
 use jsonschema::{Draft, JSONSchema};
 use serde_json::Value;
 use service_utils::{
-    bad_argument, result as superposition,
+    bad_argument, not_found, result as superposition,
     service::types::{AppState, DbConnection},
     unexpected_error,
 };
 
 use superposition_types::{SuperpositionUser, User};
 
+type DBConnection = PooledConnection<ConnectionManager<PgConnection>>;
+
 pub fn endpoints() -> Scope {
-    Scope::new("").service(create).service(get)
+    Scope::new("")
+        .service(create)
+        .service(get)
+        .service(update)
+        .service(delete_dimension)
+}
+
+fn parse_function_name(fun_name: Option<Value>) -> superposition::Result<Option<String>> {
+    match fun_name {
+        Some(Value::String(func_name)) => Ok(Some(func_name)),
+        Some(Value::Null) | None => Ok(None),
+        _ => {
+            log::error!("Expected a string or null as the function name.");
+            Err(bad_argument!(
+                "Expected a string or null as the function name."
+            ))
+        }
+    }
 }
 
 #[put("")]
@@ -33,18 +69,80 @@ async fn create(
 ) -> superposition::Result<HttpResponse> {
     let DbConnection(mut conn) = db_conn;
 
-    if req.priority <= 0 {
-        return Err(bad_argument!("Priority should be greater than 0"));
-    }
-
     let create_req = req.into_inner();
     let schema_value = create_req.schema;
 
-    validate_jsonschema(&state.meta_schema, &schema_value)?;
+    let fun_name = parse_function_name(create_req.function_name)?;
+
+    let new_dimension = Dimension {
+        dimension: create_req.dimension,
+        priority: i32::from(create_req.priority),
+        schema: schema_value,
+        created_by: user.get_email(),
+        created_at: Utc::now(),
+        function_name: fun_name.clone(),
+    };
+    validate_dimension(&state, &new_dimension)?;
+
+    let upserted_dimension =
+        conn.transaction::<_, superposition::AppError, _>(|conn| {
+            let existing = dimensions
+                .filter(dimension.eq(&new_dimension.dimension))
+                .first::<Dimension>(conn)
+                .optional()?;
+
+            // an existing dimension is updated exactly as through `update`
+            if let Some(existing) = existing {
+                let updated_dimension = Dimension {
+                    created_at: existing.created_at,
+                    created_by: existing.created_by.clone(),
+                    ..new_dimension
+                };
+                return replace_dimension(existing, updated_dimension, conn, &user)
+                    .map(|response| response.dimension);
+            }
+
+            diesel::insert_into(dimensions)
+                .values(&new_dimension)
+                .get_result::<Dimension>(conn)
+                .map_err(|err| match err {
+                    diesel::result::Error::DatabaseError(
+                        diesel::result::DatabaseErrorKind::ForeignKeyViolation,
+                        e,
+                    ) => {
+                        log::error!("{fun_name:?} function not found with error: {e:?}");
+                        bad_argument!(
+                            "Funtion {} doesn't exists",
+                            fun_name.clone().unwrap_or(String::new())
+                        )
+                    }
+                    e => {
+                        log::error!("Dimension upsert failed with error: {e}");
+                        unexpected_error!(
+                            "Something went wrong, failed to create/update dimension"
+                        )
+                    }
+                })
+        })?;
+
+    Ok(HttpResponse::Created().json(upserted_dimension))
+}
+
+/// Validates the priority of `new_dimension` and its schema against the meta
+/// schema.
+pub fn validate_dimension(
+    state: &AppState,
+    new_dimension: &Dimension,
+) -> superposition::Result<()> {
+    if new_dimension.priority <= 0 {
+        return Err(bad_argument!("Priority should be greater than 0"));
+    }
+
+    validate_jsonschema(&state.meta_schema, &new_dimension.schema)?;
 
     let schema_compile_result = JSONSchema::options()
         .with_draft(Draft::Draft7)
-        .compile(&schema_value);
+        .compile(&new_dimension.schema);
 
     if let Err(e) = schema_compile_result {
         return Err(bad_argument!(
@@ -52,61 +150,166 @@ async fn create(
             e
         ));
     };
+    Ok(())
+}
 
-    let fun_name = match create_req.function_name {
-        Some(Value::String(func_name)) => Some(func_name),
-        Some(Value::Null) | None => None,
-        _ => {
-            log::error!("Expected a string or null as the function name.");
-            return Err(bad_argument!(
-                "Expected a string or null as the function name."
-            ));
+#[get("")]
+async fn get(db_conn: DbConnection) -> superposition::Result<Json<Vec<Dimension>>> {
+    let DbConnection(mut conn) = db_conn;
+
+    let result: Vec<Dimension> = dimensions.get_results(&mut conn)?;
+    Ok(Json(result))
+}
+
+#[put("/{name}")]
+async fn update(
+    state: Data<AppState>,
+    path: Path<String>,
+    req: Json<UpdateReq>,
+    user: User,
+    db_conn: DbConnection,
+) -> superposition::Result<Json<UpdateResp>> {
+    let DbConnection(mut conn) = db_conn;
+    let name = path.into_inner();
+    let update_req = req.into_inner();
+
+    let response = conn.transaction::<_, superposition::AppError, _>(|conn| {
+        let existing = dimensions
+            .filter(dimension.eq(&name))
+            .first::<Dimension>(conn)
+            .optional()?
+            .ok_or_else(|| not_found!("Dimension `{}` doesn't exists", name))?;
+
+        let function_name_update = match update_req.function_name {
+            Some(value) => parse_function_name(Some(value))?,
+            None => existing.function_name.clone(),
+        };
+        let updated_dimension = Dimension {
+            dimension: name.clone(),
+            priority: update_req.priority.map_or(existing.priority, i32::from),
+            schema: update_req.schema.unwrap_or(existing.schema.clone()),
+            function_name: function_name_update,
+            created_at: existing.created_at,
+            created_by: existing.created_by.clone(),
+        };
+        validate_dimension(&state, &updated_dimension)?;
+
+        replace_dimension(existing, updated_dimension, conn, &user)
+    })?;
+
+    log::info!("dimension {name} updated by {}", user.get_email());
+    Ok(Json(response))
+}
+
+/// Writes `updated_dimension` over `existing`, checking the contexts using
+/// the dimension against its new schema and function, and recomputing their
+/// priorities when its priority changed. Meant to be run inside a transaction.
+fn replace_dimension(
+    existing: Dimension,
+    updated_dimension: Dimension,
+    conn: &mut DBConnection,
+    user: &User,
+) -> superposition::Result<UpdateResp> {
+    let name = existing.dimension.clone();
+    let updated_dimension = diesel::update(dimensions)
+        .filter(dimension.eq(&name))
+        .set(&updated_dimension)
+        .get_result::<Dimension>(conn)
+        .map_err(|err| match err {
+            diesel::result::Error::DatabaseError(
+                diesel::result::DatabaseErrorKind::ForeignKeyViolation,
+                e,
+            ) => {
+                log::error!("function not found with error: {e:?}");
+                bad_argument!(
+                    "Funtion {} doesn't exists",
+                    updated_dimension.function_name.clone().unwrap_or_default()
+                )
+            }
+            err => {
+                log::error!("Dimension update failed with error: {err}");
+                unexpected_error!("Something went wrong, failed to update dimension")
+            }
+        })?;
+
+    // the contexts using the dimension have to hold up against its new
+    // schema and function
+    let dimension_schema_map = get_all_dimension_schema_map(conn)?;
+    let mut invalid_contexts = Vec::new();
+    for context in get_dimension_usage_contexts(&name, conn)? {
+        let validation = validate_dimensions_and_calculate_priority(
+            "context",
+            &context.value,
+            &dimension_schema_map,
+        )
+        .and_then(|_| validate_condition_with_functions(conn, &context.value));
+        if let Err(err) = validation {
+            log::info!("context {} fails validation: {err}", context.id);
+            invalid_contexts.push(format!("{} ({err})", context.id));
         }
-    };
+    }
+    if !invalid_contexts.is_empty() {
+        return Err(bad_argument!(
+            "Existing contexts do not match the updated dimension: {}",
+            invalid_contexts.join(", ")
+        ));
+    }
 
-    let new_dimension = Dimension {
-        dimension: create_req.dimension,
-        priority: i32::from(create_req.priority),
-        schema: schema_value,
-        created_by: user.get_email(),
-        created_at: Utc::now(),
-        function_name: fun_name.clone(),
+    let priority_changes = if updated_dimension.priority != existing.priority {
+        recompute_context_priorities(conn, user)?
+            .into_iter()
+            .filter(|change| change.old_priority != change.new_priority)
+            .collect()
+    } else {
+        Vec::new()
     };
 
-    let upsert = diesel::insert_into(dimensions)
-        .values(&new_dimension)
-        .on_conflict(dimension)
-        .do_update()
-        .set(&new_dimension)
-        .get_result::<Dimension>(&mut conn);
+    Ok(UpdateResp {
+        dimension: updated_dimension,
+        priority_changes,
+    })
+}
 
-    match upsert {
-        Ok(upserted_dimension) => {
-            return Ok(HttpResponse::Created().json(upserted_dimension))
-        }
-        Err(diesel::result::Error::DatabaseError(
-            diesel::result::DatabaseErrorKind::ForeignKeyViolation,
-            e,
-        )) => {
-            log::error!("{fun_name:?} function not found with error: {e:?}");
+#[delete("/{name}")]
+async fn delete_dimension(
+    path: Path<String>,
+    query: Query<DeleteQuery>,
+    user: User,
+    db_conn: DbConnection,
+) -> superposition::Result<Json<DeleteResp>> {
+    let DbConnection(mut conn) = db_conn;
+    let name = path.into_inner();
+    let cascade = query.into_inner().cascade;
+
+    let deleted_contexts = conn.transaction::<_, superposition::AppError, _>(|conn| {
+        let context_ids = get_dimension_usage_contexts(&name, conn)?
+            .into_iter()
+            .map(|context| context.id)
+            .collect::<Vec<String>>();
+        if !context_ids.is_empty() && !cascade {
             return Err(bad_argument!(
-                "Funtion {} doesn't exists",
-                fun_name.unwrap_or(String::new())
+                "Dimension `{}` is used in contexts: {}. Pass cascade=true to delete them along with it",
+                name,
+                context_ids.join(",")
             ));
         }
-        Err(e) => {
-            log::error!("Dimension upsert failed with error: {e}");
-            return Err(unexpected_error!(
-                "Something went wrong, failed to create/update dimension"
-            ));
-        }
-    }
-}
 
-#[get("")]
-async fn get(db_conn: DbConnection) -> superposition::Result<Json<Vec<Dimension>>> {
-    let DbConnection(mut conn) = db_conn;
+        diesel::delete(ctxt::contexts.filter(ctxt::id.eq_any(&context_ids)))
+            .execute(conn)?;
+        let deleted = diesel::delete(dimensions.filter(dimension.eq(&name)))
+            .execute(conn)?;
+        if deleted == 0 {
+            return Err(not_found!("Dimension `{}` doesn't exists", name));
+        }
+        if !context_ids.is_empty() {
+            add_config_version(conn, None, &user)?;
+        }
+        Ok(context_ids)
+    })?;
 
-    let result: Vec<Dimension> = dimensions.get_results(&mut conn)?;
-    Ok(Json(result))
+    log::info!("dimension {name} deleted by {}", user.get_email());
+    Ok(Json(DeleteResp {
+        dimension: name,
+        deleted_contexts,
+    }))
 }
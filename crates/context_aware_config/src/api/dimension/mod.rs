@@ -1,5 +1,5 @@
 mod handlers;
 mod types;
 mod utils;
-pub use handlers::endpoints;
-pub use utils::get_all_dimension_schema_map;
+pub use handlers::{endpoints, validate_dimension};
+pub use utils::{condition_uses_dimension, get_all_dimension_schema_map};
@@ -1,6 +1,8 @@
-use serde::{Deserialize, Deserializer};
+use serde::{Deserialize, Deserializer, Serialize};
 use serde_json::Value;
 
+use crate::{api::context::types::PriorityRecomputeResponse, db::models::Dimension};
+
 #[derive(Debug, Deserialize)]
 pub struct CreateReq {
     pub dimension: String,
@@ -10,6 +12,34 @@ pub struct CreateReq {
     pub function_name: Option<Value>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct UpdateReq {
+    pub priority: Option<u16>,
+    pub schema: Option<Value>,
+    #[serde(default, deserialize_with = "deserialize_option")]
+    pub function_name: Option<Value>,
+}
+
+#[derive(Serialize)]
+pub struct UpdateResp {
+    pub dimension: Dimension,
+    /// contexts whose priority changed along with the dimension's
+    pub priority_changes: Vec<PriorityRecomputeResponse>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeleteQuery {
+    /// deletes the contexts using the dimension instead of refusing to
+    #[serde(default)]
+    pub cascade: bool,
+}
+
+#[derive(Serialize)]
+pub struct DeleteResp {
+    pub dimension: String,
+    pub deleted_contexts: Vec<String>,
+}
+
 fn deserialize_option<'de, D>(deserializer: D) -> Result<Option<Value>, D::Error>
 where
     D: Deserializer<'de>,
@@ -1,12 +1,16 @@
 use std::collections::HashMap;
 
-use crate::db::{models::Dimension, schema::dimensions::dsl::*};
+use crate::db::{
+    models::{Context, Dimension},
+    schema::{contexts::dsl as ctxt, dimensions::dsl::*},
+};
 use diesel::RunQueryDsl;
 use diesel::{
     r2d2::{ConnectionManager, PooledConnection},
     PgConnection,
 };
 use jsonschema::{Draft, JSONSchema};
+use serde_json::Value;
 use service_utils::result as superposition;
 
 pub fn get_all_dimension_schema_map(
@@ -28,3 +32,30 @@ pub fn get_all_dimension_schema_map(
 
     Ok(dimension_schema_map)
 }
+
+/// Tells if `condition`, or any condition nested in it, refers to
+/// `dimension_name`.
+pub fn condition_uses_dimension(condition: &Value, dimension_name: &str) -> bool {
+    match condition {
+        Value::Object(obj) => obj.iter().any(|(key, value)| {
+            (key == "var" && value.as_str() == Some(dimension_name))
+                || condition_uses_dimension(value, dimension_name)
+        }),
+        Value::Array(arr) => arr
+            .iter()
+            .any(|item| condition_uses_dimension(item, dimension_name)),
+        _ => false,
+    }
+}
+
+/// Lists the contexts whose conditions refer to `dimension_name`.
+pub fn get_dimension_usage_contexts(
+    dimension_name: &str,
+    conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+) -> superposition::Result<Vec<Context>> {
+    let contexts = ctxt::contexts.load::<Context>(conn)?;
+    Ok(contexts
+        .into_iter()
+        .filter(|context| condition_uses_dimension(&context.value, dimension_name))
+        .collect())
+}
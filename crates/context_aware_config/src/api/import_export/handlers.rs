@@ -0,0 +1,426 @@
+use std::collections::{HashMap, HashSet};
+
+use super::types::{
+    ImportMode, ImportQuery, ImportResponse, ImportSummary, TenantExport,
+    EXPORT_FORMAT_VERSION,
+};
+use crate::{
+    api::{
+        config::add_config_version,
+        context::{
+            create_ctx_from_put_req, helpers::validate_condition_with_functions,
+            recompute_context_priorities, types::PutReq,
+            validate_dimensions_and_calculate_priority,
+        },
+        default_config::{find_invalid_overrides, validate_default_config},
+        dimension::{get_all_dimension_schema_map, validate_dimension},
+        functions::helpers::decode_base64_to_string,
+    },
+    db::{
+        models::{Context, DefaultConfig, Dimension, Function},
+        schema::{
+            contexts::dsl as ctxt, default_configs::dsl as def_conf,
+            dimensions::dsl as dims, functions::dsl as funcs,
+        },
+    },
+    validation_functions::compile_fn,
+};
+use actix_web::{
+    get, post,
+    web::{Data, Json, Query},
+    Scope,
+};
+use chrono::Utc;
+use diesel::{
+    r2d2::{ConnectionManager, PooledConnection},
+    upsert::excluded,
+    Connection, ExpressionMethods, PgConnection, QueryDsl, RunQueryDsl,
+};
+use serde_json::Value;
+use service_utils::{
+    bad_argument, result as superposition,
+    service::types::{AppState, DbConnection},
+};
+use superposition_types::{SuperpositionUser, User};
+
+type DBConnection = PooledConnection<ConnectionManager<PgConnection>>;
+
+pub fn export_endpoints() -> Scope {
+    Scope::new("").service(export)
+}
+
+pub fn import_endpoints() -> Scope {
+    Scope::new("").service(import)
+}
+
+#[get("")]
+async fn export(db_conn: DbConnection) -> superposition::Result<Json<TenantExport>> {
+    let DbConnection(mut conn) = db_conn;
+
+    let functions = funcs::functions
+        .order(funcs::function_name.asc())
+        .load::<Function>(&mut conn)?;
+    let dimensions = dims::dimensions
+        .order(dims::dimension.asc())
+        .load::<Dimension>(&mut conn)?;
+    let default_configs = def_conf::default_configs
+        .order(def_conf::key.asc())
+        .load::<DefaultConfig>(&mut conn)?;
+    let contexts = ctxt::contexts
+        .order((ctxt::priority.asc(), ctxt::created_at.asc()))
+        .load::<Context>(&mut conn)?;
+
+    Ok(Json(TenantExport {
+        version: EXPORT_FORMAT_VERSION,
+        exported_at: Utc::now(),
+        functions,
+        dimensions,
+        default_configs,
+        contexts,
+    }))
+}
+
+#[post("")]
+async fn import(
+    state: Data<AppState>,
+    query: Query<ImportQuery>,
+    req: Json<TenantExport>,
+    db_conn: DbConnection,
+    user: User,
+) -> superposition::Result<Json<ImportResponse>> {
+    let DbConnection(mut conn) = db_conn;
+    let mode = query.into_inner().mode;
+    let document = req.into_inner();
+
+    if document.version != EXPORT_FORMAT_VERSION {
+        return Err(bad_argument!(
+            "Unsupported export version {}, expected {}",
+            document.version,
+            EXPORT_FORMAT_VERSION
+        ));
+    }
+
+    // functions come first as everything else may refer to them, and the
+    // contexts last as they are validated against the dimensions and default
+    // configs being imported
+    let response = conn.transaction::<_, superposition::AppError, _>(|conn| {
+        let functions = import_functions(document.functions, mode, conn)?;
+        let dimensions =
+            import_dimensions(&state, document.dimensions, mode, conn, &user)?;
+        let default_configs =
+            import_default_configs(&state, document.default_configs, mode, conn, &user)?;
+        let contexts = import_contexts(document.contexts, mode, conn, &user)?;
+        delete_absent(&contexts, &default_configs, &dimensions, &functions, conn)?;
+        validate_remaining_contexts(&dimensions, conn, &user)?;
+        validate_remaining_overrides(&default_configs, conn)?;
+
+        if default_configs.has_changes() || contexts.has_changes() {
+            add_config_version(conn, Some(String::from("Import")), &user)?;
+        }
+        Ok(ImportResponse {
+            mode,
+            functions,
+            dimensions,
+            default_configs,
+            contexts,
+        })
+    })?;
+
+    log::info!("tenant config imported by {}", user.get_email());
+    Ok(Json(response))
+}
+
+/// In replace mode, lists the existing entries that are not part of the
+/// imported document as deleted.
+fn collect_absent(
+    summary: &mut ImportSummary,
+    existing: impl IntoIterator<Item = String>,
+    imported: &HashSet<String>,
+    mode: ImportMode,
+) {
+    if mode == ImportMode::Replace {
+        summary.deleted = existing
+            .into_iter()
+            .filter(|key| !imported.contains(key))
+            .collect();
+        summary.deleted.sort();
+    }
+}
+
+fn import_functions(
+    functions: Vec<Function>,
+    mode: ImportMode,
+    conn: &mut DBConnection,
+) -> superposition::Result<ImportSummary> {
+    let existing = funcs::functions
+        .load::<Function>(conn)?
+        .into_iter()
+        .map(|function| (function.function_name.clone(), function))
+        .collect::<HashMap<String, Function>>();
+    let mut summary = ImportSummary::default();
+    let mut imported = HashSet::new();
+
+    for function in functions {
+        compile_fn(&decode_base64_to_string(&function.draft_code)?)?;
+        if let Some(code) = &function.published_code {
+            compile_fn(&decode_base64_to_string(code)?)?;
+        }
+
+        imported.insert(function.function_name.clone());
+        let unchanged = existing.get(&function.function_name).map(|current| {
+            current.draft_code == function.draft_code
+                && current.draft_runtime_version == function.draft_runtime_version
+                && current.function_description == function.function_description
+                && current.published_code == function.published_code
+                && current.published_runtime_version == function.published_runtime_version
+        });
+        if summary.record(function.function_name.clone(), unchanged) {
+            diesel::insert_into(funcs::functions)
+                .values(&function)
+                .on_conflict(funcs::function_name)
+                .do_update()
+                .set((
+                    funcs::draft_code.eq(excluded(funcs::draft_code)),
+                    funcs::draft_runtime_version
+                        .eq(excluded(funcs::draft_runtime_version)),
+                    funcs::function_description.eq(excluded(funcs::function_description)),
+                    funcs::draft_edited_at.eq(excluded(funcs::draft_edited_at)),
+                    funcs::draft_edited_by.eq(excluded(funcs::draft_edited_by)),
+                    funcs::published_code.eq(excluded(funcs::published_code)),
+                    funcs::published_runtime_version
+                        .eq(excluded(funcs::published_runtime_version)),
+                    funcs::published_at.eq(excluded(funcs::published_at)),
+                    funcs::published_by.eq(excluded(funcs::published_by)),
+                ))
+                .execute(conn)?;
+        }
+    }
+
+    collect_absent(&mut summary, existing.into_keys(), &imported, mode);
+    Ok(summary)
+}
+
+fn import_dimensions(
+    state: &AppState,
+    dimensions: Vec<Dimension>,
+    mode: ImportMode,
+    conn: &mut DBConnection,
+    user: &User,
+) -> superposition::Result<ImportSummary> {
+    let existing = dims::dimensions
+        .load::<Dimension>(conn)?
+        .into_iter()
+        .map(|dimension| (dimension.dimension.clone(), dimension))
+        .collect::<HashMap<String, Dimension>>();
+    let mut summary = ImportSummary::default();
+    let mut imported = HashSet::new();
+
+    for dimension in dimensions {
+        validate_dimension(state, &dimension)?;
+
+        imported.insert(dimension.dimension.clone());
+        let unchanged = existing.get(&dimension.dimension).map(|current| {
+            current.priority == dimension.priority
+                && current.schema == dimension.schema
+                && current.function_name == dimension.function_name
+        });
+        if summary.record(dimension.dimension.clone(), unchanged) {
+            let dimension = Dimension {
+                created_at: Utc::now(),
+                created_by: user.get_email(),
+                ..dimension
+            };
+            diesel::insert_into(dims::dimensions)
+                .values(&dimension)
+                .on_conflict(dims::dimension)
+                .do_update()
+                .set(&dimension)
+                .execute(conn)?;
+        }
+    }
+
+    collect_absent(&mut summary, existing.into_keys(), &imported, mode);
+    Ok(summary)
+}
+
+fn import_default_configs(
+    state: &AppState,
+    default_configs: Vec<DefaultConfig>,
+    mode: ImportMode,
+    conn: &mut DBConnection,
+    user: &User,
+) -> superposition::Result<ImportSummary> {
+    let existing = def_conf::default_configs
+        .load::<DefaultConfig>(conn)?
+        .into_iter()
+        .map(|default_config| (default_config.key.clone(), default_config))
+        .collect::<HashMap<String, DefaultConfig>>();
+    let mut summary = ImportSummary::default();
+    let mut imported = HashSet::new();
+
+    for default_config in default_configs {
+        validate_default_config(state, &default_config, conn)?;
+
+        imported.insert(default_config.key.clone());
+        let unchanged = existing.get(&default_config.key).map(|current| {
+            current.value == default_config.value
+                && current.schema == default_config.schema
+                && current.function_name == default_config.function_name
+        });
+        if summary.record(default_config.key.clone(), unchanged) {
+            let default_config = DefaultConfig {
+                created_at: Utc::now(),
+                created_by: user.get_email(),
+                ..default_config
+            };
+            diesel::insert_into(def_conf::default_configs)
+                .values(&default_config)
+                .on_conflict(def_conf::key)
+                .do_update()
+                .set(&default_config)
+                .execute(conn)?;
+        }
+    }
+
+    collect_absent(&mut summary, existing.into_keys(), &imported, mode);
+    Ok(summary)
+}
+
+fn import_contexts(
+    contexts: Vec<Context>,
+    mode: ImportMode,
+    conn: &mut DBConnection,
+    user: &User,
+) -> superposition::Result<ImportSummary> {
+    let existing = ctxt::contexts
+        .load::<Context>(conn)?
+        .into_iter()
+        .map(|context| (context.id.clone(), context))
+        .collect::<HashMap<String, Context>>();
+    let mut summary = ImportSummary::default();
+    let mut imported = HashSet::new();
+
+    for context in contexts {
+        let (Value::Object(condition), Value::Object(override_)) =
+            (context.value, context.override_)
+        else {
+            return Err(bad_argument!(
+                "Context {} should have an object as its condition and override",
+                context.id
+            ));
+        };
+        // ids and priorities are derived, so they are recomputed for this tenant
+        let context = create_ctx_from_put_req(
+            Json(PutReq {
+                context: condition,
+                r#override: override_,
+            }),
+            conn,
+            user,
+        )?;
+
+        imported.insert(context.id.clone());
+        let unchanged = existing
+            .get(&context.id)
+            .map(|current| current.override_ == context.override_);
+        if summary.record(context.id.clone(), unchanged) {
+            diesel::insert_into(ctxt::contexts)
+                .values(&context)
+                .on_conflict(ctxt::id)
+                .do_update()
+                .set(&context)
+                .execute(conn)?;
+        }
+    }
+
+    collect_absent(&mut summary, existing.into_keys(), &imported, mode);
+    Ok(summary)
+}
+
+/// Checks every context, including the ones left untouched by the import,
+/// against the updated and deleted dimensions, and recomputes the priorities
+/// of the contexts from the imported dimension priorities.
+fn validate_remaining_contexts(
+    dimensions: &ImportSummary,
+    conn: &mut DBConnection,
+    user: &User,
+) -> superposition::Result<()> {
+    if dimensions.updated.is_empty() && dimensions.deleted.is_empty() {
+        return Ok(());
+    }
+
+    let dimension_schema_map = get_all_dimension_schema_map(conn)?;
+    let mut invalid_contexts = Vec::new();
+    for context in ctxt::contexts.load::<Context>(conn)? {
+        let validation = validate_dimensions_and_calculate_priority(
+            "context",
+            &context.value,
+            &dimension_schema_map,
+        )
+        .and_then(|_| validate_condition_with_functions(conn, &context.value));
+        if let Err(err) = validation {
+            log::info!("context {} fails validation: {err}", context.id);
+            invalid_contexts.push(format!("{} ({err})", context.id));
+        }
+    }
+    if !invalid_contexts.is_empty() {
+        return Err(bad_argument!(
+            "Existing contexts do not match the imported dimensions: {}",
+            invalid_contexts.join(", ")
+        ));
+    }
+
+    recompute_context_priorities(conn, user)?;
+    Ok(())
+}
+
+/// Checks the overrides of the updated default configs, including the ones of
+/// contexts left untouched by the import, against their imported schemas.
+fn validate_remaining_overrides(
+    default_configs: &ImportSummary,
+    conn: &mut DBConnection,
+) -> superposition::Result<()> {
+    let updated = def_conf::default_configs
+        .filter(def_conf::key.eq_any(&default_configs.updated))
+        .load::<DefaultConfig>(conn)?;
+    for default_config in updated {
+        let invalid_overrides =
+            find_invalid_overrides(&default_config.key, &default_config.schema, conn)?;
+        if !invalid_overrides.is_empty() {
+            return Err(bad_argument!(
+                "The imported schema of {} does not match its overrides in contexts: {}",
+                default_config.key,
+                invalid_overrides
+                    .iter()
+                    .map(|invalid| invalid.context_id.as_str())
+                    .collect::<Vec<&str>>()
+                    .join(",")
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Deletes the entries listed as deleted, dependents before what they refer
+/// to.
+fn delete_absent(
+    contexts: &ImportSummary,
+    default_configs: &ImportSummary,
+    dimensions: &ImportSummary,
+    functions: &ImportSummary,
+    conn: &mut DBConnection,
+) -> superposition::Result<()> {
+    diesel::delete(ctxt::contexts.filter(ctxt::id.eq_any(&contexts.deleted)))
+        .execute(conn)?;
+    diesel::delete(
+        def_conf::default_configs.filter(def_conf::key.eq_any(&default_configs.deleted)),
+    )
+    .execute(conn)?;
+    diesel::delete(dims::dimensions.filter(dims::dimension.eq_any(&dimensions.deleted)))
+        .execute(conn)?;
+    diesel::delete(
+        funcs::functions.filter(funcs::function_name.eq_any(&functions.deleted)),
+    )
+    .execute(conn)?;
+    Ok(())
+}
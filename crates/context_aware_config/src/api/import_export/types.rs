@@ -0,0 +1,68 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::db::models::{Context, DefaultConfig, Dimension, Function};
+
+/// Bumped whenever the layout of [`TenantExport`] changes incompatibly.
+pub const EXPORT_FORMAT_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+pub struct TenantExport {
+    pub version: u32,
+    pub exported_at: DateTime<Utc>,
+    pub functions: Vec<Function>,
+    pub dimensions: Vec<Dimension>,
+    pub default_configs: Vec<DefaultConfig>,
+    pub contexts: Vec<Context>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportMode {
+    #[default]
+    Merge,
+    Replace,
+}
+
+#[derive(Deserialize)]
+pub struct ImportQuery {
+    #[serde(default)]
+    pub mode: ImportMode,
+}
+
+#[derive(Serialize, Default)]
+pub struct ImportSummary {
+    pub created: Vec<String>,
+    pub updated: Vec<String>,
+    pub skipped: Vec<String>,
+    pub deleted: Vec<String>,
+}
+
+impl ImportSummary {
+    /// Records the outcome for `key`, `unchanged` being `None` when the entry
+    /// does not exist yet. Returns whether the entry has to be written.
+    pub fn record(&mut self, key: String, unchanged: Option<bool>) -> bool {
+        match unchanged {
+            None => self.created.push(key),
+            Some(false) => self.updated.push(key),
+            Some(true) => {
+                self.skipped.push(key);
+                return false;
+            }
+        }
+        true
+    }
+
+    pub fn has_changes(&self) -> bool {
+        !(self.created.is_empty() && self.updated.is_empty() && self.deleted.is_empty())
+    }
+}
+
+#[derive(Serialize)]
+pub struct ImportResponse {
+    pub mode: ImportMode,
+    pub functions: ImportSummary,
+    pub dimensions: ImportSummary,
+    pub default_configs: ImportSummary,
+    pub contexts: ImportSummary,
+}
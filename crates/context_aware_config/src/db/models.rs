@@ -1,10 +1,14 @@
-use crate::db::schema::{contexts, default_configs, dimensions, event_log, functions};
+use crate::db::schema::{
+    config_versions, contexts, default_configs, dimensions, event_log, functions,
+};
 use chrono::{offset::Utc, DateTime, NaiveDateTime};
 use diesel::{AsChangeset, Insertable, Queryable, Selectable};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-#[derive(Queryable, Selectable, Insertable, AsChangeset, Clone, Serialize, Debug)]
+#[derive(
+    Queryable, Selectable, Insertable, AsChangeset, Clone, Serialize, Deserialize, Debug,
+)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 #[diesel(primary_key(id))]
 pub struct Context {
@@ -14,11 +18,13 @@ pub struct Context {
     pub created_at: DateTime<Utc>,
     pub created_by: String,
     pub priority: i32,
-    #[serde(rename(serialize = "override"))]
+    #[serde(rename = "override")]
     pub override_: Value,
 }
 
-#[derive(Queryable, Selectable, Insertable, AsChangeset, Serialize)]
+#[derive(
+    Queryable, Selectable, Insertable, AsChangeset, Serialize, Deserialize, Clone,
+)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 #[diesel(primary_key(dimension))]
 #[diesel(treat_none_as_null = true)]
@@ -31,7 +37,9 @@ pub struct Dimension {
     pub function_name: Option<String>,
 }
 
-#[derive(Queryable, Selectable, Insertable, AsChangeset, Serialize, Clone)]
+#[derive(
+    Queryable, Selectable, Insertable, AsChangeset, Serialize, Deserialize, Clone,
+)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 #[diesel(primary_key(key))]
 #[diesel(treat_none_as_null = true)]
@@ -44,7 +52,9 @@ pub struct DefaultConfig {
     pub function_name: Option<String>,
 }
 
-#[derive(Queryable, Selectable, Insertable, AsChangeset, Serialize, Clone, Debug)]
+#[derive(
+    Queryable, Selectable, Insertable, AsChangeset, Serialize, Deserialize, Clone, Debug,
+)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 #[diesel(primary_key(name))]
 pub struct Function {
@@ -74,3 +84,15 @@ pub struct EventLog {
     pub new_data: Option<Value>,
     pub query: String,
 }
+
+#[derive(Queryable, Selectable, Serialize, Clone, Debug)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+#[diesel(primary_key(id))]
+pub struct ConfigVersion {
+    pub id: i64,
+    pub contexts: Value,
+    pub default_configs: Value,
+    pub description: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub created_by: String,
+}
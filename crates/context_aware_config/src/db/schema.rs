@@ -1,5 +1,16 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    config_versions (id) {
+        id -> Int8,
+        contexts -> Json,
+        default_configs -> Json,
+        description -> Nullable<Text>,
+        created_at -> Timestamptz,
+        created_by -> Varchar,
+    }
+}
+
 diesel::table! {
     contexts (id) {
         id -> Varchar,
@@ -602,6 +613,7 @@ diesel::joinable!(default_configs -> functions (function_name));
 diesel::joinable!(dimensions -> functions (function_name));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    config_versions,
     contexts,
     default_configs,
     dimensions,
@@ -1,9 +1,16 @@
-use actix_web::http::header::{HeaderMap, HeaderName, HeaderValue};
+use actix_web::{
+    http::{
+        header::{self, HeaderMap, HeaderName, HeaderValue},
+        StatusCode,
+    },
+    HttpRequest,
+};
 use itertools::{self, Itertools};
 use jsonschema::{Draft, JSONSchema, ValidationError};
 use serde_json::{json, Value};
 use service_utils::{
-    helpers::validation_err_to_str, result as superposition, validation_error,
+    bad_argument, helpers::validation_err_to_str, response_error,
+    result as superposition, validation_error,
 };
 use std::collections::HashMap;
 
@@ -253,6 +260,37 @@ pub fn json_to_sorted_string(v: &Value) -> String {
     }
 }
 
+/// Checks the `If-Match` header of `req`, when present, against
+/// `current_tag`, the entity tag of the resource being written or `None` if
+/// it does not exist. Fails with `412 Precondition Failed` when none of the
+/// listed tags match, so concurrent edits are not silently overwritten.
+pub fn validate_if_match(
+    req: &HttpRequest,
+    current_tag: Option<&str>,
+) -> superposition::Result<()> {
+    let Some(if_match) = req.headers().get(header::IF_MATCH) else {
+        return Ok(());
+    };
+    let if_match = if_match
+        .to_str()
+        .map_err(|_| bad_argument!("Invalid If-Match header"))?;
+
+    let matched = current_tag.is_some_and(|current_tag| {
+        if_match.split(',').map(str::trim).any(|tag| {
+            tag == "*" || tag.trim_start_matches("W/").trim_matches('"') == current_tag
+        })
+    });
+    if matched {
+        Ok(())
+    } else {
+        log::info!("If-Match {if_match} did not match the current tag {current_tag:?}");
+        Err(response_error!(
+            StatusCode::PRECONDITION_FAILED,
+            "The resource was modified since it was last read, fetch it again and retry."
+        ))
+    }
+}
+
 pub fn calculate_context_priority(
     object_key: &str,
     cond: &Value,
@@ -279,10 +317,20 @@ pub fn calculate_context_priority(
         Value::Object(x) => x.iter().try_fold(0, |acc, (key, val)| {
             get_priority(key, val).map(|res| res + acc)
         }),
-        Value::Array(arr) => arr.iter().try_fold(0, |acc, item| {
-            calculate_context_priority(object_key, item, dimension_schema_map)
-                .map(|res| res + acc)
-        }),
+        Value::Array(arr) => {
+            let priorities = arr
+                .iter()
+                .map(|item| {
+                    calculate_context_priority(object_key, item, dimension_schema_map)
+                })
+                .collect::<Result<Vec<i32>, String>>()?;
+            // an `or` is only as specific as its least specific branch
+            if object_key == "or" {
+                Ok(priorities.into_iter().min().unwrap_or_default())
+            } else {
+                Ok(priorities.into_iter().sum())
+            }
+        }
         _ => Ok(0),
     }
 }
@@ -422,4 +470,29 @@ mod tests {
         assert_eq!(err_arr_context, true);
         assert_eq!(ok_arr_context.unwrap(), ());
     }
+
+    #[test]
+    fn test_validate_if_match() {
+        let request = |if_match: Option<&str>| {
+            let mut req = actix_web::test::TestRequest::default();
+            if let Some(if_match) = if_match {
+                req = req.insert_header((header::IF_MATCH, if_match));
+            }
+            req.to_http_request()
+        };
+        let matches = |if_match: Option<&str>, current_tag: Option<&str>| {
+            validate_if_match(&request(if_match), current_tag).is_ok()
+        };
+
+        assert!(matches(None, Some("abc")));
+        assert!(matches(None, None));
+        assert!(matches(Some("\"abc\""), Some("abc")));
+        assert!(matches(Some("W/\"abc\""), Some("abc")));
+        assert!(matches(Some("\"xyz\", W/\"abc\""), Some("abc")));
+        assert!(matches(Some("\"xyz\",\"abc\""), Some("abc")));
+        assert!(!matches(Some("\"xyz\", W/\"uvw\""), Some("abc")));
+        assert!(matches(Some("*"), Some("abc")));
+        assert!(!matches(Some("*"), None));
+        assert!(!matches(Some("\"abc\""), None));
+    }
 }
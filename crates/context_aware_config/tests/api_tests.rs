@@ -0,0 +1,1080 @@
+// These tests call the API against a postgres database holding the schemas of
+// the `test` tenant (see `make test-tenant`), read from DATABASE_URL. They are
+// ignored by default, run them with
+// `cargo test -p context_aware_config --test api_tests -- --ignored`
+use std::{
+    collections::HashSet,
+    sync::{Mutex, OnceLock},
+    thread,
+    time::Duration,
+};
+
+use actix_web::{
+    dev::Service,
+    http::{header, StatusCode},
+    test::{self, TestRequest},
+    web::{scope, Data},
+    App, HttpMessage,
+};
+use chrono::Utc;
+use context_aware_config::{
+    api::{
+        config,
+        config::add_config_version,
+        context, default_config, dimension,
+        import_export::{self, types::EXPORT_FORMAT_VERSION},
+    },
+    db::{
+        models::{ConfigVersion, Context, DefaultConfig},
+        schema::{
+            config_versions::dsl as cv, contexts::dsl as ctxt,
+            default_configs::dsl as def_conf,
+        },
+    },
+    helpers::{get_default_config_validation_schema, get_meta_schema},
+};
+use diesel::{Connection, ExpressionMethods, QueryDsl, RunQueryDsl};
+use futures::lock::Mutex as AsyncMutex;
+use serde_json::{json, Value};
+use service_utils::{
+    db::pgschema_manager::{ConnectionConfig, PgSchemaManager},
+    middlewares::{
+        app_scope::AppExecutionScopeMiddlewareFactory, tenant::TenantMiddlewareFactory,
+    },
+    result as superposition,
+    service::types::{
+        AppEnv, AppScope, AppState, BanditConfig, ExperimentApprovalConfig,
+        ExperimentationFlags, WebhookConfig,
+    },
+};
+use snowflake::SnowflakeIdGenerator;
+use superposition_types::User;
+
+const TENANT: &str = "test";
+const CAC_SCHEMA: &str = "test_cac";
+
+// rolling back replaces every context and default config of the tenant, so
+// the tests here take turns instead of running alongside each other
+async fn exclusive() -> futures::lock::MutexGuard<'static, ()> {
+    static LOCK: OnceLock<AsyncMutex<()>> = OnceLock::new();
+    LOCK.get_or_init(|| AsyncMutex::new(())).lock().await
+}
+
+fn app_state() -> Data<AppState> {
+    dotenv::dotenv().ok();
+    let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL is not set");
+    let db_pool = PgSchemaManager::from(
+        [CAC_SCHEMA, "test_experimentation"]
+            .into_iter()
+            .map(|namespace| {
+                ConnectionConfig::new(
+                    namespace.to_string(),
+                    database_url.clone(),
+                    namespace.to_string(),
+                    5,
+                )
+            })
+            .collect::<Vec<ConnectionConfig>>(),
+    );
+    Data::new(AppState {
+        cac_host: "http://localhost:8080".to_string(),
+        cac_in_process: true,
+        app_env: AppEnv::TEST,
+        tenants: HashSet::from([TENANT.to_string()]),
+        cac_version: "v0.1.0".to_string(),
+        db_pool,
+        default_config_validation_schema: get_default_config_validation_schema(),
+        meta_schema: get_meta_schema(),
+        experimentation_flags: ExperimentationFlags {
+            allow_same_keys_overlapping_ctx: true,
+            allow_diff_keys_overlapping_ctx: true,
+            allow_same_keys_non_overlapping_ctx: true,
+        },
+        experiment_approval: ExperimentApprovalConfig {
+            enabled: false,
+            ramp_threshold: 0,
+        },
+        webhook: WebhookConfig {
+            max_attempts: 1,
+            retry_backoff_secs: 0,
+        },
+        bandit: BanditConfig {
+            min_weight: 5,
+            samples: 100,
+        },
+        snowflake_generator: Mutex::new(SnowflakeIdGenerator::new(1, 1)),
+        enable_tenant_and_scope: true,
+        tenant_middleware_exclusion_list: HashSet::new(),
+        service_prefix: String::new(),
+    })
+}
+
+macro_rules! test_app {
+    ($state:expr) => {
+        test::init_service(
+            App::new()
+                .wrap_fn(|req, srv| {
+                    req.extensions_mut().insert(User::default());
+                    srv.call(req)
+                })
+                .wrap(TenantMiddlewareFactory)
+                .app_data($state)
+                .service(
+                    scope("/context")
+                        .wrap(AppExecutionScopeMiddlewareFactory::new(AppScope::CAC))
+                        .service(context::endpoints()),
+                )
+                .service(
+                    scope("/dimension")
+                        .wrap(AppExecutionScopeMiddlewareFactory::new(AppScope::CAC))
+                        .service(dimension::endpoints()),
+                )
+                .service(
+                    scope("/default-config")
+                        .wrap(AppExecutionScopeMiddlewareFactory::new(AppScope::CAC))
+                        .service(default_config::endpoints()),
+                )
+                .service(
+                    scope("/config")
+                        .wrap(AppExecutionScopeMiddlewareFactory::new(AppScope::CAC))
+                        .service(config::endpoints()),
+                )
+                .service(
+                    scope("/import")
+                        .wrap(AppExecutionScopeMiddlewareFactory::new(AppScope::CAC))
+                        .service(import_export::import_endpoints()),
+                ),
+        )
+        .await
+    };
+}
+
+// returns the status, the json body and the config version header
+macro_rules! send {
+    ($app:expr, $req:expr) => {{
+        let resp = test::call_service(
+            &$app,
+            $req.insert_header(("x-tenant", TENANT)).to_request(),
+        )
+        .await;
+        let status = resp.status();
+        let version = resp
+            .headers()
+            .get("x-config-version")
+            .and_then(|version| version.to_str().ok())
+            .and_then(|version| version.parse::<i64>().ok());
+        let body = test::read_body(resp).await;
+        (
+            status,
+            serde_json::from_slice::<Value>(&body).unwrap_or(Value::Null),
+            version,
+        )
+    }};
+}
+
+macro_rules! put_dimension {
+    ($app:expr, $name:expr, $priority:expr) => {{
+        let (status, body, _) = send!(
+            $app,
+            TestRequest::put().uri("/dimension").set_json(json!({
+                "dimension": $name,
+                "priority": $priority,
+                "schema": {"type": "string", "pattern": ".*"}
+            }))
+        );
+        assert!(status.is_success(), "{body}");
+    }};
+}
+
+macro_rules! put_default_config {
+    ($app:expr, $key:expr, $value:expr) => {{
+        let (status, body, _) = send!(
+            $app,
+            TestRequest::put()
+                .uri(&format!("/default-config/{}", $key))
+                .set_json(json!({
+                    "value": $value,
+                    "schema": {"type": "string", "pattern": ".*"}
+                }))
+        );
+        assert!(status.is_success(), "{body}");
+    }};
+}
+
+// returns the id of the context
+macro_rules! put_context {
+    ($app:expr, $condition:expr, $overrides:expr) => {{
+        let (status, body, _) = send!(
+            $app,
+            TestRequest::put().uri("/context").set_json(json!({
+                "context": $condition,
+                "override": $overrides
+            }))
+        );
+        assert_eq!(status, StatusCode::OK, "{body}");
+        body["context_id"].as_str().unwrap().to_string()
+    }};
+}
+
+macro_rules! latest_version {
+    ($app:expr) => {{
+        let (status, _, version) = send!($app, TestRequest::get().uri("/config"));
+        assert_eq!(status, StatusCode::OK);
+        version.expect("config is not versioned")
+    }};
+}
+
+// returns the entity tag `GET $uri` responds with
+macro_rules! etag {
+    ($app:expr, $uri:expr) => {{
+        let resp = test::call_service(
+            &$app,
+            TestRequest::get()
+                .uri($uri)
+                .insert_header(("x-tenant", TENANT))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        resp.headers()
+            .get(header::ETAG)
+            .and_then(|tag| tag.to_str().ok())
+            .expect("no ETag in the response")
+            .to_string()
+    }};
+}
+
+fn unique(prefix: &str) -> String {
+    format!("{}-{}", prefix, Utc::now().timestamp_micros())
+}
+
+fn condition(dimension: &str, value: &str) -> Value {
+    json!({"==": [{"var": dimension}, value]})
+}
+
+fn context_ids(config: &Value) -> Vec<&str> {
+    config["contexts"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|context| context["id"].as_str().unwrap())
+        .collect()
+}
+
+#[actix_web::test]
+#[ignore = "needs a database"]
+async fn test_config_versions_include_concurrent_changes() {
+    let _turn = exclusive().await;
+    let state = app_state();
+    let keys = (0..4)
+        .map(|i| unique(&format!("concurrentKey{i}")))
+        .collect::<Vec<String>>();
+
+    // every change keeps its transaction open for a while after taking its
+    // version, for the others to snapshot the rows in the meantime
+    let versions = thread::scope(|scope| {
+        let handles = keys
+            .iter()
+            .map(|key| {
+                let state = &state;
+                scope.spawn(move || {
+                    let mut conn =
+                        state.db_pool.get_conn(CAC_SCHEMA.to_string()).unwrap();
+                    conn.transaction::<_, superposition::AppError, _>(|conn| {
+                        diesel::insert_into(def_conf::default_configs)
+                            .values(DefaultConfig {
+                                key: key.clone(),
+                                value: json!("value"),
+                                created_at: Utc::now(),
+                                created_by: User::default().email,
+                                schema: json!({"type": "string"}),
+                                function_name: None,
+                            })
+                            .execute(conn)?;
+                        let version = add_config_version(conn, None, &User::default())?;
+                        thread::sleep(Duration::from_millis(300));
+                        Ok(version)
+                    })
+                    .unwrap()
+                })
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect::<Vec<i64>>()
+    });
+
+    let mut conn = state.db_pool.get_conn(CAC_SCHEMA.to_string()).unwrap();
+    let versions = cv::config_versions
+        .filter(cv::id.eq_any(&versions))
+        .order_by(cv::id.asc())
+        .load::<ConfigVersion>(&mut conn)
+        .unwrap();
+    let mut recorded: HashSet<String> = HashSet::new();
+    for version in versions {
+        let version_keys = version
+            .default_configs
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|default_config| default_config["key"].as_str())
+            .filter(|key| keys.iter().any(|k| k == key))
+            .map(String::from)
+            .collect::<HashSet<String>>();
+        assert!(
+            version_keys.is_superset(&recorded),
+            "version {} misses changes recorded before it",
+            version.id
+        );
+        assert_eq!(version_keys.len(), recorded.len() + 1);
+        recorded = version_keys;
+    }
+    assert_eq!(recorded.len(), keys.len());
+}
+
+#[actix_web::test]
+#[ignore = "needs a database"]
+async fn test_config_at_version() {
+    let _turn = exclusive().await;
+    let app = test_app!(app_state());
+    let key = unique("versionedKey");
+
+    put_default_config!(app, key, "first");
+    let first = latest_version!(app);
+    put_default_config!(app, key, "second");
+    let second = latest_version!(app);
+    assert!(second > first);
+
+    for (version, value) in [(first, "first"), (second, "second")] {
+        let (status, config, served_version) = send!(
+            app,
+            TestRequest::get().uri(&format!("/config?version={version}"))
+        );
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(served_version, Some(version));
+        assert_eq!(config["default_configs"][&key], value);
+    }
+
+    let (status, _, _) = send!(app, TestRequest::get().uri("/config?version=-1"));
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+#[ignore = "needs a database"]
+async fn test_rollback() {
+    let _turn = exclusive().await;
+    let app = test_app!(app_state());
+    let dimension = unique("rollbackDimension");
+    let key = unique("rollbackKey");
+    put_dimension!(app, dimension, 10);
+    put_default_config!(app, key, "before");
+    let restored_context = put_context!(
+        app,
+        condition(&dimension, "restored"),
+        json!({&key: "context"})
+    );
+    let target = latest_version!(app);
+
+    put_default_config!(app, key, "after");
+    let (status, _, _) = send!(
+        app,
+        TestRequest::delete().uri(&format!("/context/{restored_context}"))
+    );
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let added_context =
+        put_context!(app, condition(&dimension, "added"), json!({&key: "added"}));
+    let (status, body, _) = send!(
+        app,
+        TestRequest::put()
+            .uri(&format!("/dimension/{dimension}"))
+            .set_json(json!({"priority": 37}))
+    );
+    assert_eq!(status, StatusCode::OK, "{body}");
+
+    let (status, rollback, _) = send!(
+        app,
+        TestRequest::put().uri(&format!("/config/versions/{target}/rollback"))
+    );
+    assert_eq!(status, StatusCode::OK, "{rollback}");
+    assert!(rollback["version"].as_i64().unwrap() > target);
+    assert_eq!(
+        rollback["description"],
+        format!("Rollback to version {target}")
+    );
+
+    let (_, config, version) = send!(app, TestRequest::get().uri("/config"));
+    assert_eq!(version, rollback["version"].as_i64());
+    assert_eq!(config["default_configs"][&key], "before");
+    let ids = context_ids(&config);
+    assert!(ids.contains(&restored_context.as_str()));
+    assert!(!ids.contains(&added_context.as_str()));
+
+    // the restored context is prioritised by the dimension as it is now
+    let (status, context, _) = send!(
+        app,
+        TestRequest::get().uri(&format!("/context/{restored_context}"))
+    );
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(context["priority"], 37);
+}
+
+#[actix_web::test]
+#[ignore = "needs a database"]
+async fn test_rollback_validates_restored_contexts() {
+    let _turn = exclusive().await;
+    let app = test_app!(app_state());
+    let dimension = unique("deletedDimension");
+    let key = unique("rollbackKey");
+    put_dimension!(app, dimension, 10);
+    put_default_config!(app, key, "value");
+    let context = put_context!(app, condition(&dimension, "value"), json!({&key: "x"}));
+    let target = latest_version!(app);
+
+    let (status, body, _) = send!(
+        app,
+        TestRequest::delete().uri(&format!("/dimension/{dimension}?cascade=true"))
+    );
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let latest = latest_version!(app);
+
+    let (status, body, _) = send!(
+        app,
+        TestRequest::put().uri(&format!("/config/versions/{target}/rollback"))
+    );
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(
+        body["message"].as_str().unwrap().contains(&dimension),
+        "{body}"
+    );
+
+    let (_, config, version) = send!(app, TestRequest::get().uri("/config"));
+    assert_eq!(version, Some(latest));
+    assert!(!context_ids(&config).contains(&context.as_str()));
+}
+
+#[actix_web::test]
+#[ignore = "needs a database"]
+async fn test_rollback_keeps_experiment_contexts() {
+    let _turn = exclusive().await;
+    let app = test_app!(app_state());
+    let dimension = unique("experimentDimension");
+    let key = unique("rollbackKey");
+    put_dimension!(app, dimension, 10);
+    put_default_config!(app, key, "value");
+    let target = latest_version!(app);
+
+    let experiment_context = put_context!(
+        app,
+        json!({
+            "and": [
+                condition(&dimension, "value"),
+                condition("variantIds", &unique("experiment-test"))
+            ]
+        }),
+        json!({&key: "test"})
+    );
+
+    let rollback_uri = format!("/config/versions/{target}/rollback");
+    let (status, body, _) = send!(app, TestRequest::put().uri(&rollback_uri));
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(
+        body["message"]
+            .as_str()
+            .unwrap()
+            .contains(&experiment_context),
+        "{body}"
+    );
+    let (_, config, _) = send!(app, TestRequest::get().uri("/config"));
+    assert!(context_ids(&config).contains(&experiment_context.as_str()));
+
+    let (status, body, _) = send!(
+        app,
+        TestRequest::put().uri(&format!("{rollback_uri}?force=true"))
+    );
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let (_, config, _) = send!(app, TestRequest::get().uri("/config"));
+    assert!(!context_ids(&config).contains(&experiment_context.as_str()));
+}
+
+fn dimension_import(dimension: &str, priority: i32, schema: Value) -> Value {
+    json!({
+        "version": EXPORT_FORMAT_VERSION,
+        "exported_at": Utc::now(),
+        "functions": [],
+        "dimensions": [{
+            "dimension": dimension,
+            "priority": priority,
+            "created_at": Utc::now(),
+            "created_by": User::default().email,
+            "schema": schema,
+            "function_name": null
+        }],
+        "default_configs": [],
+        "contexts": []
+    })
+}
+
+#[actix_web::test]
+#[ignore = "needs a database"]
+async fn test_merge_import_revalidates_contexts() {
+    let _turn = exclusive().await;
+    let app = test_app!(app_state());
+    let dimension = unique("importedDimension");
+    let key = unique("importKey");
+    put_dimension!(app, dimension, 10);
+    put_default_config!(app, key, "value");
+    let context = put_context!(app, condition(&dimension, "value"), json!({&key: "x"}));
+
+    // the context is not part of the document, but no longer matches the schema
+    let (status, body, _) = send!(
+        app,
+        TestRequest::post()
+            .uri("/import")
+            .set_json(dimension_import(
+                &dimension,
+                10,
+                json!({"type": "string", "enum": ["other"]})
+            ))
+    );
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(
+        body["message"].as_str().unwrap().contains(&context),
+        "{body}"
+    );
+
+    let (status, body, _) = send!(
+        app,
+        TestRequest::post()
+            .uri("/import")
+            .set_json(dimension_import(
+                &dimension,
+                23,
+                json!({"type": "string", "pattern": ".*"})
+            ))
+    );
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["dimensions"]["updated"], json!([dimension]));
+
+    let (status, body, _) =
+        send!(app, TestRequest::get().uri(&format!("/context/{context}")));
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["priority"], 23);
+}
+
+#[actix_web::test]
+#[ignore = "needs a database"]
+async fn test_bulk_operations_dry_run() {
+    let _turn = exclusive().await;
+    let app = test_app!(app_state());
+    let dimension = unique("bulkDimension");
+    let key = unique("bulkKey");
+    put_dimension!(app, dimension, 10);
+    put_default_config!(app, key, "value");
+    let deleted = put_context!(app, condition(&dimension, "deleted"), json!({&key: "x"}));
+    let (_, config_before, version_before) =
+        send!(app, TestRequest::get().uri("/config"));
+
+    let (status, dry_run, _) = send!(
+        app,
+        TestRequest::put()
+            .uri("/context/bulk-operations?dry_run=true")
+            .set_json(json!([
+                {"PUT": {
+                    "context": condition(&dimension, "added"),
+                    "override": {&key: "y"}
+                }},
+                {"DELETE": deleted}
+            ]))
+    );
+    assert_eq!(status, StatusCode::OK, "{dry_run}");
+    let added = dry_run["operations"][0]["PUT"]["context_id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    assert_eq!(
+        dry_run["operations"][1]["DELETE"],
+        format!("{deleted} deleted succesfully")
+    );
+    assert_eq!(dry_run["contexts"]["added"][0]["id"], added);
+    assert_eq!(dry_run["contexts"]["removed"][0]["id"], deleted);
+    assert_eq!(
+        dry_run["overrides"]["added"][0]["after"],
+        json!({&key: "y"})
+    );
+    assert_eq!(
+        dry_run["overrides"]["removed"][0]["before"],
+        json!({&key: "x"})
+    );
+
+    let (_, config_after, version_after) = send!(app, TestRequest::get().uri("/config"));
+    assert_eq!(version_after, version_before);
+    assert_eq!(context_ids(&config_after), context_ids(&config_before));
+    assert!(context_ids(&config_after).contains(&deleted.as_str()));
+    assert!(!context_ids(&config_after).contains(&added.as_str()));
+}
+
+#[actix_web::test]
+#[ignore = "needs a database"]
+async fn test_bulk_operations_dry_run_reports_failing_action() {
+    let _turn = exclusive().await;
+    let app = test_app!(app_state());
+    let key = unique("bulkKey");
+    put_default_config!(app, key, "value");
+    let missing_dimension = unique("missingDimension");
+    let missing_context = unique("missingContext");
+
+    let (status, body, _) = send!(
+        app,
+        TestRequest::put()
+            .uri("/context/bulk-operations?dry_run=true")
+            .set_json(json!([{"PUT": {
+                "context": condition(&missing_dimension, "value"),
+                "override": {&key: "y"}
+            }}]))
+    );
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(
+        body["message"]
+            .as_str()
+            .unwrap()
+            .contains(&missing_dimension),
+        "{body}"
+    );
+
+    let (status, body, _) = send!(
+        app,
+        TestRequest::put()
+            .uri("/context/bulk-operations?dry_run=true")
+            .set_json(json!([{"DELETE": missing_context}]))
+    );
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(
+        body["message"],
+        format!("context with id {missing_context} not found")
+    );
+}
+
+#[actix_web::test]
+#[ignore = "needs a database"]
+async fn test_list_context_filters() {
+    let _turn = exclusive().await;
+    let app = test_app!(app_state());
+    let dimension = unique("listDimension");
+    let (first, second) = (unique("firstKey"), unique("secondKey"));
+    put_dimension!(app, dimension, 7);
+    put_default_config!(app, first, "value");
+    put_default_config!(app, second, "value");
+    let equals = put_context!(
+        app,
+        condition(&dimension, "chennai"),
+        json!({&first: "x", &second: "y"})
+    );
+    let within = put_context!(
+        app,
+        json!({"in": [{"var": &dimension}, ["delhi", "mumbai"]]}),
+        json!({&first: "z"})
+    );
+
+    let list = |query: String| TestRequest::get().uri(&format!("/context/list?{query}"));
+    let cases = [
+        (format!("dimensions={dimension}"), vec![&equals, &within]),
+        (format!("dimensions={dimension}=chennai"), vec![&equals]),
+        (format!("dimensions={dimension}=mumbai"), vec![&within]),
+        (format!("dimensions={dimension}=pune"), vec![]),
+        (
+            format!("dimensions={dimension}&override_keys={first},{second}"),
+            vec![&equals],
+        ),
+        (
+            format!("dimensions={dimension}&min_priority=7&max_priority=7"),
+            vec![&equals, &within],
+        ),
+        (format!("dimensions={dimension}&min_priority=8"), vec![]),
+        (format!("dimensions={dimension}&max_priority=6"), vec![]),
+    ];
+    for (query, expected) in cases {
+        let (status, body, _) = send!(app, list(query.clone()));
+        assert_eq!(status, StatusCode::OK, "{body}");
+        let mut ids = body["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|context| context["id"].as_str().unwrap().to_string())
+            .collect::<Vec<String>>();
+        let mut expected = expected.into_iter().cloned().collect::<Vec<String>>();
+        ids.sort();
+        expected.sort();
+        assert_eq!(ids, expected, "{query}");
+        assert_eq!(body["total_items"], expected.len(), "{query}");
+    }
+
+    let (status, body, _) = send!(
+        app,
+        list(format!(
+            "dimensions={dimension}&size=1&page=2&sort_on=created_at"
+        ))
+    );
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["total_pages"], 2);
+    assert_eq!(body["data"][0]["id"], within);
+}
+
+macro_rules! get_dimension {
+    ($app:expr, $name:expr) => {{
+        let (status, body, _) = send!($app, TestRequest::get().uri("/dimension"));
+        assert_eq!(status, StatusCode::OK);
+        body.as_array()
+            .unwrap()
+            .iter()
+            .find(|dimension| dimension["dimension"] == $name)
+            .cloned()
+    }};
+}
+
+#[actix_web::test]
+#[ignore = "needs a database"]
+async fn test_upserting_a_dimension_updates_it() {
+    let _turn = exclusive().await;
+    let app = test_app!(app_state());
+    let dimension = unique("upsertedDimension");
+    let key = unique("upsertKey");
+    put_dimension!(app, dimension, 10);
+    put_default_config!(app, key, "value");
+    let context = put_context!(app, condition(&dimension, "value"), json!({&key: "x"}));
+    let created = get_dimension!(app, dimension).unwrap();
+
+    let (status, body, _) = send!(
+        app,
+        TestRequest::put().uri("/dimension").set_json(json!({
+            "dimension": dimension,
+            "priority": 10,
+            "schema": {"type": "string", "enum": ["other"]}
+        }))
+    );
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(
+        body["message"].as_str().unwrap().contains(&context),
+        "{body}"
+    );
+    assert_eq!(get_dimension!(app, dimension).unwrap(), created);
+
+    put_dimension!(app, dimension, 19);
+    let upserted = get_dimension!(app, dimension).unwrap();
+    assert_eq!(upserted["priority"], 19);
+    assert_eq!(upserted["created_at"], created["created_at"]);
+    assert_eq!(upserted["created_by"], created["created_by"]);
+    let (_, body, _) = send!(app, TestRequest::get().uri(&format!("/context/{context}")));
+    assert_eq!(body["priority"], 19);
+
+    let (status, body, _) = send!(
+        app,
+        TestRequest::put()
+            .uri(&format!("/dimension/{dimension}"))
+            .set_json(json!({"priority": 21}))
+    );
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["dimension"]["created_at"], created["created_at"]);
+    assert_eq!(body["dimension"]["created_by"], created["created_by"]);
+    assert_eq!(body["priority_changes"][0]["id"], context);
+    assert_eq!(body["priority_changes"][0]["new_priority"], 21);
+}
+
+#[actix_web::test]
+#[ignore = "needs a database"]
+async fn test_delete_dimension() {
+    let _turn = exclusive().await;
+    let app = test_app!(app_state());
+    let dimension = unique("deletedDimension");
+    let key = unique("deleteKey");
+    put_dimension!(app, dimension, 10);
+    put_default_config!(app, key, "value");
+    let context = put_context!(app, condition(&dimension, "value"), json!({&key: "x"}));
+    let version = latest_version!(app);
+    let delete_uri = format!("/dimension/{dimension}");
+
+    // refused while contexts use the dimension
+    let (status, body, _) = send!(app, TestRequest::delete().uri(&delete_uri));
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(
+        body["message"].as_str().unwrap().contains(&context),
+        "{body}"
+    );
+    assert!(get_dimension!(app, dimension).is_some());
+    assert_eq!(latest_version!(app), version);
+
+    let (status, body, _) = send!(
+        app,
+        TestRequest::delete().uri(&format!("{delete_uri}?cascade=true"))
+    );
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["deleted_contexts"], json!([context]));
+    assert!(get_dimension!(app, dimension).is_none());
+    let (status, _, _) =
+        send!(app, TestRequest::get().uri(&format!("/context/{context}")));
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert!(latest_version!(app) > version);
+
+    // a dimension no context uses is deleted without a new version
+    let unused = unique("unusedDimension");
+    put_dimension!(app, unused, 10);
+    let version = latest_version!(app);
+    let (status, body, _) = send!(
+        app,
+        TestRequest::delete().uri(&format!("/dimension/{unused}"))
+    );
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["deleted_contexts"], json!([]));
+    assert_eq!(latest_version!(app), version);
+
+    let (status, _, _) = send!(
+        app,
+        TestRequest::delete().uri(&format!("/dimension/{unused}"))
+    );
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+#[ignore = "needs a database"]
+async fn test_narrowing_a_default_config_schema() {
+    let _turn = exclusive().await;
+    let app = test_app!(app_state());
+    let dimension = unique("schemaDimension");
+    let key = unique("narrowedKey");
+    put_dimension!(app, dimension, 10);
+    put_default_config!(app, key, "a");
+    let too_long = put_context!(
+        app,
+        condition(&dimension, "long"),
+        json!({&key: "too long"})
+    );
+    let short = put_context!(app, condition(&dimension, "short"), json!({&key: "ab"}));
+    let version = latest_version!(app);
+    let narrowed =
+        json!({"value": "a", "schema": {"type": "string", "pattern": "^.{0,3}$"}});
+    let uri = format!("/default-config/{key}");
+
+    let (status, dry_run, _) = send!(
+        app,
+        TestRequest::put()
+            .uri(&format!("{uri}?dry_run=true"))
+            .set_json(&narrowed)
+    );
+    assert_eq!(status, StatusCode::OK, "{dry_run}");
+    let invalid_overrides = dry_run["invalid_overrides"].as_array().unwrap();
+    assert_eq!(invalid_overrides.len(), 1, "{dry_run}");
+    assert_eq!(invalid_overrides[0]["context_id"], too_long);
+    assert!(!invalid_overrides[0]["error"].as_str().unwrap().is_empty());
+    assert_eq!(dry_run["default_config"]["schema"], narrowed["schema"]);
+    assert_eq!(latest_version!(app), version);
+
+    let (status, body, _) = send!(app, TestRequest::put().uri(&uri).set_json(&narrowed));
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let message = body["message"].as_str().unwrap();
+    assert!(message.contains(&too_long), "{message}");
+    assert!(!message.contains(&short), "{message}");
+    assert_eq!(latest_version!(app), version);
+
+    let (_, default_config, _) = send!(app, TestRequest::get().uri(&uri));
+    assert_eq!(
+        default_config["schema"],
+        json!({"type": "string", "pattern": ".*"})
+    );
+}
+
+#[actix_web::test]
+#[ignore = "needs a database"]
+async fn test_unchanged_default_config_schema_skips_override_check() {
+    let _turn = exclusive().await;
+    let state = app_state();
+    let app = test_app!(state.clone());
+    let key = unique("unchangedKey");
+    put_default_config!(app, key, "a");
+
+    // an override that no longer matches the schema, as the API would not
+    // let one in
+    let invalid = unique("invalidContext");
+    let mut conn = state.db_pool.get_conn(CAC_SCHEMA.to_string()).unwrap();
+    diesel::insert_into(ctxt::contexts)
+        .values(Context {
+            id: invalid.clone(),
+            value: condition("variantIds", &invalid),
+            override_id: invalid.clone(),
+            created_at: Utc::now(),
+            created_by: User::default().email,
+            priority: 1,
+            override_: json!({&key: 42}),
+        })
+        .execute(&mut conn)
+        .unwrap();
+
+    let uri = format!("/default-config/{key}");
+    let (status, dry_run, _) = send!(
+        app,
+        TestRequest::put()
+            .uri(&format!("{uri}?dry_run=true"))
+            .set_json(json!({"value": "b"}))
+    );
+    assert_eq!(status, StatusCode::OK, "{dry_run}");
+    assert_eq!(dry_run["invalid_overrides"], json!([]));
+
+    let (status, body, _) = send!(
+        app,
+        TestRequest::put().uri(&uri).set_json(json!({"value": "b"}))
+    );
+    assert_eq!(status, StatusCode::OK, "{body}");
+
+    // while any change to the schema is checked
+    let (status, body, _) = send!(
+        app,
+        TestRequest::put()
+            .uri(&uri)
+            .set_json(json!({"schema": {"type": "string", "pattern": ".+"}}))
+    );
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(
+        body["message"].as_str().unwrap().contains(&invalid),
+        "{body}"
+    );
+
+    diesel::delete(ctxt::contexts.filter(ctxt::id.eq(&invalid)))
+        .execute(&mut conn)
+        .unwrap();
+}
+
+#[actix_web::test]
+#[ignore = "needs a database"]
+async fn test_context_writes_check_if_match() {
+    let _turn = exclusive().await;
+    let app = test_app!(app_state());
+    let dimension = unique("ifMatchDimension");
+    let key = unique("ifMatchKey");
+    put_dimension!(app, dimension, 10);
+    put_default_config!(app, key, "default");
+    let ctx_id = put_context!(app, condition(&dimension, "a"), json!({&key: "first"}));
+    let tag = etag!(app, &format!("/context/{ctx_id}"));
+    let stale = "\"stale\"";
+    let put = |overrides: Value| {
+        TestRequest::put().uri("/context").set_json(json!({
+            "context": condition(&dimension, "a"),
+            "override": overrides
+        }))
+    };
+
+    let (status, _, _) = send!(
+        app,
+        put(json!({&key: "stale"})).insert_header((header::IF_MATCH, stale))
+    );
+    assert_eq!(status, StatusCode::PRECONDITION_FAILED);
+    let (_, ctx, _) = send!(app, TestRequest::get().uri(&format!("/context/{ctx_id}")));
+    assert_eq!(ctx["override"], json!({&key: "first"}));
+
+    // a matching tag is accepted, among others and weak or not
+    let (status, body, _) = send!(
+        app,
+        put(json!({&key: "second"}))
+            .insert_header((header::IF_MATCH, format!("{stale}, W/{tag}")))
+    );
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let new_tag = format!("\"{}\"", body["override_id"].as_str().unwrap());
+    assert_ne!(new_tag, tag);
+
+    let move_to = |value: &str| {
+        TestRequest::put()
+            .uri(&format!("/context/move/{ctx_id}"))
+            .set_json(json!({"context": condition(&dimension, value)}))
+    };
+    let (status, _, _) = send!(
+        app,
+        move_to("b").insert_header((header::IF_MATCH, tag.as_str()))
+    );
+    assert_eq!(status, StatusCode::PRECONDITION_FAILED);
+    let (status, moved, _) = send!(
+        app,
+        move_to("b").insert_header((header::IF_MATCH, new_tag.as_str()))
+    );
+    assert_eq!(status, StatusCode::OK, "{moved}");
+    let moved_id = moved["context_id"].as_str().unwrap().to_string();
+
+    let delete = |id: &str| TestRequest::delete().uri(&format!("/context/{id}"));
+    let (status, _, _) = send!(
+        app,
+        delete(&moved_id).insert_header((header::IF_MATCH, stale))
+    );
+    assert_eq!(status, StatusCode::PRECONDITION_FAILED);
+    let (status, _, _) = send!(
+        app,
+        delete(&moved_id).insert_header((header::IF_MATCH, "*"))
+    );
+    assert!(status.is_success(), "{status}");
+
+    // `*` requires the context to exist
+    let (status, _, _) = send!(
+        app,
+        delete(&moved_id).insert_header((header::IF_MATCH, "*"))
+    );
+    assert_eq!(status, StatusCode::PRECONDITION_FAILED);
+    let (status, _, _) = send!(
+        app,
+        put(json!({&key: "new"})).insert_header((header::IF_MATCH, "*"))
+    );
+    assert_eq!(status, StatusCode::PRECONDITION_FAILED);
+}
+
+#[actix_web::test]
+#[ignore = "needs a database"]
+async fn test_default_config_writes_check_if_match() {
+    let _turn = exclusive().await;
+    let app = test_app!(app_state());
+    let key = unique("ifMatchKey");
+    let uri = format!("/default-config/{key}");
+    put_default_config!(app, key, "first");
+    let tag = etag!(app, &uri);
+    let put = |value: &str| {
+        TestRequest::put().uri(&uri).set_json(json!({
+            "value": value,
+            "schema": {"type": "string", "pattern": ".*"}
+        }))
+    };
+
+    let (status, _, _) = send!(
+        app,
+        put("stale").insert_header((header::IF_MATCH, "\"stale\""))
+    );
+    assert_eq!(status, StatusCode::PRECONDITION_FAILED);
+    let (_, default_config, _) = send!(app, TestRequest::get().uri(&uri));
+    assert_eq!(default_config["value"], "first");
+
+    let (status, body, _) = send!(
+        app,
+        put("second").insert_header((header::IF_MATCH, tag.as_str()))
+    );
+    assert_eq!(status, StatusCode::OK, "{body}");
+
+    // the tag read before the update is stale now
+    let (status, _, _) = send!(
+        app,
+        put("third").insert_header((header::IF_MATCH, tag.as_str()))
+    );
+    assert_eq!(status, StatusCode::PRECONDITION_FAILED);
+    let (status, _, _) = send!(
+        app,
+        TestRequest::delete()
+            .uri(&uri)
+            .insert_header((header::IF_MATCH, tag.as_str()))
+    );
+    assert_eq!(status, StatusCode::PRECONDITION_FAILED);
+
+    let new_tag = etag!(app, &uri);
+    let (status, _, _) = send!(
+        app,
+        TestRequest::delete()
+            .uri(&uri)
+            .insert_header((header::IF_MATCH, new_tag.as_str()))
+    );
+    assert!(status.is_success(), "{status}");
+
+    // `*` requires the default config to exist
+    let (status, _, _) = send!(app, put("new").insert_header((header::IF_MATCH, "*")));
+    assert_eq!(status, StatusCode::PRECONDITION_FAILED);
+}
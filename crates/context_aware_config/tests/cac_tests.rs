@@ -1,5 +1,15 @@
+use chrono::{Duration, Utc};
+use context_aware_config::api::context::{
+    helpers::analyze_contexts,
+    types::{AmbiguousOrder, DefaultValueOverride, ShadowedOverride},
+};
+use context_aware_config::api::dimension::condition_uses_dimension;
+use context_aware_config::api::import_export::types::{
+    TenantExport, EXPORT_FORMAT_VERSION,
+};
+use context_aware_config::db::models::{Context, DefaultConfig, Dimension, Function};
 use context_aware_config::validation_functions::{compile_fn, execute_fn};
-use serde_json::json;
+use serde_json::{json, Map, Value};
 use service_utils::result as superposition;
 
 // #[test] //todo : currently there is issue in running this test
@@ -40,3 +50,167 @@ fn test_execute_fn() {
     assert_eq!(compile_fn(&(code_ok.to_owned())).unwrap(), ());
     assert_eq!(err_compile, true);
 }
+
+fn context(id: &str, condition: Value, priority: i32, override_: Value) -> Context {
+    Context {
+        id: id.to_string(),
+        value: condition,
+        override_id: format!("{id}-override"),
+        created_at: Utc::now(),
+        created_by: "user@superposition.io".to_string(),
+        priority,
+        override_,
+    }
+}
+
+#[test]
+fn test_analyze_contexts() {
+    let city_in = context(
+        "city_in",
+        json!({"in": [{"var": "city"}, ["Bangalore", "Chennai"]]}),
+        2,
+        json!({"base_rate": 10, "surge": true}),
+    );
+    let bangalore = Context {
+        created_at: Utc::now() - Duration::hours(1),
+        ..context(
+            "bangalore",
+            json!({"==": [{"var": "city"}, "Bangalore"]}),
+            2,
+            json!({"base_rate": 20}),
+        )
+    };
+    let chennai_default = context(
+        "chennai_default",
+        json!({"and": [
+            {"==": [{"var": "city"}, "Chennai"]},
+            {"==": [{"var": "vehicle"}, "cab"]}
+        ]}),
+        6,
+        json!({"surge": false, "base_rate": 15, "max_distance": 5}),
+    );
+    let complex = context(
+        "complex",
+        json!({"or": [{"==": [{"var": "city"}, "Delhi"]}]}),
+        2,
+        json!({"base_rate": 30}),
+    );
+    let default_configs = Map::from_iter([
+        ("base_rate".to_string(), json!(15)),
+        ("surge".to_string(), json!(false)),
+        ("max_distance".to_string(), json!(5)),
+    ]);
+
+    let analysis = analyze_contexts(
+        &[bangalore, city_in, complex, chennai_default],
+        &default_configs,
+    );
+
+    // whenever `bangalore` matches so does `city_in`, which is applied later
+    assert_eq!(
+        analysis.shadowed_overrides,
+        vec![ShadowedOverride {
+            context_id: "bangalore".to_string(),
+            key: "base_rate".to_string(),
+            shadowed_by: "city_in".to_string(),
+        }]
+    );
+    assert_eq!(analysis.unreachable_contexts, vec!["bangalore".to_string()]);
+    assert_eq!(
+        analysis.ambiguous_orders,
+        vec![AmbiguousOrder {
+            priority: 2,
+            context_ids: ["bangalore".to_string(), "city_in".to_string()],
+            keys: vec!["base_rate".to_string()],
+        }]
+    );
+    // `surge` and `base_rate` undo earlier overrides, `max_distance` changes nothing
+    assert_eq!(
+        analysis.default_value_overrides,
+        vec![DefaultValueOverride {
+            context_id: "chennai_default".to_string(),
+            key: "max_distance".to_string(),
+            value: json!(5),
+        }]
+    );
+    assert_eq!(analysis.skipped_contexts, vec!["complex".to_string()]);
+}
+
+#[test]
+fn test_tenant_export_round_trip() {
+    let now = Utc::now();
+    let export = TenantExport {
+        version: EXPORT_FORMAT_VERSION,
+        exported_at: now,
+        functions: vec![Function {
+            function_name: "validate_city".to_string(),
+            published_code: None,
+            draft_code: "YXN5bmMgZnVuY3Rpb24gdmFsaWRhdGUoKSB7IHJldHVybiB0cnVlOyB9"
+                .to_string(),
+            function_description: "checks the city".to_string(),
+            published_runtime_version: None,
+            draft_runtime_version: "1.0".to_string(),
+            published_at: None,
+            draft_edited_at: now.naive_utc(),
+            published_by: None,
+            draft_edited_by: "user@superposition.io".to_string(),
+        }],
+        dimensions: vec![Dimension {
+            dimension: "city".to_string(),
+            priority: 4,
+            created_at: now,
+            created_by: "user@superposition.io".to_string(),
+            schema: json!({"type": "string", "pattern": ".*"}),
+            function_name: Some("validate_city".to_string()),
+        }],
+        default_configs: vec![DefaultConfig {
+            key: "base_rate".to_string(),
+            value: json!(10),
+            created_at: now,
+            created_by: "user@superposition.io".to_string(),
+            schema: json!({"type": "number"}),
+            function_name: None,
+        }],
+        contexts: vec![context(
+            "bangalore",
+            json!({"==": [{"var": "city"}, "Bangalore"]}),
+            4,
+            json!({"base_rate": 20}),
+        )],
+    };
+
+    let document = serde_json::to_string(&export).unwrap();
+    let imported = serde_json::from_str::<TenantExport>(&document).unwrap();
+    assert_eq!(
+        serde_json::to_value(imported).unwrap(),
+        serde_json::to_value(&export).unwrap()
+    );
+}
+
+#[test]
+fn test_condition_uses_dimension() {
+    let condition = json!({
+        "and": [
+            {"==": [{"var": "os"}, "android"]},
+            {"or": [
+                {"in": [{"var": "city"}, ["Delhi", "Mumbai"]]},
+                {"!": {"==": [{"var": "clientId"}, "zee5"]}}
+            ]},
+            {"!": [{"<=": [100, {"var": "appVersion"}, 200]}]}
+        ]
+    });
+    for dimension in ["os", "city", "clientId", "appVersion"] {
+        assert!(
+            condition_uses_dimension(&condition, dimension),
+            "{dimension}"
+        );
+    }
+
+    // values and operators are not dimensions, nor are prefixes of a name
+    for dimension in ["android", "Delhi", "zee5", "or", "var", "app"] {
+        assert!(
+            !condition_uses_dimension(&condition, dimension),
+            "{dimension}"
+        );
+    }
+}
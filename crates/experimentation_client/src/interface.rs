@@ -1,4 +1,5 @@
 use std::{
+    collections::HashMap,
     ffi::{c_char, c_ulong, CStr},
     sync::Arc,
 };
@@ -195,6 +196,53 @@ pub extern "C" fn get_applicable_variant(
         .unwrap_or_else(|err| error_block(err.to_string()))
 }
 
+#[no_mangle]
+pub extern "C" fn get_variant_assignments(
+    client: *mut Arc<Client>,
+    c_context: *const c_char,
+    toss: c_short,
+) -> *mut c_char {
+    let context = match cstring_to_rstring(c_context) {
+        Ok(c) => match serde_json::from_str::<Value>(c.as_str()) {
+            Ok(con) => con,
+            Err(err) => return error_block(err.to_string()),
+        },
+        Err(err) => return error_block(err),
+    };
+    let local = task::LocalSet::new();
+    let assignments_result = local.block_on(&Runtime::new().unwrap(), unsafe {
+        (*client).get_variant_assignments(&context, toss as i8)
+    });
+    assignments_result
+        .map(|result| {
+            serde_json::to_string(&result)
+                .map(|json| rstring_to_cstring(json).into_raw())
+                .unwrap_or_else(|err| error_block(err.to_string()))
+        })
+        .unwrap_or_else(|err| error_block(err.to_string()))
+}
+
+#[no_mangle]
+pub extern "C" fn set_forced_variants(
+    client: *mut Arc<Client>,
+    c_forced_variants: *const c_char,
+) -> c_int {
+    let forced_variants = match cstring_to_rstring(c_forced_variants).and_then(|c| {
+        serde_json::from_str::<HashMap<String, String>>(c.as_str()).map_err(to_string)
+    }) {
+        Ok(forced_variants) => forced_variants,
+        Err(err) => {
+            update_last_error(err);
+            return 1;
+        }
+    };
+    let local = task::LocalSet::new();
+    local.block_on(&Runtime::new().unwrap(), unsafe {
+        (*client).set_forced_variants(forced_variants)
+    });
+    0
+}
+
 #[no_mangle]
 pub extern "C" fn get_satisfied_experiments(
     client: *mut Arc<Client>,
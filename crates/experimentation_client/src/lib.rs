@@ -13,14 +13,27 @@ use tokio::{
     sync::RwLock,
     time::{self, Duration},
 };
-pub use types::{Config, Experiment, Experiments, Variants};
-use types::{ExperimentStore, ListExperimentsResponse, Variant, VariantType};
+pub use types::{Config, Experiment, Experiments, VariantAssignment, Variants};
+use types::{ExperimentStore, Holdout, ListExperimentsResponse, Variant, VariantType};
 use utils::MapError;
 
+/// Context key listing variant ids that should be returned for their
+/// experiments regardless of traffic, e.g. `{"forcedVariants": ["7123-test"]}`,
+/// meant for testers to pin a variant on their device.
+pub const FORCED_VARIANTS_KEY: &str = "forcedVariants";
+
+/// Context key identifying the unit, e.g. the user, being bucketed. Layers
+/// and holdouts bucket units by hashing it, which keeps them independent of
+/// each other, units without one are bucketed by their toss.
+pub const UNIT_ID_KEY: &str = "unitId";
+
 #[derive(Clone, Debug)]
 pub struct Client {
     pub client_config: Arc<Config>,
     pub(crate) experiments: Arc<RwLock<ExperimentStore>>,
+    pub(crate) holdouts: Arc<RwLock<Vec<Holdout>>>,
+    // experiment id to the variant id forced for it on this client
+    pub(crate) forced_variants: Arc<RwLock<HashMap<String, String>>>,
     pub(crate) http_client: reqwest::Client,
     last_polled: Arc<RwLock<DateTime<Utc>>>,
 }
@@ -33,6 +46,8 @@ impl Client {
         Client {
             client_config: Arc::new(config),
             experiments: Arc::new(RwLock::new(HashMap::new())),
+            holdouts: Arc::new(RwLock::new(Vec::new())),
+            forced_variants: Arc::new(RwLock::new(HashMap::new())),
             http_client: reqwest::Client::new(),
             last_polled: Arc::new(RwLock::new(
                 Utc.with_ymd_and_hms(2023, 01, 1, 0, 0, 0).unwrap(),
@@ -69,27 +84,94 @@ impl Client {
                     };
                 }
             } // write lock on exp store releases here
+
+            // holdouts are few, so all of them are fetched on every poll
+            match get_holdouts(
+                hostname.clone(),
+                self.http_client.clone(),
+                self.client_config.tenant.to_string(),
+            )
+            .await
+            {
+                Ok(holdouts) => *self.holdouts.write().await = holdouts,
+                Err(err) => log::error!("failed to fetch holdouts: {}", err),
+            }
             *start_date = Utc::now();
             interval.tick().await;
         }
     }
 
+    /// Forces variants on this client, keyed by experiment id, replacing the
+    /// ones forced before. Variants forced through the context with
+    /// `FORCED_VARIANTS_KEY` take precedence over these.
+    pub async fn set_forced_variants(&self, forced_variants: HashMap<String, String>) {
+        *self.forced_variants.write().await = forced_variants;
+    }
+
     pub async fn get_applicable_variant(
         &self,
         context: &Value,
         toss: i8,
     ) -> Result<Vec<String>, String> {
+        let assignments = self.get_variant_assignments(context, toss).await?;
+        Ok(assignments
+            .into_iter()
+            .map(|assignment| assignment.variant_id)
+            .collect())
+    }
+
+    /// Same as `get_applicable_variant`, along with the experiment of each
+    /// variant and whether it was forced rather than picked by the toss.
+    pub async fn get_variant_assignments(
+        &self,
+        context: &Value,
+        toss: i8,
+    ) -> Result<Vec<VariantAssignment>, String> {
         let experiments: Experiments =
             self.get_satisfied_experiments(context, None).await?;
-        let mut variants: Vec<String> = Vec::new();
+        let holdouts = self.holdouts.read().await;
+        let client_forced = self.forced_variants.read().await;
+        let context_forced = forced_variants_in_context(context);
+        let unit_id = unit_id_in_context(context);
+        let mut assignments: Vec<VariantAssignment> = Vec::new();
         for exp in experiments {
+            let forced = exp
+                .variants
+                .iter()
+                .find(|variant| context_forced.contains(&variant.id))
+                .or_else(|| {
+                    exp.variants
+                        .iter()
+                        .find(|variant| client_forced.get(&exp.id) == Some(&variant.id))
+                });
+            if let Some(variant) = forced {
+                assignments.push(VariantAssignment {
+                    experiment_id: exp.id.clone(),
+                    variant_id: variant.id.clone(),
+                    forced: true,
+                });
+                continue;
+            }
+            if holdouts
+                .iter()
+                .any(|holdout| holdout.holds_out(&exp, toss, unit_id.as_deref()))
+            {
+                continue;
+            }
+            let Some(toss) = exp.toss_in_layer(toss, unit_id.as_deref()) else {
+                continue;
+            };
             if let Some(v) =
                 self.decide_variant(exp.traffic_percentage, exp.variants, toss)?
             {
-                variants.push(v.id)
+                assignments.push(VariantAssignment {
+                    experiment_id: exp.id,
+                    variant_id: v.id,
+                    forced: false,
+                });
             }
         }
-        Ok(variants)
+        Ok(assignments)
     }
 
     pub async fn get_satisfied_experiments(
@@ -98,10 +180,12 @@ impl Client {
         prefix: Option<Vec<String>>,
     ) -> Result<Experiments, String> {
         let running_experiments = self.experiments.read().await;
+        let now = Utc::now();
         let filtered_running_experiments = running_experiments
             .iter()
             .filter(|(_, exp)| {
-                jsonlogic::apply(&exp.context, context) == Ok(Value::Bool(true))
+                exp.is_live_at(&now)
+                    && jsonlogic::apply(&exp.context, context) == Ok(Value::Bool(true))
             })
             .map(|(_, exp)| exp.clone())
             .collect::<Experiments>();
@@ -157,7 +241,12 @@ impl Client {
 
     pub async fn get_running_experiments(&self) -> Result<Experiments, String> {
         let running_experiments = self.experiments.read().await;
-        let experiments: Experiments = running_experiments.values().cloned().collect();
+        let now = Utc::now();
+        let experiments: Experiments = running_experiments
+            .values()
+            .filter(|exp| exp.is_live_at(&now))
+            .cloned()
+            .collect();
         Ok(experiments)
     }
 
@@ -180,6 +269,19 @@ impl Client {
         if (toss as i32) >= range {
             return Ok(None);
         }
+        if applicable_variants
+            .iter()
+            .all(|variant| variant.weight.is_some())
+        {
+            let mut cumulative_weight = 0;
+            for variant in applicable_variants.iter() {
+                cumulative_weight += variant.weight.unwrap_or_default() as i32;
+                if (toss as i32) * 100 < range * cumulative_weight {
+                    return Ok(Some(variant.clone()));
+                }
+            }
+            return Ok(None);
+        }
         let buckets = (1..=variant_count)
             .map(|i| (traffic * i) as i8)
             .collect::<Vec<i8>>();
@@ -192,6 +294,27 @@ impl Client {
     }
 }
 
+fn forced_variants_in_context(context: &Value) -> Vec<String> {
+    context
+        .get(FORCED_VARIANTS_KEY)
+        .and_then(Value::as_array)
+        .map(|variant_ids| {
+            variant_ids
+                .iter()
+                .filter_map(|variant_id| variant_id.as_str().map(String::from))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn unit_id_in_context(context: &Value) -> Option<String> {
+    match context.get(UNIT_ID_KEY)? {
+        Value::String(unit_id) => Some(unit_id.clone()),
+        Value::Number(unit_id) => Some(unit_id.to_string()),
+        _ => None,
+    }
+}
+
 async fn get_experiments(
     hostname: String,
     http_client: reqwest::Client,
@@ -231,6 +354,22 @@ async fn get_experiments(
     Ok(curr_exp_store)
 }
 
+async fn get_holdouts(
+    hostname: String,
+    http_client: reqwest::Client,
+    tenant: String,
+) -> Result<Vec<Holdout>, String> {
+    http_client
+        .get(format!("{hostname}/experiments/holdouts"))
+        .header("x-tenant", tenant)
+        .send()
+        .await
+        .map_err_to_string()?
+        .json::<Vec<Holdout>>()
+        .await
+        .map_err_to_string()
+}
+
 #[derive(Deref, DerefMut)]
 pub struct ClientFactory(RwLock<HashMap<String, Arc<Client>>>);
 impl ClientFactory {
@@ -268,3 +407,276 @@ impl ClientFactory {
 use once_cell::sync::Lazy;
 pub static CLIENT_FACTORY: Lazy<ClientFactory> =
     Lazy::new(|| ClientFactory(RwLock::new(HashMap::new())));
+
+// ************ Tests *************
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn experiment(id: &str, traffic_percentage: u8) -> Experiment {
+        Experiment {
+            variants: vec![
+                Variant {
+                    id: format!("{id}-control"),
+                    overrides: json!({"key": "control"}),
+                    variant_type: VariantType::CONTROL,
+                    weight: None,
+                },
+                Variant {
+                    id: format!("{id}-test"),
+                    overrides: json!({"key": "test"}),
+                    variant_type: VariantType::EXPERIMENTAL,
+                    weight: None,
+                },
+            ],
+            name: id.to_string(),
+            id: id.to_string(),
+            traffic_percentage,
+            context: json!({"==": [{"var": "os"}, "android"]}),
+            status: types::ExperimentStatusType::INPROGRESS,
+            start_at: None,
+            end_at: None,
+            layer: None,
+            layer_offset: None,
+            layer_allocation: None,
+        }
+    }
+
+    async fn client_with(experiments: Vec<Experiment>) -> Client {
+        let client = Client::new(Config {
+            tenant: "test".to_string(),
+            hostname: "http://localhost:8080".to_string(),
+            poll_frequency: 10,
+        });
+        *client.experiments.write().await = experiments
+            .into_iter()
+            .map(|exp| (exp.id.clone(), exp))
+            .collect();
+        client
+    }
+
+    fn assignment_of(
+        assignments: &[VariantAssignment],
+        experiment_id: &str,
+    ) -> Option<(String, bool)> {
+        assignments
+            .iter()
+            .find(|assignment| assignment.experiment_id == experiment_id)
+            .map(|assignment| (assignment.variant_id.clone(), assignment.forced))
+    }
+
+    #[tokio::test]
+    async fn test_bucketed_variant_is_not_forced() {
+        let client = client_with(vec![experiment("exp", 50)]).await;
+        let assignments = client
+            .get_variant_assignments(&json!({"os": "android"}), 10)
+            .await
+            .unwrap();
+        assert_eq!(
+            assignment_of(&assignments, "exp"),
+            Some(("exp-control".to_string(), false))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_context_forced_variant_takes_precedence_over_client() {
+        let client = client_with(vec![experiment("exp", 50)]).await;
+        client
+            .set_forced_variants(HashMap::from([(
+                "exp".to_string(),
+                "exp-control".to_string(),
+            )]))
+            .await;
+
+        let context = json!({"os": "android", FORCED_VARIANTS_KEY: ["exp-test"]});
+        let assignments = client.get_variant_assignments(&context, 10).await.unwrap();
+        assert_eq!(
+            assignment_of(&assignments, "exp"),
+            Some(("exp-test".to_string(), true))
+        );
+
+        // without the context key the client map applies
+        let assignments = client
+            .get_variant_assignments(&json!({"os": "android"}), 10)
+            .await
+            .unwrap();
+        assert_eq!(
+            assignment_of(&assignments, "exp"),
+            Some(("exp-control".to_string(), true))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_forced_variant_bypasses_traffic() {
+        let client = client_with(vec![experiment("exp", 0)]).await;
+        let assignments = client
+            .get_variant_assignments(&json!({"os": "android"}), 10)
+            .await
+            .unwrap();
+        assert_eq!(assignment_of(&assignments, "exp"), None);
+
+        let context = json!({"os": "android", FORCED_VARIANTS_KEY: ["exp-test"]});
+        let assignments = client.get_variant_assignments(&context, 10).await.unwrap();
+        assert_eq!(
+            assignment_of(&assignments, "exp"),
+            Some(("exp-test".to_string(), true))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_forced_variant_bypasses_holdouts() {
+        let client = client_with(vec![experiment("exp", 50)]).await;
+        *client.holdouts.write().await = vec![Holdout {
+            name: "everyone".to_string(),
+            layer: None,
+            percentage: 100,
+            enabled: true,
+        }];
+        let assignments = client
+            .get_variant_assignments(&json!({"os": "android"}), 10)
+            .await
+            .unwrap();
+        assert_eq!(assignment_of(&assignments, "exp"), None);
+
+        let context = json!({"os": "android", FORCED_VARIANTS_KEY: ["exp-test"]});
+        let assignments = client.get_variant_assignments(&context, 10).await.unwrap();
+        assert_eq!(
+            assignment_of(&assignments, "exp"),
+            Some(("exp-test".to_string(), true))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_forced_variant_bypasses_layers() {
+        // an experiment owning none of its layer's buckets gets no units
+        let client = client_with(vec![Experiment {
+            layer: Some("checkout".to_string()),
+            layer_offset: Some(0),
+            layer_allocation: Some(0),
+            ..experiment("exp", 50)
+        }])
+        .await;
+        let assignments = client
+            .get_variant_assignments(&json!({"os": "android"}), 10)
+            .await
+            .unwrap();
+        assert_eq!(assignment_of(&assignments, "exp"), None);
+
+        client
+            .set_forced_variants(HashMap::from([(
+                "exp".to_string(),
+                "exp-test".to_string(),
+            )]))
+            .await;
+        let assignments = client
+            .get_variant_assignments(&json!({"os": "android"}), 10)
+            .await
+            .unwrap();
+        assert_eq!(
+            assignment_of(&assignments, "exp"),
+            Some(("exp-test".to_string(), true))
+        );
+    }
+
+    #[test]
+    fn test_unit_buckets_of_layers_are_independent() {
+        // the units falling in each decile of one layer should spread evenly
+        // over the deciles of another layer, checked with a chi-squared test
+        let units = 10_000;
+        let mut counts = [[0_u32; 10]; 10];
+        for unit in 0..units {
+            let unit_id = format!("user-{unit}");
+            let checkout =
+                types::unit_bucket(types::LAYER_SALT, "checkout", &unit_id) / 10;
+            let search = types::unit_bucket(types::LAYER_SALT, "search", &unit_id) / 10;
+            counts[checkout as usize][search as usize] += 1;
+        }
+        let expected = units as f64 / 100.0;
+        let chi_squared: f64 = counts
+            .iter()
+            .flatten()
+            .map(|&count| (count as f64 - expected).powi(2) / expected)
+            .sum();
+        // 99.9th percentile of the chi-squared distribution with 81 degrees
+        // of freedom
+        assert!(chi_squared < 128.0, "chi-squared {chi_squared}");
+    }
+
+    #[tokio::test]
+    async fn test_layers_assign_units_independently() {
+        // both experiments own half of their layer, so a unit is in both about
+        // a quarter of the time when the layers are orthogonal, while with a
+        // shared toss every unit would be in both or in neither
+        let in_layer = |id: &str, layer: &str| Experiment {
+            layer: Some(layer.to_string()),
+            layer_offset: Some(0),
+            layer_allocation: Some(50),
+            ..experiment(id, 50)
+        };
+        let client = client_with(vec![
+            in_layer("checkout-exp", "checkout"),
+            in_layer("search-exp", "search"),
+        ])
+        .await;
+        let units = 10_000;
+        let (mut checkout, mut search, mut both) = (0, 0, 0);
+        for unit in 0..units {
+            let context = json!({"os": "android", UNIT_ID_KEY: format!("user-{unit}")});
+            let assignments = client.get_variant_assignments(&context, 10).await.unwrap();
+            let in_checkout = assignment_of(&assignments, "checkout-exp").is_some();
+            let in_search = assignment_of(&assignments, "search-exp").is_some();
+            checkout += in_checkout as u32;
+            search += in_search as u32;
+            both += (in_checkout && in_search) as u32;
+        }
+        let share = |count: u32| count as f64 / units as f64;
+        assert!((share(checkout) - 0.5).abs() < 0.02, "checkout {checkout}");
+        assert!((share(search) - 0.5).abs() < 0.02, "search {search}");
+        assert!(
+            (share(both) - share(checkout) * share(search)).abs() < 0.02,
+            "both {both}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_holdout_keeps_out_its_share_of_a_layer_slice() {
+        let client = client_with(vec![Experiment {
+            layer: Some("checkout".to_string()),
+            layer_offset: Some(20),
+            layer_allocation: Some(30),
+            ..experiment("exp", 50)
+        }])
+        .await;
+        *client.holdouts.write().await = vec![Holdout {
+            name: "global".to_string(),
+            layer: None,
+            percentage: 10,
+            enabled: true,
+        }];
+
+        // every unit in the experiment's slice is assigned a variant unless
+        // it is held out
+        let (mut in_slice, mut held_out) = (0, 0);
+        for unit in 0..20_000 {
+            let unit_id = format!("user-{unit}");
+            if !(20..50).contains(&types::unit_bucket(
+                types::LAYER_SALT,
+                "checkout",
+                &unit_id,
+            )) {
+                continue;
+            }
+            in_slice += 1;
+            let context = json!({"os": "android", UNIT_ID_KEY: unit_id});
+            let assignments = client.get_variant_assignments(&context, 10).await.unwrap();
+            held_out += assignment_of(&assignments, "exp").is_none() as u32;
+        }
+        let held_out_share = held_out as f64 / in_slice as f64;
+        assert!(
+            (held_out_share - 0.1).abs() < 0.015,
+            "held out {held_out} of {in_slice}"
+        );
+    }
+}
@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
@@ -29,6 +30,10 @@ pub struct Variant {
     pub id: String,
     pub overrides: Value,
     pub(crate) variant_type: VariantType,
+    // percentage of the experiment's traffic the variant gets, only set on
+    // bandit experiments once their traffic has been reallocated
+    #[serde(default)]
+    pub weight: Option<u8>,
 }
 
 pub type Variants = Vec<Variant>;
@@ -42,10 +47,145 @@ pub struct Experiment {
     pub(crate) traffic_percentage: u8,
     pub(crate) context: Value,
     pub(crate) status: ExperimentStatusType,
+    #[serde(default)]
+    pub(crate) start_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub(crate) end_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub(crate) layer: Option<String>,
+    #[serde(default)]
+    pub(crate) layer_offset: Option<u8>,
+    #[serde(default)]
+    pub(crate) layer_allocation: Option<u8>,
+}
+
+impl Experiment {
+    // the server starts and ends scheduled experiments on its own, but only
+    // learns about it on the next poll, so the window is checked locally too
+    pub(crate) fn is_live_at(&self, time: &DateTime<Utc>) -> bool {
+        self.start_at.map_or(true, |start_at| start_at <= *time)
+            && self.end_at.map_or(true, |end_at| *time < end_at)
+    }
+
+    // experiments in a layer own a slice of the layer's buckets, the toss is
+    // only meaningful to the experiment when the unit lands in that slice
+    pub(crate) fn toss_in_layer(&self, toss: i8, unit_id: Option<&str>) -> Option<i8> {
+        match (&self.layer, self.layer_offset, self.layer_allocation) {
+            (Some(layer), Some(offset), Some(allocation)) if toss >= 0 => {
+                let bucket = match unit_id {
+                    Some(unit_id) => unit_bucket(LAYER_SALT, layer, unit_id),
+                    None => layer_bucket(layer, toss as u8),
+                };
+                (offset..offset.saturating_add(allocation))
+                    .contains(&bucket)
+                    .then(|| (bucket - offset) as i8)
+            }
+            _ => Some(toss),
+        }
+    }
+}
+
+// FNV-1a, used for being stable across platforms and releases
+fn fnv1a(bytes: impl Iterator<Item = u8>) -> u64 {
+    bytes.fold(0xcbf29ce484222325_u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+pub(crate) const LAYER_SALT: &str = "layer";
+pub(crate) const HOLDOUT_SALT: &str = "holdout";
+
+/// Maps a unit to its bucket out of 100 in `name`, a layer or a holdout as
+/// told by `salt`. The unit's identifier is hashed along with both, so the
+/// buckets a unit gets in different layers and holdouts are independent of
+/// each other, even when a layer and a holdout share a name.
+pub(crate) fn unit_bucket(salt: &str, name: &str, unit_id: &str) -> u8 {
+    // the parts are separated by a byte none of them has, so that no two
+    // triples hash the same bytes
+    let separator = std::iter::once(0xff);
+    let bytes = salt
+        .bytes()
+        .chain(separator.clone())
+        .chain(name.bytes())
+        .chain(separator)
+        .chain(unit_id.bytes());
+    // FNV-1a mixes its last bytes poorly into the low bits the bucket is taken
+    // from, the finalizer of murmur3 spreads them over the whole hash
+    let mut hash = fnv1a(bytes);
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xff51afd7ed558ccd);
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xc4ceb9fe1a85ec53);
+    hash ^= hash >> 33;
+    (hash % 100) as u8
+}
+
+/// Maps a unit's toss to its bucket in `layer`, for units without an
+/// identifier. The map is a permutation of the 100 buckets picked by the
+/// layer's name, so a unit always gets the same bucket in a layer and every
+/// bucket gets the same share of units. All layers permute the same single
+/// toss though, so a unit's bucket in one layer determines its bucket in
+/// every other layer, only `unit_bucket` keeps layers orthogonal.
+pub(crate) fn layer_bucket(layer: &str, toss: u8) -> u8 {
+    let hash = fnv1a(layer.bytes());
+    // an affine map with a multiplier coprime to 100 is a permutation of
+    // the buckets, keeping the traffic split between them uniform
+    let mut multiplier = (hash % 100) as u32;
+    while multiplier % 2 == 0 || multiplier % 5 == 0 {
+        multiplier = (multiplier + 1) % 100;
+    }
+    let shift = ((hash >> 32) % 100) as u32;
+    ((toss as u32 % 100 * multiplier + shift) % 100) as u8
 }
 
 pub type Experiments = Vec<Experiment>;
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct VariantAssignment {
+    pub experiment_id: String,
+    pub variant_id: String,
+    // forced variants are not picked by bucketing, so they should not be
+    // reported as exposures of the experiment
+    pub forced: bool,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub(crate) struct Holdout {
+    pub(crate) name: String,
+    pub(crate) layer: Option<String>,
+    pub(crate) percentage: u8,
+    pub(crate) enabled: bool,
+}
+
+impl Holdout {
+    // units are held out by a hash of their identifier salted with the
+    // holdout, independent of their bucket in any layer, so a holdout keeps
+    // out the same share of every slice of a layer. Units without an
+    // identifier fall back to a permutation of their toss, which is not
+    // independent of their layer buckets
+    pub(crate) fn holds_out(
+        &self,
+        experiment: &Experiment,
+        toss: i8,
+        unit_id: Option<&str>,
+    ) -> bool {
+        let covers_experiment = match &self.layer {
+            Some(layer) => experiment.layer.as_ref() == Some(layer),
+            None => true,
+        };
+        let bucket = match unit_id {
+            Some(unit_id) => Some(unit_bucket(HOLDOUT_SALT, &self.name, unit_id)),
+            None if toss >= 0 => {
+                Some(layer_bucket(&format!("holdout:{}", self.name), toss as u8))
+            }
+            None => None,
+        };
+        self.enabled
+            && covers_experiment
+            && bucket.is_some_and(|bucket| bucket < self.percentage)
+    }
+}
+
 pub(crate) type ExperimentStore = HashMap<String, Experiment>;
 
 #[derive(Serialize, Deserialize, Default)]
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE public.experiment_change_requests;
+DROP TYPE public.experiment_change_request_status;
+DROP TYPE public.experiment_change_request_action;
@@ -0,0 +1,48 @@
+-- Your SQL goes here
+--
+-- Name: experiment_change_request_action; Type: TYPE; Schema: public; Owner: -
+--
+CREATE TYPE public.experiment_change_request_action AS ENUM (
+    'CREATE',
+    'RAMP',
+    'CONCLUDE'
+);
+--
+-- Name: experiment_change_request_status; Type: TYPE; Schema: public; Owner: -
+--
+CREATE TYPE public.experiment_change_request_status AS ENUM (
+    'PENDING',
+    'APPROVED',
+    'REJECTED'
+);
+--
+-- Name: experiment_change_requests; Type: TABLE; Schema: public; Owner: -
+--
+CREATE TABLE public.experiment_change_requests (
+    id bigint PRIMARY KEY,
+    experiment_id bigint NOT NULL,
+    action public.experiment_change_request_action NOT NULL,
+    payload json NOT NULL,
+    status public.experiment_change_request_status NOT NULL,
+    created_at timestamp with time zone DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    created_by text NOT NULL,
+    reviewed_at timestamp with time zone,
+    reviewed_by text,
+    review_comment text
+);
+--
+-- Name: experiment_change_requests_status_index; Type: INDEX; Schema: public; Owner: -
+--
+CREATE INDEX experiment_change_requests_status_index ON public.experiment_change_requests USING btree (status) INCLUDE (experiment_id, created_at);
+--
+-- Name: experiment_change_requests_experiment_id_index; Type: INDEX; Schema: public; Owner: -
+--
+CREATE INDEX experiment_change_requests_experiment_id_index ON public.experiment_change_requests USING btree (experiment_id) INCLUDE (status);
+--
+-- Name: experiment_change_requests_pending_index; Type: INDEX; Schema: public; Owner: -
+--
+CREATE UNIQUE INDEX experiment_change_requests_pending_index ON public.experiment_change_requests USING btree (experiment_id) WHERE (status = 'PENDING'::public.experiment_change_request_status);
+--
+-- Name: experiment_change_requests experiment_change_requests_audit; Type: TRIGGER; Schema: public; Owner: -
+--
+CREATE TRIGGER experiment_change_requests_audit AFTER INSERT OR DELETE OR UPDATE ON public.experiment_change_requests FOR EACH ROW EXECUTE FUNCTION public.event_logger();
@@ -0,0 +1,8 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS public.experiment_tags_index;
+ALTER TABLE public.experiments
+    DROP COLUMN description,
+    DROP COLUMN hypothesis,
+    DROP COLUMN owner_team,
+    DROP COLUMN tags,
+    DROP COLUMN external_links;
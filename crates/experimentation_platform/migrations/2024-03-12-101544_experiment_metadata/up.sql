@@ -0,0 +1,11 @@
+-- Your SQL goes here
+ALTER TABLE public.experiments
+    ADD COLUMN description text,
+    ADD COLUMN hypothesis text,
+    ADD COLUMN owner_team text,
+    ADD COLUMN tags public.not_null_text[] DEFAULT '{}'::text[] NOT NULL,
+    ADD COLUMN external_links public.not_null_text[] DEFAULT '{}'::text[] NOT NULL;
+--
+-- Name: experiment_tags_index; Type: INDEX; Schema: public; Owner: -
+--
+CREATE INDEX experiment_tags_index ON public.experiments USING gin (tags);
@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE public.experiment_webhook_deliveries;
+DROP TABLE public.experiment_webhooks;
+DROP TYPE public.experiment_webhook_delivery_status;
+DROP TYPE public.experiment_webhook_event;
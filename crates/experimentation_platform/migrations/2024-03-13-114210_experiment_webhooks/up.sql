@@ -0,0 +1,52 @@
+-- Your SQL goes here
+--
+-- Name: experiment_webhook_event; Type: TYPE; Schema: public; Owner: -
+--
+CREATE TYPE public.experiment_webhook_event AS ENUM (
+    'CREATED',
+    'RAMPED',
+    'CONCLUDED',
+    'DISCARDED',
+    'TEST'
+);
+--
+-- Name: experiment_webhook_delivery_status; Type: TYPE; Schema: public; Owner: -
+--
+CREATE TYPE public.experiment_webhook_delivery_status AS ENUM (
+    'PENDING',
+    'SUCCEEDED',
+    'FAILED'
+);
+--
+-- Name: experiment_webhooks; Type: TABLE; Schema: public; Owner: -
+--
+CREATE TABLE public.experiment_webhooks (
+    id bigint PRIMARY KEY,
+    url text NOT NULL,
+    secret text NOT NULL,
+    events public.experiment_webhook_event[] NOT NULL,
+    enabled boolean DEFAULT true NOT NULL,
+    created_at timestamp with time zone DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    created_by text NOT NULL,
+    last_modified timestamp with time zone DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    last_modified_by text NOT NULL
+);
+--
+-- Name: experiment_webhook_deliveries; Type: TABLE; Schema: public; Owner: -
+--
+CREATE TABLE public.experiment_webhook_deliveries (
+    id bigint PRIMARY KEY,
+    webhook_id bigint NOT NULL REFERENCES public.experiment_webhooks(id) ON DELETE CASCADE,
+    event public.experiment_webhook_event NOT NULL,
+    payload json NOT NULL,
+    status public.experiment_webhook_delivery_status NOT NULL,
+    attempts integer DEFAULT 0 NOT NULL,
+    response_code integer,
+    error text,
+    created_at timestamp with time zone DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    last_attempted_at timestamp with time zone
+);
+--
+-- Name: experiment_webhook_deliveries_webhook_id_index; Type: INDEX; Schema: public; Owner: -
+--
+CREATE INDEX experiment_webhook_deliveries_webhook_id_index ON public.experiment_webhook_deliveries USING btree (webhook_id, created_at);
@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS public.experiment_schedule_index;
+ALTER TABLE public.experiments
+    DROP COLUMN start_at,
+    DROP COLUMN end_at,
+    DROP COLUMN start_traffic_percentage,
+    DROP COLUMN fallback_variant;
@@ -0,0 +1,10 @@
+-- Your SQL goes here
+ALTER TABLE public.experiments
+    ADD COLUMN start_at timestamp with time zone,
+    ADD COLUMN end_at timestamp with time zone,
+    ADD COLUMN start_traffic_percentage integer,
+    ADD COLUMN fallback_variant text;
+--
+-- Name: experiment_schedule_index; Type: INDEX; Schema: public; Owner: -
+--
+CREATE INDEX experiment_schedule_index ON public.experiments USING btree (status, start_at, end_at);
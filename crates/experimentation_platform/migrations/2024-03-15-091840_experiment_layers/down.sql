@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS public.experiment_layer_index;
+ALTER TABLE public.experiments
+    DROP COLUMN layer,
+    DROP COLUMN layer_offset,
+    DROP COLUMN layer_allocation;
@@ -0,0 +1,9 @@
+-- Your SQL goes here
+ALTER TABLE public.experiments
+    ADD COLUMN layer text,
+    ADD COLUMN layer_offset integer,
+    ADD COLUMN layer_allocation integer;
+--
+-- Name: experiment_layer_index; Type: INDEX; Schema: public; Owner: -
+--
+CREATE INDEX experiment_layer_index ON public.experiments USING btree (layer) INCLUDE (status, layer_offset, layer_allocation);
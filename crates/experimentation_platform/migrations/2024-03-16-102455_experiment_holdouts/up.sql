@@ -0,0 +1,18 @@
+-- Your SQL goes here
+--
+-- Name: experiment_holdouts; Type: TABLE; Schema: public; Owner: -
+--
+CREATE TABLE public.experiment_holdouts (
+    name text PRIMARY KEY,
+    layer text,
+    percentage integer NOT NULL CHECK (percentage >= 0 AND percentage <= 100),
+    enabled boolean DEFAULT true NOT NULL,
+    created_at timestamp with time zone DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    created_by text NOT NULL,
+    last_modified timestamp with time zone DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    last_modified_by text NOT NULL
+);
+--
+-- Name: experiment_holdouts experiment_holdouts_audit; Type: TRIGGER; Schema: public; Owner: -
+--
+CREATE TRIGGER experiment_holdouts_audit AFTER INSERT OR DELETE OR UPDATE ON public.experiment_holdouts FOR EACH ROW EXECUTE FUNCTION public.event_logger();
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE public.experiment_variant_metrics;
+ALTER TABLE public.experiments
+    DROP COLUMN bandit;
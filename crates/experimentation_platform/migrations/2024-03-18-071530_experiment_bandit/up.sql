@@ -0,0 +1,14 @@
+-- Your SQL goes here
+ALTER TABLE public.experiments
+    ADD COLUMN bandit boolean DEFAULT false NOT NULL;
+--
+-- Name: experiment_variant_metrics; Type: TABLE; Schema: public; Owner: -
+--
+CREATE TABLE public.experiment_variant_metrics (
+    experiment_id bigint NOT NULL,
+    variant_id text NOT NULL,
+    trials bigint DEFAULT 0 NOT NULL,
+    successes bigint DEFAULT 0 NOT NULL,
+    last_updated timestamp with time zone DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    PRIMARY KEY (experiment_id, variant_id)
+);
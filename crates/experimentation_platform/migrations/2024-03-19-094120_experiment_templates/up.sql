@@ -0,0 +1,17 @@
+-- Your SQL goes here
+--
+-- Name: experiment_templates; Type: TABLE; Schema: public; Owner: -
+--
+CREATE TABLE public.experiment_templates (
+    name text PRIMARY KEY,
+    description text,
+    variants json NOT NULL,
+    created_at timestamp with time zone DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    created_by text NOT NULL,
+    last_modified timestamp with time zone DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    last_modified_by text NOT NULL
+);
+--
+-- Name: experiment_templates experiment_templates_audit; Type: TRIGGER; Schema: public; Owner: -
+--
+CREATE TRIGGER experiment_templates_audit AFTER INSERT OR DELETE OR UPDATE ON public.experiment_templates FOR EACH ROW EXECUTE FUNCTION public.event_logger();
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS public.experiment_webhook_deliveries_next_attempt_index;
+ALTER TABLE public.experiment_webhook_deliveries
+    DROP COLUMN next_attempt_at;
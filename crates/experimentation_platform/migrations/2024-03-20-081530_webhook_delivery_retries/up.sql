@@ -0,0 +1,10 @@
+-- Your SQL goes here
+ALTER TABLE public.experiment_webhook_deliveries
+    ADD COLUMN next_attempt_at timestamp with time zone;
+UPDATE public.experiment_webhook_deliveries
+    SET next_attempt_at = COALESCE(last_attempted_at, created_at)
+    WHERE status = 'PENDING';
+--
+-- Name: experiment_webhook_deliveries_next_attempt_index; Type: INDEX; Schema: public; Owner: -
+--
+CREATE INDEX experiment_webhook_deliveries_next_attempt_index ON public.experiment_webhook_deliveries USING btree (next_attempt_at) WHERE (status = 'PENDING');
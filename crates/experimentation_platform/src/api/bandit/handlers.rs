@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+
+use actix_web::{
+    get, post,
+    web::{self, Data, Json},
+    Scope,
+};
+use chrono::Utc;
+use diesel::{upsert::excluded, Connection, ExpressionMethods, RunQueryDsl};
+use service_utils::{
+    bad_argument, result as superposition,
+    service::types::{AppState, DbConnection},
+};
+use superposition_types::User;
+
+use super::{
+    helpers::{
+        bandit_response, parse_variants, reallocate_traffic, validate_bandit_experiment,
+    },
+    types::{BanditEventsRequest, BanditResponse},
+};
+use crate::{
+    api::experiments::handlers::get_experiment,
+    db::{models::VariantMetrics, schema::experiment_variant_metrics::dsl as metrics},
+};
+
+pub fn endpoints(scope: Scope) -> Scope {
+    scope
+        .service(get_bandit)
+        .service(ingest_events)
+        .service(reallocate)
+}
+
+#[get("/{id}")]
+async fn get_bandit(
+    params: web::Path<i64>,
+    db_conn: DbConnection,
+) -> superposition::Result<Json<BanditResponse>> {
+    let DbConnection(mut conn) = db_conn;
+    let experiment = get_experiment(params.into_inner(), &mut conn)?;
+    if !experiment.bandit {
+        return Err(bad_argument!(
+            "experiment {} is not a bandit experiment",
+            experiment.id
+        ));
+    }
+    Ok(Json(bandit_response(&experiment, &mut conn)?))
+}
+
+#[post("/{id}/events")]
+async fn ingest_events(
+    params: web::Path<i64>,
+    req: Json<BanditEventsRequest>,
+    db_conn: DbConnection,
+) -> superposition::Result<Json<BanditResponse>> {
+    let DbConnection(mut conn) = db_conn;
+    let experiment = get_experiment(params.into_inner(), &mut conn)?;
+    validate_bandit_experiment(&experiment)?;
+    let events = req.into_inner().events;
+    if events.is_empty() {
+        return Err(bad_argument!("provide atleast one event to ingest"));
+    }
+
+    let variant_ids = parse_variants(&experiment)?
+        .into_iter()
+        .map(|variant| variant.id)
+        .collect::<Vec<String>>();
+    let mut counts: HashMap<String, (i64, i64)> = HashMap::new();
+    for event in events {
+        if !variant_ids.contains(&event.variant_id) {
+            return Err(bad_argument!(
+                "variant {} does not belong to experiment {}",
+                event.variant_id,
+                experiment.id
+            ));
+        }
+        let (trials, successes) = counts.entry(event.variant_id).or_default();
+        *trials += 1;
+        *successes += i64::from(event.success);
+    }
+
+    let rows = counts
+        .into_iter()
+        .map(|(variant_id, (trials, successes))| VariantMetrics {
+            experiment_id: experiment.id,
+            variant_id,
+            trials,
+            successes,
+            last_updated: Utc::now(),
+        })
+        .collect::<Vec<VariantMetrics>>();
+    conn.transaction::<_, superposition::AppError, _>(|transaction_conn| {
+        for row in &rows {
+            diesel::insert_into(metrics::experiment_variant_metrics)
+                .values(row)
+                .on_conflict((metrics::experiment_id, metrics::variant_id))
+                .do_update()
+                .set((
+                    metrics::trials.eq(metrics::trials + excluded(metrics::trials)),
+                    metrics::successes
+                        .eq(metrics::successes + excluded(metrics::successes)),
+                    metrics::last_updated.eq(excluded(metrics::last_updated)),
+                ))
+                .execute(transaction_conn)?;
+        }
+        Ok(())
+    })?;
+
+    Ok(Json(bandit_response(&experiment, &mut conn)?))
+}
+
+#[post("/{id}/reallocate")]
+async fn reallocate(
+    state: Data<AppState>,
+    params: web::Path<i64>,
+    db_conn: DbConnection,
+    user: User,
+) -> superposition::Result<Json<BanditResponse>> {
+    let DbConnection(mut conn) = db_conn;
+    let experiment = get_experiment(params.into_inner(), &mut conn)?;
+    let updated = reallocate_traffic(&state, &experiment, &mut conn, &user)?;
+    Ok(Json(bandit_response(&updated, &mut conn)?))
+}
@@ -0,0 +1,267 @@
+use std::{collections::HashMap, time::Duration};
+
+use actix_web::web::Data;
+use chrono::Utc;
+use diesel::{
+    r2d2::{ConnectionManager, PooledConnection},
+    ExpressionMethods, PgConnection, QueryDsl, RunQueryDsl,
+};
+use rand::Rng;
+use service_utils::{
+    bad_argument, result as superposition,
+    service::types::{AppExecutionNamespace, AppScope, AppState},
+    unexpected_error,
+};
+use superposition_types::{SuperpositionUser, User};
+
+use super::types::{BanditResponse, VariantStats};
+use crate::{
+    api::experiments::{helpers::get_job_tenants, types::Variant},
+    db::{
+        models::{Experiment, ExperimentStatusType, VariantMetrics},
+        schema::{
+            experiment_variant_metrics::dsl as metrics, experiments::dsl as experiments,
+        },
+    },
+};
+
+pub fn validate_bandit_experiment(experiment: &Experiment) -> superposition::Result<()> {
+    if !experiment.bandit {
+        return Err(bad_argument!(
+            "experiment {} is not a bandit experiment",
+            experiment.id
+        ));
+    }
+    if experiment.status == ExperimentStatusType::CONCLUDED {
+        return Err(bad_argument!(
+            "experiment {} is already concluded",
+            experiment.id
+        ));
+    }
+    Ok(())
+}
+
+pub fn parse_variants(experiment: &Experiment) -> superposition::Result<Vec<Variant>> {
+    serde_json::from_value(experiment.variants.clone()).map_err(|err| {
+        log::error!(
+            "failed to parse variants of bandit experiment {}: {}",
+            experiment.id,
+            err
+        );
+        unexpected_error!("Something went wrong, failed to parse experiment variants")
+    })
+}
+
+fn load_metrics(
+    experiment_id: i64,
+    conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+) -> superposition::Result<HashMap<String, VariantMetrics>> {
+    let result = metrics::experiment_variant_metrics
+        .filter(metrics::experiment_id.eq(experiment_id))
+        .load::<VariantMetrics>(conn)?;
+    Ok(result
+        .into_iter()
+        .map(|m| (m.variant_id.clone(), m))
+        .collect())
+}
+
+pub fn bandit_response(
+    experiment: &Experiment,
+    conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+) -> superposition::Result<BanditResponse> {
+    let variants = parse_variants(experiment)?;
+    let metrics = load_metrics(experiment.id, conn)?;
+    Ok(BanditResponse {
+        experiment_id: experiment.id.to_string(),
+        variants: variants
+            .into_iter()
+            .map(|variant| {
+                let stats = metrics.get(&variant.id);
+                VariantStats::new(variant.id, stats, variant.weight)
+            })
+            .collect(),
+    })
+}
+
+// Box-Muller transform, `1 - u` keeps the argument of ln away from zero
+fn standard_normal<R: Rng>(rng: &mut R) -> f64 {
+    let u1 = 1.0 - rng.gen::<f64>();
+    let u2 = rng.gen::<f64>();
+    (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+}
+
+// Marsaglia-Tsang method, valid for shape >= 1 which always holds for the
+// beta posteriors sampled here
+fn gamma_sample<R: Rng>(shape: f64, rng: &mut R) -> f64 {
+    let d = shape - 1.0 / 3.0;
+    let c = 1.0 / (9.0 * d).sqrt();
+    loop {
+        let x = standard_normal(rng);
+        let v = (1.0 + c * x).powi(3);
+        if v <= 0.0 {
+            continue;
+        }
+        let u = 1.0 - rng.gen::<f64>();
+        if u.ln() < 0.5 * x * x + d - d * v + d * v.ln() {
+            return d * v;
+        }
+    }
+}
+
+fn beta_sample<R: Rng>(alpha: f64, beta: f64, rng: &mut R) -> f64 {
+    let x = gamma_sample(alpha, rng);
+    let y = gamma_sample(beta, rng);
+    x / (x + y)
+}
+
+/// Traffic weights, in percent, for arms given as `(trials, successes)`.
+/// Each arm's share is the probability of it being the best arm, estimated by
+/// drawing `samples` times from the Beta(successes + 1, failures + 1)
+/// posteriors. Every arm keeps at least `min_weight` percent so that arms
+/// which started badly still get a chance to recover, and the weights always
+/// add up to 100.
+pub fn thompson_sampling_weights<R: Rng>(
+    arms: &[(i64, i64)],
+    samples: u32,
+    min_weight: u8,
+    rng: &mut R,
+) -> Vec<u8> {
+    if arms.is_empty() {
+        return Vec::new();
+    }
+    let samples = samples.max(1);
+    let mut wins = vec![0_u32; arms.len()];
+    for _ in 0..samples {
+        let mut best = (0, f64::MIN);
+        for (index, (trials, successes)) in arms.iter().enumerate() {
+            let successes = (*successes).clamp(0, (*trials).max(0));
+            let failures = (*trials).max(0) - successes;
+            let draw = beta_sample(successes as f64 + 1.0, failures as f64 + 1.0, rng);
+            if draw > best.1 {
+                best = (index, draw);
+            }
+        }
+        wins[best.0] += 1;
+    }
+
+    let floor = (min_weight as usize).min(100 / arms.len());
+    let distributable = (100 - floor * arms.len()) as f64;
+    let shares = wins
+        .iter()
+        .map(|wins| floor as f64 + distributable * *wins as f64 / samples as f64)
+        .collect::<Vec<f64>>();
+
+    // largest remainder rounding, so the rounded weights still sum to 100
+    let mut weights = shares
+        .iter()
+        .map(|share| share.floor() as u8)
+        .collect::<Vec<u8>>();
+    let mut by_remainder = (0..shares.len()).collect::<Vec<usize>>();
+    by_remainder.sort_by(|a, b| {
+        let remainder = |i: usize| shares[i] - shares[i].floor();
+        remainder(*b).total_cmp(&remainder(*a))
+    });
+    let assigned: usize = weights.iter().map(|w| *w as usize).sum();
+    for index in by_remainder.into_iter().take(100 - assigned) {
+        weights[index] += 1;
+    }
+    weights
+}
+
+/// Recomputes the variant weights of a bandit experiment from the metrics
+/// ingested so far and stores them on its variants, clients pick the new
+/// split up along with the experiment's other changes.
+pub fn reallocate_traffic(
+    state: &Data<AppState>,
+    experiment: &Experiment,
+    conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+    user: &User,
+) -> superposition::Result<Experiment> {
+    validate_bandit_experiment(experiment)?;
+    let mut variants = parse_variants(experiment)?;
+    let metrics = load_metrics(experiment.id, conn)?;
+
+    let arms = variants
+        .iter()
+        .map(|variant| {
+            metrics
+                .get(&variant.id)
+                .map(|m| (m.trials, m.successes))
+                .unwrap_or_default()
+        })
+        .collect::<Vec<(i64, i64)>>();
+    let weights = thompson_sampling_weights(
+        &arms,
+        state.bandit.samples,
+        state.bandit.min_weight,
+        &mut rand::thread_rng(),
+    );
+    for (variant, weight) in variants.iter_mut().zip(weights) {
+        variant.weight = Some(weight);
+    }
+
+    let variants = serde_json::to_value(variants).map_err(|err| {
+        log::error!("failed to serialize bandit variants: {}", err);
+        unexpected_error!("Something went wrong, failed to reallocate traffic")
+    })?;
+    let updated = diesel::update(experiments::experiments)
+        .filter(experiments::id.eq(experiment.id))
+        .set((
+            experiments::variants.eq(variants),
+            experiments::last_modified.eq(Utc::now()),
+            experiments::last_modified_by.eq(user.get_email()),
+        ))
+        .get_result::<Experiment>(conn)?;
+    Ok(updated)
+}
+
+fn reallocate_running_bandits(
+    state: &Data<AppState>,
+    conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+    user: &User,
+) -> superposition::Result<()> {
+    let running = experiments::experiments
+        .filter(experiments::bandit.eq(true))
+        .filter(experiments::status.eq(ExperimentStatusType::INPROGRESS))
+        .load::<Experiment>(conn)?;
+    for experiment in running {
+        if let Err(err) = reallocate_traffic(state, &experiment, conn, user) {
+            log::error!(
+                "bandit: failed to reallocate traffic of experiment {}: {}",
+                experiment.id,
+                err
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Periodically reallocates the traffic of every running bandit experiment,
+/// for all tenants.
+pub async fn run_bandit_job(state: Data<AppState>, interval: Duration) {
+    let tenants = get_job_tenants(&state);
+    let user = User::default();
+    let mut ticker = actix_web::rt::time::interval(interval);
+
+    loop {
+        ticker.tick().await;
+        for tenant in &tenants {
+            let namespace =
+                AppExecutionNamespace::new(&state, tenant, AppScope::EXPERIMENTATION);
+            let mut conn = match state.db_pool.get_conn(namespace.to_string()) {
+                Ok(conn) => conn,
+                Err(err) => {
+                    log::error!(
+                        "bandit: unable to get db connection for {}: {}",
+                        tenant.as_str(),
+                        err
+                    );
+                    continue;
+                }
+            };
+            if let Err(err) = reallocate_running_bandits(&state, &mut conn, &user) {
+                log::error!("bandit job failed for tenant {}: {}", tenant.as_str(), err);
+            }
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use serde::{Deserialize, Serialize};
+
+use crate::db::models::VariantMetrics;
+
+#[derive(Deserialize, Debug)]
+pub struct BanditEvent {
+    pub variant_id: String,
+    // every event is a trial of the variant, successful ones are its reward
+    #[serde(default)]
+    pub success: bool,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct BanditEventsRequest {
+    pub events: Vec<BanditEvent>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct VariantStats {
+    pub variant_id: String,
+    pub trials: i64,
+    pub successes: i64,
+    pub weight: Option<u8>,
+}
+
+impl VariantStats {
+    pub fn new(
+        variant_id: String,
+        metrics: Option<&VariantMetrics>,
+        weight: Option<u8>,
+    ) -> Self {
+        VariantStats {
+            variant_id,
+            trials: metrics.map(|m| m.trials).unwrap_or_default(),
+            successes: metrics.map(|m| m.successes).unwrap_or_default(),
+            weight,
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct BanditResponse {
+    pub experiment_id: String,
+    pub variants: Vec<VariantStats>,
+}
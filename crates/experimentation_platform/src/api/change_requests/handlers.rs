@@ -0,0 +1,278 @@
+use actix_web::{
+    get, patch,
+    web::{self, Data, Json, Query},
+    HttpResponse, Scope,
+};
+use chrono::Utc;
+use diesel::{
+    r2d2::{ConnectionManager, PooledConnection},
+    ExpressionMethods, OptionalExtension, PgConnection, QueryDsl, RunQueryDsl,
+};
+use service_utils::{
+    bad_argument, result as superposition,
+    service::types::{AppState, DbConnection, Tenant},
+};
+use superposition_types::{SuperpositionUser, User};
+
+use super::{
+    helpers::parse_payload,
+    types::{
+        ChangeRequestFilters, ChangeRequestResponse, ChangeRequestsResponse,
+        ReviewRequest,
+    },
+};
+use crate::{
+    api::{
+        experiments::{
+            handlers::{conclude, create_experiment, ramp_experiment},
+            types::{ConcludeExperimentRequest, ExperimentCreateRequest, RampRequest},
+        },
+        webhooks::notify_webhooks,
+    },
+    db::{
+        models::{
+            ChangeRequestAction, ChangeRequestStatus, ExperimentChangeRequest,
+            WebhookEvent,
+        },
+        schema::experiment_change_requests::dsl,
+    },
+};
+
+pub fn endpoints(scope: Scope) -> Scope {
+    scope
+        .service(list_change_requests)
+        .service(get_change_request)
+        .service(approve)
+        .service(reject)
+}
+
+#[get("")]
+async fn list_change_requests(
+    filters: Query<ChangeRequestFilters>,
+    db_conn: DbConnection,
+) -> superposition::Result<Json<ChangeRequestsResponse>> {
+    let DbConnection(mut conn) = db_conn;
+
+    let query_builder = |filters: &ChangeRequestFilters| {
+        let mut builder = dsl::experiment_change_requests.into_boxed();
+        if let Some(states) = filters.status.clone() {
+            builder = builder.filter(dsl::status.eq_any(states.0));
+        }
+        if let Some(experiment_id) = filters.experiment_id {
+            builder = builder.filter(dsl::experiment_id.eq(experiment_id));
+        }
+        if let Some(created_by) = filters.created_by.clone() {
+            builder = builder.filter(dsl::created_by.eq(created_by));
+        }
+        builder
+    };
+    let filters = filters.into_inner();
+    let base_query = query_builder(&filters);
+    let count_query = query_builder(&filters);
+
+    let limit = filters.count.unwrap_or(10);
+    let offset = (filters.page.unwrap_or(1) - 1) * limit;
+    let query = base_query
+        .order(dsl::created_at.desc())
+        .limit(limit)
+        .offset(offset);
+
+    let total_items: i64 = count_query.count().get_result(&mut conn)?;
+    let change_requests = query.load::<ExperimentChangeRequest>(&mut conn)?;
+    let total_pages = (total_items as f64 / limit as f64).ceil() as i64;
+
+    Ok(Json(ChangeRequestsResponse {
+        total_items,
+        total_pages,
+        data: change_requests
+            .into_iter()
+            .map(ChangeRequestResponse::from)
+            .collect(),
+    }))
+}
+
+#[get("/{id}")]
+async fn get_change_request(
+    params: web::Path<i64>,
+    db_conn: DbConnection,
+) -> superposition::Result<Json<ChangeRequestResponse>> {
+    let DbConnection(mut conn) = db_conn;
+    let change_request = dsl::experiment_change_requests
+        .find(params.into_inner())
+        .get_result::<ExperimentChangeRequest>(&mut conn)?;
+    Ok(Json(ChangeRequestResponse::from(change_request)))
+}
+
+fn fetch_pending_change_request(
+    change_request_id: i64,
+    conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+) -> superposition::Result<ExperimentChangeRequest> {
+    let change_request = dsl::experiment_change_requests
+        .find(change_request_id)
+        .get_result::<ExperimentChangeRequest>(conn)?;
+    if change_request.status != ChangeRequestStatus::PENDING {
+        return Err(bad_argument!(
+            "change request {} is not pending, it has already been reviewed",
+            change_request_id
+        ));
+    }
+    Ok(change_request)
+}
+
+/// Moves a pending change request to `status`, failing if another reviewer
+/// got to it first. Claiming the request before applying it ensures the
+/// underlying change is only ever applied once.
+fn mark_reviewed(
+    change_request_id: i64,
+    status: ChangeRequestStatus,
+    comment: Option<String>,
+    user: &User,
+    conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+) -> superposition::Result<ExperimentChangeRequest> {
+    diesel::update(dsl::experiment_change_requests)
+        .filter(dsl::id.eq(change_request_id))
+        .filter(dsl::status.eq(ChangeRequestStatus::PENDING))
+        .set((
+            dsl::status.eq(status),
+            dsl::reviewed_at.eq(Some(Utc::now())),
+            dsl::reviewed_by.eq(Some(user.get_email())),
+            dsl::review_comment.eq(comment),
+        ))
+        .get_result::<ExperimentChangeRequest>(conn)
+        .optional()?
+        .ok_or_else(|| {
+            bad_argument!(
+                "change request {} is not pending, it has already been reviewed",
+                change_request_id
+            )
+        })
+}
+
+async fn apply_change_request(
+    state: Data<AppState>,
+    change_request: &ExperimentChangeRequest,
+    conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+    tenant: Tenant,
+    user: User,
+) -> superposition::Result<()> {
+    let experiment_id = change_request.experiment_id;
+    match change_request.action {
+        ChangeRequestAction::CREATE => {
+            let req: ExperimentCreateRequest = parse_payload(change_request)?;
+            create_experiment(
+                state,
+                experiment_id,
+                req,
+                conn,
+                tenant,
+                user,
+                change_request.created_by.clone(),
+            )
+            .await?;
+        }
+        ChangeRequestAction::RAMP => {
+            let req: RampRequest = parse_payload(change_request)?;
+            ramp_experiment(
+                &state,
+                experiment_id,
+                req.traffic_percentage,
+                conn,
+                &tenant,
+                &user,
+            )?;
+        }
+        ChangeRequestAction::CONCLUDE => {
+            let req: ConcludeExperimentRequest = parse_payload(change_request)?;
+            conclude(state, experiment_id, req, conn, tenant, user).await?;
+        }
+    }
+    Ok(())
+}
+
+#[patch("/{id}/approve")]
+async fn approve(
+    state: Data<AppState>,
+    params: web::Path<i64>,
+    req: Option<Json<ReviewRequest>>,
+    db_conn: DbConnection,
+    tenant: Tenant,
+    user: User,
+) -> superposition::Result<HttpResponse> {
+    let DbConnection(mut conn) = db_conn;
+    let change_request_id = params.into_inner();
+    let comment = req.and_then(|req| req.into_inner().comment);
+
+    let change_request = fetch_pending_change_request(change_request_id, &mut conn)?;
+    if change_request.created_by == user.get_email() {
+        return Err(bad_argument!(
+            "change requests cannot be approved by the user who raised them"
+        ));
+    }
+
+    let approved = mark_reviewed(
+        change_request_id,
+        ChangeRequestStatus::APPROVED,
+        comment,
+        &user,
+        &mut conn,
+    )?;
+
+    if let Err(err) =
+        apply_change_request(state, &approved, &mut conn, tenant, user).await
+    {
+        log::error!(
+            "failed to apply change request {}, moving it back to pending: {}",
+            change_request_id,
+            err
+        );
+        diesel::update(dsl::experiment_change_requests.find(change_request_id))
+            .set((
+                dsl::status.eq(ChangeRequestStatus::PENDING),
+                dsl::reviewed_at.eq(None::<chrono::DateTime<Utc>>),
+                dsl::reviewed_by.eq(None::<String>),
+                dsl::review_comment.eq(None::<String>),
+            ))
+            .execute(&mut conn)?;
+        return Err(err);
+    }
+
+    Ok(HttpResponse::Ok().json(ChangeRequestResponse::from(approved)))
+}
+
+#[patch("/{id}/reject")]
+async fn reject(
+    state: Data<AppState>,
+    params: web::Path<i64>,
+    req: Option<Json<ReviewRequest>>,
+    db_conn: DbConnection,
+    tenant: Tenant,
+    user: User,
+) -> superposition::Result<Json<ChangeRequestResponse>> {
+    let DbConnection(mut conn) = db_conn;
+    let change_request_id = params.into_inner();
+    let comment = req.and_then(|req| req.into_inner().comment);
+
+    fetch_pending_change_request(change_request_id, &mut conn)?;
+    let rejected = mark_reviewed(
+        change_request_id,
+        ChangeRequestStatus::REJECTED,
+        comment,
+        &user,
+        &mut conn,
+    )?;
+    let response = ChangeRequestResponse::from(rejected);
+
+    // rejecting the creation of an experiment discards it
+    if response.action == ChangeRequestAction::CREATE {
+        match serde_json::to_value(&response) {
+            Ok(data) => {
+                notify_webhooks(&state, &tenant, WebhookEvent::DISCARDED, data, &mut conn)
+            }
+            Err(err) => {
+                log::error!("failed to serialize change request for webhooks: {}", err)
+            }
+        }
+    }
+
+    Ok(Json(response))
+}
@@ -0,0 +1,77 @@
+use actix_web::web::Data;
+use chrono::Utc;
+use diesel::{
+    r2d2::{ConnectionManager, PooledConnection},
+    result::{DatabaseErrorKind::UniqueViolation, Error::DatabaseError},
+    PgConnection, RunQueryDsl,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use service_utils::{
+    bad_argument, result as superposition, service::types::AppState, unexpected_error,
+};
+use superposition_types::{SuperpositionUser, User};
+
+use crate::db::{
+    models::{ChangeRequestAction, ChangeRequestStatus, ExperimentChangeRequest},
+    schema::experiment_change_requests::dsl,
+};
+
+pub fn create_change_request<T: Serialize>(
+    state: &Data<AppState>,
+    experiment_id: i64,
+    action: ChangeRequestAction,
+    payload: &T,
+    user: &User,
+    conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+) -> superposition::Result<ExperimentChangeRequest> {
+    let payload = serde_json::to_value(payload).map_err(|err| {
+        log::error!("failed to serialize change request payload: {}", err);
+        unexpected_error!("Something went wrong, failed to create change request")
+    })?;
+
+    let id = {
+        let mut snowflake_generator = state.snowflake_generator.lock().unwrap();
+        snowflake_generator.real_time_generate()
+    };
+
+    let change_request = ExperimentChangeRequest {
+        id,
+        experiment_id,
+        action,
+        payload,
+        status: ChangeRequestStatus::PENDING,
+        created_at: Utc::now(),
+        created_by: user.get_email(),
+        reviewed_at: None,
+        reviewed_by: None,
+        review_comment: None,
+    };
+
+    // an experiment can have only one pending change request, which is
+    // enforced by a partial unique index so that concurrent requests cannot
+    // both get in
+    match diesel::insert_into(dsl::experiment_change_requests)
+        .values(&change_request)
+        .get_result::<ExperimentChangeRequest>(conn)
+    {
+        Ok(inserted) => Ok(inserted),
+        Err(DatabaseError(UniqueViolation, _)) => Err(bad_argument!(
+            "experiment {} already has a pending change request. Approve or reject it before requesting another change",
+            experiment_id
+        )),
+        Err(err) => Err(err.into()),
+    }
+}
+
+pub fn parse_payload<T: DeserializeOwned>(
+    change_request: &ExperimentChangeRequest,
+) -> superposition::Result<T> {
+    serde_json::from_value(change_request.payload.clone()).map_err(|err| {
+        log::error!(
+            "failed to parse payload of change request {}: {}",
+            change_request.id,
+            err
+        );
+        unexpected_error!("Something went wrong, failed to apply change request")
+    })
+}
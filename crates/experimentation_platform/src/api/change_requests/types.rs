@@ -0,0 +1,71 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use service_utils::helpers::deserialize_stringified_list;
+
+use crate::db::models::{self, ChangeRequestAction, ChangeRequestStatus};
+
+/********** Change Request Response Type **************/
+// ids are sent as String for the same reason as ExperimentResponse,
+// JS cannot represent 64-bit integers without losing precision
+#[derive(Serialize, Deserialize)]
+pub struct ChangeRequestResponse {
+    pub id: String,
+    pub experiment_id: String,
+    pub action: ChangeRequestAction,
+    pub payload: Value,
+    pub status: ChangeRequestStatus,
+    pub created_at: DateTime<Utc>,
+    pub created_by: String,
+    pub reviewed_at: Option<DateTime<Utc>>,
+    pub reviewed_by: Option<String>,
+    pub review_comment: Option<String>,
+}
+
+impl From<models::ExperimentChangeRequest> for ChangeRequestResponse {
+    fn from(change_request: models::ExperimentChangeRequest) -> Self {
+        ChangeRequestResponse {
+            id: change_request.id.to_string(),
+            experiment_id: change_request.experiment_id.to_string(),
+            action: change_request.action,
+            payload: change_request.payload,
+            status: change_request.status,
+            created_at: change_request.created_at,
+            created_by: change_request.created_by,
+            reviewed_at: change_request.reviewed_at,
+            reviewed_by: change_request.reviewed_by,
+            review_comment: change_request.review_comment,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ChangeRequestsResponse {
+    pub total_items: i64,
+    pub total_pages: i64,
+    pub data: Vec<ChangeRequestResponse>,
+}
+
+/********** Review API type **********/
+
+#[derive(Deserialize, Debug)]
+pub struct ReviewRequest {
+    pub comment: Option<String>,
+}
+
+/********** List API Filter Type *************/
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ChangeRequestStatusTypes(
+    #[serde(deserialize_with = "deserialize_stringified_list")]
+    pub  Vec<ChangeRequestStatus>,
+);
+
+#[derive(Deserialize, Debug)]
+pub struct ChangeRequestFilters {
+    pub status: Option<ChangeRequestStatusTypes>,
+    pub experiment_id: Option<i64>,
+    pub created_by: Option<String>,
+    pub page: Option<i64>,
+    pub count: Option<i64>,
+}
@@ -0,0 +1,40 @@
+use actix_web::{
+    get, post,
+    web::{Data, Json},
+    Scope,
+};
+use service_utils::{
+    result as superposition,
+    service::types::{AppState, DbConnection, Tenant},
+};
+use superposition_types::User;
+
+use super::{helpers::reconcile, types::DriftReport};
+
+pub fn endpoints(scope: Scope) -> Scope {
+    scope.service(get_drift).service(repair_drift)
+}
+
+#[get("")]
+async fn get_drift(
+    state: Data<AppState>,
+    db_conn: DbConnection,
+    tenant: Tenant,
+    user: User,
+) -> superposition::Result<Json<DriftReport>> {
+    let DbConnection(mut conn) = db_conn;
+    let report = reconcile(&state, &mut conn, &tenant, &user, false).await?;
+    Ok(Json(report))
+}
+
+#[post("/repair")]
+async fn repair_drift(
+    state: Data<AppState>,
+    db_conn: DbConnection,
+    tenant: Tenant,
+    user: User,
+) -> superposition::Result<Json<DriftReport>> {
+    let DbConnection(mut conn) = db_conn;
+    let report = reconcile(&state, &mut conn, &tenant, &user, true).await?;
+    Ok(Json(report))
+}
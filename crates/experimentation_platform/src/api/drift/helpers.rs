@@ -0,0 +1,381 @@
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use actix_web::web::Data;
+use chrono::Utc;
+use diesel::{
+    r2d2::{ConnectionManager, PooledConnection},
+    Connection, ExpressionMethods, PgConnection, QueryDsl, RunQueryDsl,
+};
+use serde_json::{json, Value};
+use service_utils::{
+    helpers::extract_dimensions,
+    result as superposition,
+    service::types::{AppExecutionNamespace, AppScope, AppState, Tenant},
+    unexpected_error,
+};
+use superposition_types::{SuperpositionUser, User};
+
+use context_aware_config::db::{
+    models::Context as CacContextModel, schema::contexts::dsl as cac_contexts,
+};
+
+use super::types::{
+    CacContext, CacContextList, Drift, DriftReport, DriftType, RepairFailure,
+};
+use crate::{
+    api::experiments::{
+        handlers::{apply_cac_operations, set_search_path},
+        helpers::{add_variant_dimension_to_ctx, get_job_tenants},
+        types::{ContextAction, ContextBulkResponse, ContextPutReq, Variant},
+    },
+    db::{
+        models::{Experiment, ExperimentStatusType},
+        schema::experiments::dsl as experiments,
+    },
+};
+
+const CONTEXT_LIST_PAGE_SIZE: usize = 100;
+
+/// Returns the variant id a context is scoped to through the `variantIds`
+/// dimension, if any.
+pub fn get_variant_id_from_context(context: &Value) -> Option<String> {
+    let dimensions = extract_dimensions(context).ok()?;
+    dimensions
+        .get("variantIds")
+        .and_then(Value::as_str)
+        .map(String::from)
+}
+
+fn parse_variants(experiment: &Experiment) -> superposition::Result<Vec<Variant>> {
+    serde_json::from_value(experiment.variants.clone()).map_err(|err| {
+        log::error!(
+            "failed to parse variants of experiment {}: {}",
+            experiment.id,
+            err
+        );
+        unexpected_error!("Something went wrong, failed to check experiment drift")
+    })
+}
+
+/// Compares the variants of `active_experiments` against the contexts present
+/// in CAC.
+pub fn detect_drift(
+    active_experiments: &[Experiment],
+    contexts: &[CacContext],
+) -> superposition::Result<Vec<Drift>> {
+    let contexts_by_id: HashMap<&str, &CacContext> =
+        contexts.iter().map(|ctx| (ctx.id.as_str(), ctx)).collect();
+    let contexts_by_variant: HashMap<String, &CacContext> = contexts
+        .iter()
+        .filter_map(|ctx| {
+            get_variant_id_from_context(&ctx.value).map(|variant_id| (variant_id, ctx))
+        })
+        .collect();
+    let mut active_variant_ids: HashSet<String> = HashSet::new();
+    let mut drifts = Vec::new();
+
+    for experiment in active_experiments {
+        for variant in parse_variants(experiment)? {
+            let context = variant
+                .context_id
+                .as_ref()
+                .and_then(|id| contexts_by_id.get(id.as_str()));
+            // a context moved to another condition gets a new id, but still
+            // carries the variant in its `variantIds` dimension
+            let moved_context = contexts_by_variant
+                .get(&variant.id)
+                .filter(|ctx| Some(&ctx.id) != variant.context_id.as_ref());
+            let (drift_type, context_id) = match (context, moved_context) {
+                (Some(ctx), _) if ctx.override_ != json!(variant.overrides) => {
+                    (Some(DriftType::AlteredOverride), variant.context_id.clone())
+                }
+                (Some(_), _) => (None, None),
+                (None, Some(ctx)) => {
+                    (Some(DriftType::MovedContext), Some(ctx.id.clone()))
+                }
+                (None, None) => {
+                    (Some(DriftType::MissingContext), variant.context_id.clone())
+                }
+            };
+            if let Some(drift_type) = drift_type {
+                drifts.push(Drift {
+                    drift_type,
+                    experiment_id: Some(experiment.id.to_string()),
+                    variant_id: variant.id.clone(),
+                    context_id,
+                });
+            }
+            active_variant_ids.insert(variant.id);
+        }
+    }
+
+    for context in contexts {
+        if let Some(variant_id) = get_variant_id_from_context(&context.value) {
+            if !active_variant_ids.contains(&variant_id) {
+                drifts.push(Drift {
+                    drift_type: DriftType::StrayContext,
+                    experiment_id: None,
+                    variant_id,
+                    context_id: Some(context.id.clone()),
+                });
+            }
+        }
+    }
+
+    Ok(drifts)
+}
+
+async fn fetch_cac_contexts(
+    state: &Data<AppState>,
+    conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+    tenant: &Tenant,
+    user: &User,
+) -> superposition::Result<Vec<CacContext>> {
+    if state.cac_in_process {
+        return conn.transaction::<_, superposition::AppError, _>(|transaction_conn| {
+            set_search_path(state, transaction_conn, tenant, AppScope::CAC)?;
+            let contexts: Vec<CacContextModel> =
+                cac_contexts::contexts.load(transaction_conn)?;
+            Ok(contexts
+                .into_iter()
+                .map(|ctx| CacContext {
+                    id: ctx.id,
+                    value: ctx.value,
+                    override_id: ctx.override_id,
+                    override_: ctx.override_,
+                })
+                .collect())
+        });
+    }
+
+    let http_client = reqwest::Client::new();
+    let url = state.cac_host.clone() + "/context/list";
+    let mut contexts = Vec::new();
+    let mut page = 1;
+    loop {
+        let response = http_client
+            .get(&url)
+            .query(&[("page", page), ("size", CONTEXT_LIST_PAGE_SIZE)])
+            .header("x-tenant", tenant.as_str())
+            .header(
+                "Authorization",
+                format!("{} {}", user.get_auth_type(), user.get_auth_token()),
+            )
+            .send()
+            .await
+            .and_then(|res| res.error_for_status())
+            .map_err(|err| {
+                log::error!("failed to list contexts from CAC with error: {}", err);
+                unexpected_error!("Something went wrong, failed to fetch CAC contexts")
+            })?;
+        let batch: CacContextList = response.json().await.map_err(|err| {
+            log::error!("failed to parse contexts listed from CAC: {}", err);
+            unexpected_error!("Something went wrong, failed to fetch CAC contexts")
+        })?;
+        contexts.extend(batch.data);
+        if page >= batch.total_pages {
+            break;
+        }
+        page += 1;
+    }
+
+    Ok(contexts)
+}
+
+// Recreates the contexts of drifted variants from the experiment's own
+// definition, which is treated as the source of truth.
+async fn repair_experiment(
+    state: &Data<AppState>,
+    experiment: &Experiment,
+    drifts: &[&Drift],
+    conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+    tenant: &Tenant,
+    user: &User,
+) -> superposition::Result<()> {
+    let mut variants = parse_variants(experiment)?;
+    let mut operations = Vec::new();
+    let mut repaired_variants = Vec::new();
+
+    for (index, variant) in variants.iter().enumerate() {
+        let Some(drift) = drifts.iter().find(|drift| drift.variant_id == variant.id)
+        else {
+            continue;
+        };
+        if let (DriftType::AlteredOverride | DriftType::MovedContext, Some(context_id)) =
+            (drift.drift_type, &drift.context_id)
+        {
+            // a PUT on an existing context merges overrides and a moved context
+            // would be left behind as a duplicate, delete it first
+            operations.push(ContextAction::DELETE(context_id.clone()));
+        }
+        let context =
+            add_variant_dimension_to_ctx(&experiment.context, variant.id.clone())?;
+        operations.push(ContextAction::PUT(ContextPutReq {
+            context: context.as_object().cloned().unwrap_or_default(),
+            r#override: json!(variant.overrides),
+        }));
+        repaired_variants.push(index);
+    }
+
+    let experiment_id = experiment.id;
+    let last_modified_by = user.get_email();
+    let update_experiment = |cac_response: Vec<ContextBulkResponse>,
+                             conn: &mut PooledConnection<
+        ConnectionManager<PgConnection>,
+    >| {
+        let created_contexts = cac_response.into_iter().filter_map(|item| match item {
+            ContextBulkResponse::PUT(context) => Some(context),
+            _ => None,
+        });
+        for (index, created_context) in
+            repaired_variants.into_iter().zip(created_contexts)
+        {
+            variants[index].context_id = Some(created_context.context_id);
+            variants[index].override_id = Some(created_context.override_id);
+        }
+        let variants_json = serde_json::to_value(variants).map_err(|err| {
+            log::error!("failed to serialize repaired variants: {}", err);
+            unexpected_error!("Something went wrong, failed to repair experiment")
+        })?;
+        diesel::update(experiments::experiments.find(experiment_id))
+            .set((
+                experiments::variants.eq(variants_json),
+                experiments::last_modified.eq(Utc::now()),
+                experiments::last_modified_by.eq(last_modified_by),
+            ))
+            .execute(conn)?;
+        Ok(())
+    };
+
+    apply_cac_operations(state, operations, conn, tenant, user, update_experiment).await
+}
+
+/// Checks every active experiment of `tenant` for drift against CAC, and
+/// repairs it when `repair` is set.
+pub async fn reconcile(
+    state: &Data<AppState>,
+    conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+    tenant: &Tenant,
+    user: &User,
+    repair: bool,
+) -> superposition::Result<DriftReport> {
+    let active_experiments: Vec<Experiment> = experiments::experiments
+        .filter(experiments::status.eq_any(vec![
+            ExperimentStatusType::CREATED,
+            ExperimentStatusType::INPROGRESS,
+        ]))
+        .load(conn)?;
+    let contexts = fetch_cac_contexts(state, conn, tenant, user).await?;
+    let drifts = detect_drift(&active_experiments, &contexts)?;
+    let mut failures = Vec::new();
+
+    if repair && !drifts.is_empty() {
+        // every experiment is repaired on its own, so that one failing repair
+        // does not hold back the others
+        for experiment in &active_experiments {
+            let experiment_id = experiment.id.to_string();
+            let experiment_drifts: Vec<&Drift> = drifts
+                .iter()
+                .filter(|drift| drift.experiment_id.as_ref() == Some(&experiment_id))
+                .collect();
+            if experiment_drifts.is_empty() {
+                continue;
+            }
+            if let Err(err) = repair_experiment(
+                state,
+                experiment,
+                &experiment_drifts,
+                conn,
+                tenant,
+                user,
+            )
+            .await
+            {
+                log::error!("failed to repair experiment {}: {}", experiment_id, err);
+                failures.push(RepairFailure {
+                    experiment_id: Some(experiment_id),
+                    error: err.to_string(),
+                });
+            }
+        }
+
+        let stray_operations: Vec<ContextAction> = drifts
+            .iter()
+            .filter(|drift| drift.drift_type == DriftType::StrayContext)
+            .filter_map(|drift| drift.context_id.clone())
+            .map(ContextAction::DELETE)
+            .collect();
+        if !stray_operations.is_empty() {
+            if let Err(err) = apply_cac_operations(
+                state,
+                stray_operations,
+                conn,
+                tenant,
+                user,
+                |_, _| Ok(()),
+            )
+            .await
+            {
+                log::error!("failed to delete stray contexts: {}", err);
+                failures.push(RepairFailure {
+                    experiment_id: None,
+                    error: err.to_string(),
+                });
+            }
+        }
+    }
+
+    Ok(DriftReport {
+        experiments_checked: active_experiments.len(),
+        repaired: repair && !drifts.is_empty() && failures.is_empty(),
+        drifts,
+        failures,
+    })
+}
+
+/// Periodically checks all tenants for experiment drift, logging what was
+/// found and repairing it when `auto_repair` is set.
+pub async fn run_drift_check_job(
+    state: Data<AppState>,
+    interval: Duration,
+    auto_repair: bool,
+) {
+    let tenants = get_job_tenants(&state);
+    let user = User::default();
+    let mut ticker = actix_web::rt::time::interval(interval);
+
+    loop {
+        ticker.tick().await;
+        for tenant in &tenants {
+            let namespace =
+                AppExecutionNamespace::new(&state, tenant, AppScope::EXPERIMENTATION);
+            let mut conn = match state.db_pool.get_conn(namespace.to_string()) {
+                Ok(conn) => conn,
+                Err(err) => {
+                    log::error!(
+                        "drift check: unable to get db connection for {}: {}",
+                        tenant.as_str(),
+                        err
+                    );
+                    continue;
+                }
+            };
+            match reconcile(&state, &mut conn, tenant, &user, auto_repair).await {
+                Ok(report) if report.drifts.is_empty() => {}
+                Ok(report) => log::warn!(
+                    "drift check: found {} drifted contexts for tenant {} (repaired: {}, failed repairs: {})",
+                    report.drifts.len(),
+                    tenant.as_str(),
+                    report.repaired,
+                    report.failures.len()
+                ),
+                Err(err) => log::error!(
+                    "drift check failed for tenant {}: {}",
+                    tenant.as_str(),
+                    err
+                ),
+            }
+        }
+    }
+}
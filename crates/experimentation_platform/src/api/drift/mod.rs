@@ -0,0 +1,5 @@
+pub mod handlers;
+pub mod helpers;
+pub mod types;
+pub use handlers::endpoints;
+pub use helpers::run_drift_check_job;
@@ -0,0 +1,59 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum DriftType {
+    // the variant's context is no longer present in CAC
+    MissingContext,
+    // the variant's context exists but its overrides were changed in CAC
+    AlteredOverride,
+    // the variant's context was moved to a different condition in CAC, the
+    // drift's `context_id` is the id it now has
+    MovedContext,
+    // a `variantIds` context in CAC which no active experiment owns
+    StrayContext,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Drift {
+    pub drift_type: DriftType,
+    pub experiment_id: Option<String>,
+    pub variant_id: String,
+    pub context_id: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct DriftReport {
+    pub experiments_checked: usize,
+    // set when a repair was requested and every drift was repaired
+    pub repaired: bool,
+    pub drifts: Vec<Drift>,
+    pub failures: Vec<RepairFailure>,
+}
+
+// A repair that could not be applied, `experiment_id` is unset for the
+// deletion of stray contexts
+#[derive(Serialize, Debug)]
+pub struct RepairFailure {
+    pub experiment_id: Option<String>,
+    pub error: String,
+}
+
+// A page of `GET /context/list`
+#[derive(Deserialize)]
+pub struct CacContextList {
+    pub total_pages: usize,
+    pub data: Vec<CacContext>,
+}
+
+// The subset of a CAC context needed to detect drift, as returned by
+// `GET /context/list`
+#[derive(Deserialize, Clone, Debug)]
+pub struct CacContext {
+    pub id: String,
+    pub value: Value,
+    pub override_id: String,
+    #[serde(rename = "override")]
+    pub override_: Value,
+}
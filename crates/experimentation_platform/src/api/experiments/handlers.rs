@@ -7,8 +7,12 @@ use actix_web::{
 };
 use chrono::{DateTime, Duration, NaiveDateTime, Utc};
 use diesel::{
+    dsl::sql,
+    pg::Pg,
     r2d2::{ConnectionManager, PooledConnection},
-    ExpressionMethods, PgConnection, QueryDsl, RunQueryDsl,
+    sql_types::{Array, Bool, Jsonb, Text},
+    BoxableExpression, Connection, ExpressionMethods, PgConnection,
+    PgTextExpressionMethods, QueryDsl, RunQueryDsl,
 };
 
 use service_utils::{
@@ -18,25 +22,42 @@ use service_utils::{
 use superposition_types::{SuperpositionUser, User};
 
 use reqwest::{Response, StatusCode};
-use service_utils::service::types::{AppState, DbConnection, Tenant};
+use service_utils::service::types::{
+    AppExecutionNamespace, AppScope, AppState, DbConnection, Tenant,
+};
 
 use super::{
     helpers::{
-        add_variant_dimension_to_ctx, check_variant_types,
-        check_variants_override_coverage, extract_override_keys, validate_experiment,
-        validate_override_keys,
+        add_variant_dimension_to_ctx, check_variants_override_coverage,
+        clone_create_request, experiment_history_entry, extract_override_keys,
+        find_layer_offset, lock_layer, validate_conclude_request, validate_experiment,
+        validate_experiment_layer, validate_experiment_metadata,
+        validate_experiment_schedule, validate_override_keys, validate_ramp_request,
+        validate_variants,
     },
     types::{
         AuditQueryFilters, ConcludeExperimentRequest, ContextAction, ContextBulkResponse,
-        ContextMoveReq, ContextPutReq, ExperimentCreateRequest, ExperimentCreateResponse,
-        ExperimentResponse, ExperimentsResponse, ListFilters, OverrideKeysUpdateRequest,
-        RampRequest, Variant,
+        ContextMoveReq, ContextPutReq, ExperimentCloneRequest, ExperimentCreateRequest,
+        ExperimentCreateResponse, ExperimentHistoryResponse,
+        ExperimentMetadataUpdateRequest, ExperimentResponse, ExperimentSortOn,
+        ExperimentsResponse, ListFilters, OverrideKeysUpdateRequest, RampRequest, SortBy,
+        Variant,
     },
 };
 
+use context_aware_config::api::{
+    context as cac_context, default_config::update_default_config_values,
+};
+
 use crate::{
+    api::change_requests::{
+        helpers::create_change_request, types::ChangeRequestResponse,
+    },
     api::experiments::helpers::validate_context,
-    db::models::{EventLog, Experiment, ExperimentStatusType},
+    api::webhooks::trigger_webhooks,
+    db::models::{
+        ChangeRequestAction, EventLog, Experiment, ExperimentStatusType, WebhookEvent,
+    },
     db::schema::{event_log::dsl as event_log, experiments::dsl as experiments},
 };
 
@@ -46,11 +67,14 @@ pub fn endpoints(scope: Scope) -> Scope {
     scope
         .service(get_audit_logs)
         .service(create)
+        .service(clone_experiment)
         .service(conclude_handler)
         .service(list_experiments)
         .service(get_experiment_handler)
         .service(ramp)
         .service(update_overrides)
+        .service(update_metadata)
+        .service(get_experiment_history)
 }
 
 async fn parse_error_response(
@@ -97,6 +121,103 @@ async fn process_cac_http_response(
     }
 }
 
+/// Applies `operations` to the contexts in CAC and then runs `persist` with
+/// the bulk response. When CAC is served from the same process both run in a
+/// single transaction on `conn`, so a failure in `persist` also rolls back the
+/// context changes. Otherwise the operations are sent to CAC over HTTP.
+pub async fn apply_cac_operations<T, F>(
+    state: &Data<AppState>,
+    operations: Vec<ContextAction>,
+    conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+    tenant: &Tenant,
+    user: &User,
+    persist: F,
+) -> superposition::Result<T>
+where
+    F: FnOnce(
+        Vec<ContextBulkResponse>,
+        &mut PooledConnection<ConnectionManager<PgConnection>>,
+    ) -> superposition::Result<T>,
+{
+    if state.cac_in_process {
+        return conn.transaction::<_, superposition::AppError, _>(|transaction_conn| {
+            let response = apply_cac_operations_in_process(
+                state,
+                operations,
+                transaction_conn,
+                tenant,
+                user,
+            )?;
+            persist(response, transaction_conn)
+        });
+    }
+
+    let http_client = reqwest::Client::new();
+    let url = state.cac_host.clone() + "/context/bulk-operations";
+    let response = http_client
+        .put(&url)
+        .header("x-tenant", tenant.as_str())
+        .header(
+            "Authorization",
+            format!("{} {}", user.get_auth_type(), user.get_auth_token()),
+        )
+        .json(&operations)
+        .send()
+        .await;
+
+    // directly return an error response if not a 200 response
+    let response = process_cac_http_response(response).await?;
+    persist(response, conn)
+}
+
+pub fn set_search_path(
+    state: &AppState,
+    conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+    tenant: &Tenant,
+    scope: AppScope,
+) -> superposition::Result<()> {
+    let namespace = AppExecutionNamespace::new(state, tenant, scope);
+    diesel::sql_query(format!(
+        "SET LOCAL search_path TO {}, public",
+        namespace.as_str()
+    ))
+    .execute(conn)?;
+    Ok(())
+}
+
+// Runs the CAC bulk operations on the experimentation connection, the caller
+// is expected to have started a transaction on it. CAC and experimentation
+// tables live in separate schemas of the same database, so the search path is
+// pointed at the CAC schema for the duration of the operations.
+fn apply_cac_operations_in_process(
+    state: &Data<AppState>,
+    operations: Vec<ContextAction>,
+    conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+    tenant: &Tenant,
+    user: &User,
+) -> superposition::Result<Vec<ContextBulkResponse>> {
+    // the types are converted through their JSON form so that both transports
+    // see exactly the same payload
+    let cac_operations: Vec<cac_context::types::ContextAction> =
+        serde_json::to_value(operations)
+            .and_then(serde_json::from_value)
+            .map_err(|err| {
+                log::error!("failed to convert operations for CAC: {}", err);
+                unexpected_error!("Something went wrong.")
+            })?;
+
+    set_search_path(state, conn, tenant, AppScope::CAC)?;
+    let response = cac_context::execute_bulk_operations(cac_operations, conn, user)?;
+    set_search_path(state, conn, tenant, AppScope::EXPERIMENTATION)?;
+
+    serde_json::to_value(response)
+        .and_then(serde_json::from_value)
+        .map_err(|err| {
+            log::error!("failed to parse CAC bulk operations response: {}", err);
+            unexpected_error!("Something went wrong.")
+        })
+}
+
 #[post("")]
 async fn create(
     state: Data<AppState>,
@@ -104,45 +225,79 @@ async fn create(
     db_conn: DbConnection,
     tenant: Tenant,
     user: User,
-) -> superposition::Result<Json<ExperimentCreateResponse>> {
-    use crate::db::schema::experiments::dsl::experiments;
-    let mut variants = req.variants.to_vec();
+) -> superposition::Result<HttpResponse> {
     let DbConnection(mut conn) = db_conn;
+    submit_create_request(state, req.into_inner(), &mut conn, tenant, user).await
+}
 
-    // Checking if experiment has exactly 1 control variant, and
-    // atleast 1 experimental variant
-    check_variant_types(&variants)?;
-    let unique_override_keys: Vec<String> = extract_override_keys(&variants[0].overrides)
-        .into_iter()
-        .collect();
-
-    let unique_ids_of_variants_from_req: HashSet<&str> =
-        HashSet::from_iter(variants.iter().map(|v| v.id.as_str()));
+#[post("/{id}/clone")]
+async fn clone_experiment(
+    state: Data<AppState>,
+    params: web::Path<i64>,
+    req: web::Json<ExperimentCloneRequest>,
+    db_conn: DbConnection,
+    tenant: Tenant,
+    user: User,
+) -> superposition::Result<HttpResponse> {
+    let DbConnection(mut conn) = db_conn;
+    let source = get_experiment(params.into_inner(), &mut conn)?;
+    let req = clone_create_request(source, req.into_inner())?;
+    submit_create_request(state, req, &mut conn, tenant, user).await
+}
 
-    if unique_ids_of_variants_from_req.len() != variants.len() {
-        return Err(bad_argument!(
-            "Variant ids are expected to be unique. Provide unqiue variant IDs"
-        ));
-    }
-    validate_override_keys(&unique_override_keys)?;
+// creates the experiment right away, or raises a change request for it when
+// experiments need approval
+async fn submit_create_request(
+    state: Data<AppState>,
+    req: ExperimentCreateRequest,
+    conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+    tenant: Tenant,
+    user: User,
+) -> superposition::Result<HttpResponse> {
+    // generating snowflake id for experiment
+    let experiment_id = {
+        let mut snowflake_generator = state.snowflake_generator.lock().unwrap();
+        snowflake_generator.real_time_generate()
+    };
 
-    // Checking if all the variants are overriding the mentioned keys
-    let variant_overrides = variants
-        .iter()
-        .map(|variant| &variant.overrides)
-        .collect::<Vec<&Map<String, Value>>>();
-    let are_valid_variants =
-        check_variants_override_coverage(&variant_overrides, &unique_override_keys);
-    if !are_valid_variants {
-        return Err(bad_argument!(
-            "all variants should contain the keys mentioned in override_keys. Check if any of the following keys [{}] are missing from keys in your variants",
-                unique_override_keys.join(",")
-            )
+    if state.experiment_approval.enabled {
+        validate_create_request(&state, &req, conn)?;
+        let change_request = create_change_request(
+            &state,
+            experiment_id,
+            ChangeRequestAction::CREATE,
+            &req,
+            &user,
+            conn,
+        )?;
+        return Ok(
+            HttpResponse::Accepted().json(ChangeRequestResponse::from(change_request))
         );
     }
 
+    let created_by = user.get_email();
+    let inserted_experiment =
+        create_experiment(state, experiment_id, req, conn, tenant, user, created_by)
+            .await?;
+
+    Ok(HttpResponse::Ok().json(ExperimentCreateResponse::from(inserted_experiment)))
+}
+
+fn validate_create_request(
+    state: &Data<AppState>,
+    req: &ExperimentCreateRequest,
+    conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+) -> superposition::Result<Vec<String>> {
+    let unique_override_keys = validate_variants(&req.variants)?;
+
     // validating context
     validate_context(&req.context)?;
+    validate_experiment_metadata(&req.tags, &req.external_links)?;
+    validate_experiment_schedule(req)?;
+    validate_experiment_layer(req.layer.as_ref(), req.layer_allocation)?;
+    if let (Some(layer), Some(allocation)) = (&req.layer, req.layer_allocation) {
+        find_layer_offset(layer, allocation, conn)?;
+    }
 
     // validating experiment against other active experiments based on permission flags
     let flags = &state.experimentation_flags;
@@ -150,16 +305,28 @@ async fn create(
         &req.context,
         &unique_override_keys,
         None,
-        &flags,
-        &mut conn,
+        req.layer.as_deref(),
+        flags,
+        conn,
     )?;
     if !valid {
         return Err(bad_argument!(reason));
     }
 
-    // generating snowflake id for experiment
-    let mut snowflake_generator = state.snowflake_generator.lock().unwrap();
-    let experiment_id = snowflake_generator.real_time_generate();
+    Ok(unique_override_keys)
+}
+
+pub async fn create_experiment(
+    state: Data<AppState>,
+    experiment_id: i64,
+    req: ExperimentCreateRequest,
+    conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+    tenant: Tenant,
+    user: User,
+    created_by: String,
+) -> superposition::Result<Experiment> {
+    let unique_override_keys = validate_create_request(&state, &req, conn)?;
+    let mut variants = req.variants.to_vec();
 
     //create overrides in CAC, if successfull then create experiment in DB
     let mut cac_operations: Vec<ContextAction> = vec![];
@@ -187,64 +354,110 @@ async fn create(
         cac_operations.push(ContextAction::PUT(payload));
     }
 
-    // creating variants' context in CAC
-    let http_client = reqwest::Client::new();
-    let url = state.cac_host.clone() + "/context/bulk-operations";
+    // creating variants' context in CAC, and the experiment in DB
+    let persist_experiment =
+        |cac_response: Vec<ContextBulkResponse>,
+         conn: &mut PooledConnection<ConnectionManager<PgConnection>>| {
+            let created_contexts =
+                cac_response.into_iter().fold(Vec::new(), |mut acc, item| {
+                    if let ContextBulkResponse::PUT(context) = item {
+                        acc.push(context);
+                    } else {
+                        log::error!("Unexpected response item: {:?}", item);
+                    }
+                    acc
+                });
+            for i in 0..created_contexts.len() {
+                let created_context = &created_contexts[i];
+                variants[i].context_id = Some(created_context.context_id.clone());
+                variants[i].override_id = Some(created_context.override_id.clone());
+            }
 
-    // Step 1: Perform the HTTP request and handle errors
-    let response = http_client
-        .put(&url)
-        .header("x-tenant", tenant.as_str())
-        .header(
-            "Authorization",
-            format!("{} {}", user.get_auth_type(), user.get_auth_token()),
-        )
-        .json(&cac_operations)
-        .send()
-        .await;
+            insert_experiment(
+                experiment_id,
+                &req,
+                &unique_override_keys,
+                variants,
+                created_by,
+                &user,
+                conn,
+            )
+        };
 
-    // directly return an error response if not a 200 response
-    let created_contexts = process_cac_http_response(response).await?.into_iter().fold(
-        Vec::new(),
-        |mut acc, item| {
-            if let ContextBulkResponse::PUT(context) = item {
-                acc.push(context);
-            } else {
-                log::error!("Unexpected response item: {:?}", item);
-            }
-            acc
-        },
-    );
-    for i in 0..created_contexts.len() {
-        let created_context = &created_contexts[i];
-        variants[i].context_id = Some(created_context.context_id.clone());
-        variants[i].override_id = Some(created_context.override_id.clone());
-    }
+    let experiment = apply_cac_operations(
+        &state,
+        cac_operations,
+        conn,
+        &tenant,
+        &user,
+        persist_experiment,
+    )
+    .await?;
+    trigger_webhooks(&state, &tenant, WebhookEvent::CREATED, &experiment, conn);
+    Ok(experiment)
+}
 
-    // inserting experiment in db
-    let new_experiment = Experiment {
-        id: experiment_id,
-        created_by: user.get_email(),
-        created_at: Utc::now(),
-        last_modified: Utc::now(),
-        name: req.name.to_string(),
-        override_keys: unique_override_keys.to_vec(),
-        traffic_percentage: 0,
-        status: ExperimentStatusType::CREATED,
-        context: req.context.clone(),
-        variants: serde_json::to_value(variants).unwrap(),
-        last_modified_by: user.get_email(),
-        chosen_variant: None,
-    };
+fn insert_experiment(
+    experiment_id: i64,
+    req: &ExperimentCreateRequest,
+    unique_override_keys: &[String],
+    variants: Vec<Variant>,
+    created_by: String,
+    user: &User,
+    conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+) -> superposition::Result<Experiment> {
+    use crate::db::schema::experiments::dsl::experiments;
+
+    conn.transaction::<_, superposition::AppError, _>(|transaction_conn| {
+        let layer_offset = match (&req.layer, req.layer_allocation) {
+            (Some(layer), Some(allocation)) => {
+                lock_layer(layer, transaction_conn)?;
+                Some(find_layer_offset(layer, allocation, transaction_conn)?)
+            }
+            _ => None,
+        };
 
-    let mut inserted_experiments = diesel::insert_into(experiments)
-        .values(&new_experiment)
-        .get_results(&mut conn)?;
+        let new_experiment = Experiment {
+            id: experiment_id,
+            created_by,
+            created_at: Utc::now(),
+            last_modified: Utc::now(),
+            name: req.name.to_string(),
+            override_keys: unique_override_keys.to_vec(),
+            traffic_percentage: 0,
+            status: ExperimentStatusType::CREATED,
+            context: req.context.clone(),
+            variants: serde_json::to_value(variants).unwrap(),
+            last_modified_by: user.get_email(),
+            chosen_variant: None,
+
+            description: req.description.clone(),
+            hypothesis: req.hypothesis.clone(),
+            owner_team: req.owner_team.clone(),
+            tags: req.tags.clone(),
+            external_links: req.external_links.clone(),
+
+            start_at: req.start_at,
+            end_at: req.end_at,
+            start_traffic_percentage: req.start_traffic_percentage.map(i32::from),
+            fallback_variant: req
+                .fallback_variant
+                .as_ref()
+                .map(|variant_id| format!("{}-{}", experiment_id, variant_id)),
+
+            layer: req.layer.clone(),
+            layer_offset,
+            layer_allocation: req.layer_allocation.map(i32::from),
+
+            bandit: req.bandit,
+        };
 
-    let inserted_experiment: Experiment = inserted_experiments.remove(0);
-    let response = ExperimentCreateResponse::from(inserted_experiment);
+        let mut inserted_experiments = diesel::insert_into(experiments)
+            .values(&new_experiment)
+            .get_results(transaction_conn)?;
 
-    return Ok(Json(response));
+        Ok(inserted_experiments.remove(0))
+    })
 }
 
 #[patch("/{experiment_id}/conclude")]
@@ -255,25 +468,51 @@ async fn conclude_handler(
     db_conn: DbConnection,
     tenant: Tenant,
     user: User,
-) -> superposition::Result<Json<ExperimentResponse>> {
-    let DbConnection(conn) = db_conn;
-    let response = conclude(
-        state,
-        path.into_inner(),
-        req.into_inner(),
-        conn,
-        tenant,
-        user,
-    )
-    .await?;
-    return Ok(Json(ExperimentResponse::from(response)));
+) -> superposition::Result<HttpResponse> {
+    let DbConnection(mut conn) = db_conn;
+    let experiment_id = path.into_inner();
+    let req = req.into_inner();
+
+    if state.experiment_approval.enabled {
+        let experiment = get_experiment(experiment_id, &mut conn)?;
+        validate_conclude_request(&experiment, &req.chosen_variant)?;
+        validate_default_promotion(&state, &req)?;
+        let change_request = create_change_request(
+            &state,
+            experiment_id,
+            ChangeRequestAction::CONCLUDE,
+            &req,
+            &user,
+            &mut conn,
+        )?;
+        return Ok(
+            HttpResponse::Accepted().json(ChangeRequestResponse::from(change_request))
+        );
+    }
+
+    let response = conclude(state, experiment_id, req, &mut conn, tenant, user).await?;
+    Ok(HttpResponse::Ok().json(ExperimentResponse::from(response)))
+}
+
+fn validate_default_promotion(
+    state: &AppState,
+    req: &ConcludeExperimentRequest,
+) -> superposition::Result<()> {
+    // promoting updates default configs and contexts together, which can only
+    // be done atomically when CAC shares the experimentation db connection
+    if req.promote_to_default && !state.cac_in_process {
+        return Err(bad_argument!(
+            "promote_to_default is only supported when CAC runs in the same process, enable CAC_IN_PROCESS to use it"
+        ));
+    }
+    Ok(())
 }
 
 pub async fn conclude(
     state: Data<AppState>,
     experiment_id: i64,
     req: ConcludeExperimentRequest,
-    mut conn: PooledConnection<ConnectionManager<PgConnection>>,
+    conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
     tenant: Tenant,
     user: User,
 ) -> superposition::Result<Experiment> {
@@ -283,14 +522,10 @@ pub async fn conclude(
 
     let experiment: Experiment = dsl::experiments
         .find(experiment_id)
-        .get_result::<Experiment>(&mut conn)?;
+        .get_result::<Experiment>(conn)?;
 
-    if matches!(experiment.status, ExperimentStatusType::CONCLUDED) {
-        return Err(bad_argument!(
-            "experiment with id {} is already concluded",
-            experiment_id
-        ));
-    }
+    validate_conclude_request(&experiment, &winner_variant_id)?;
+    validate_default_promotion(&state, &req)?;
 
     let experiment_context = experiment.context.as_object().ok_or_else(|| {
         log::error!("could not convert the context read from DB to JSON object");
@@ -308,13 +543,19 @@ pub async fn conclude(
     })?;
 
     let mut is_valid_winner_variant = false;
+    let mut promoted_overrides = Map::new();
     for variant in experiment_variants {
         let context_id = variant.context_id.ok_or_else(|| {
             log::error!("context id not available for variant {:?}", variant.id);
             unexpected_error!("Something went wrong, failed to conclude experiment")
         })?;
 
-        if variant.id == winner_variant_id {
+        if variant.id == winner_variant_id && req.promote_to_default {
+            is_valid_winner_variant = true;
+            promoted_overrides = variant.overrides;
+
+            operations.push(ContextAction::DELETE(context_id));
+        } else if variant.id == winner_variant_id {
             let context_move_req = ContextMoveReq {
                 context: experiment_context.clone(),
             };
@@ -334,34 +575,33 @@ pub async fn conclude(
         ));
     }
 
-    // calling CAC bulk api with operations as payload
-    let http_client = reqwest::Client::new();
-    let url = state.cac_host.clone() + "/context/bulk-operations";
-    let response = http_client
-        .put(&url)
-        .header("x-tenant", tenant.as_str())
-        .header(
-            "Authorization",
-            format!("{} {}", user.get_auth_type(), user.get_auth_token()),
-        )
-        .json(&operations)
-        .send()
-        .await;
-
-    let _ = process_cac_http_response(response).await?;
-
-    // updating experiment status in db
-    let updated_experiment = diesel::update(dsl::experiments)
-        .filter(dsl::id.eq(experiment_id))
-        .set((
-            dsl::status.eq(ExperimentStatusType::CONCLUDED),
-            dsl::last_modified.eq(Utc::now()),
-            dsl::last_modified_by.eq(user.get_email()),
-            dsl::chosen_variant.eq(Some(winner_variant_id)),
-        ))
-        .get_result::<Experiment>(&mut conn)?;
+    // applying operations in CAC and updating experiment status in db
+    let last_modified_by = user.get_email();
+    let update_experiment =
+        |_: Vec<ContextBulkResponse>,
+         conn: &mut PooledConnection<ConnectionManager<PgConnection>>| {
+            if req.promote_to_default {
+                set_search_path(&state, conn, &tenant, AppScope::CAC)?;
+                update_default_config_values(&state, &promoted_overrides, conn, &user)?;
+                set_search_path(&state, conn, &tenant, AppScope::EXPERIMENTATION)?;
+            }
+            let updated_experiment = diesel::update(dsl::experiments)
+                .filter(dsl::id.eq(experiment_id))
+                .set((
+                    dsl::status.eq(ExperimentStatusType::CONCLUDED),
+                    dsl::last_modified.eq(Utc::now()),
+                    dsl::last_modified_by.eq(last_modified_by),
+                    dsl::chosen_variant.eq(Some(winner_variant_id)),
+                ))
+                .get_result::<Experiment>(conn)?;
+            Ok(updated_experiment)
+        };
 
-    return Ok(updated_experiment);
+    let experiment =
+        apply_cac_operations(&state, operations, conn, &tenant, &user, update_experiment)
+            .await?;
+    trigger_webhooks(&state, &tenant, WebhookEvent::CONCLUDED, &experiment, conn);
+    Ok(experiment)
 }
 
 #[get("")]
@@ -391,29 +631,111 @@ async fn list_experiments(
         return Ok(HttpResponse::NotModified().finish());
     };
 
+    let filters = filters.into_inner();
+    let experiment_ids = filters
+        .experiment_ids
+        .clone()
+        .map(|ids| {
+            ids.0
+                .iter()
+                .map(|id| {
+                    id.parse::<i64>().map_err(|_| {
+                        bad_argument!("experiment_ids should be a list of experiment ids")
+                    })
+                })
+                .collect::<superposition::Result<Vec<i64>>>()
+        })
+        .transpose()?;
+    let dimensions = filters
+        .dimensions
+        .clone()
+        .map(|dimensions| {
+            dimensions
+                .0
+                .iter()
+                .map(|dimension| parse_dimension_filter(dimension))
+                .collect::<superposition::Result<Vec<(String, Value)>>>()
+        })
+        .transpose()?
+        .unwrap_or_default();
+
     let query_builder = |filters: &ListFilters| {
         let mut builder = experiments::experiments.into_boxed();
         if let Some(states) = filters.status.clone() {
             builder = builder.filter(experiments::status.eq_any(states.0.clone()));
         }
+        if let Some(name) = filters.experiment_name.clone() {
+            builder = builder.filter(
+                experiments::name.ilike(format!("%{}%", escape_like_pattern(&name))),
+            );
+        }
+        if let Some(ids) = experiment_ids.clone() {
+            builder = builder.filter(experiments::id.eq_any(ids));
+        }
+        if let Some(created_by) = filters.created_by.clone() {
+            builder = builder.filter(experiments::created_by.eq(created_by));
+        }
+        if let Some(keys) = filters.override_keys.clone() {
+            builder = builder.filter(array_column_contains("override_keys", keys.0));
+        }
+        if let Some(owner_team) = filters.owner_team.clone() {
+            builder = builder.filter(experiments::owner_team.eq(owner_team));
+        }
+        if let Some(layer) = filters.layer.clone() {
+            builder = builder.filter(experiments::layer.eq(layer));
+        }
+        if let Some(tags) = filters.tags.clone() {
+            builder = builder.filter(array_column_contains("tags", tags.0));
+        }
+        for (dimension, value) in dimensions.iter() {
+            builder = builder.filter(context_has_dimension(dimension, value));
+        }
+        // experiments looked up by name, id or creator are searched across
+        // all time unless a from_date is given, otherwise only the ones
+        // modified in the last 24 hours are listed
         let now = Utc::now();
-        builder
-            .filter(
-                experiments::last_modified
-                    .ge(filters.from_date.unwrap_or(now - Duration::hours(24))),
-            )
-            .filter(experiments::last_modified.le(filters.to_date.unwrap_or(now)))
+        let identified = filters.experiment_name.is_some()
+            || experiment_ids.is_some()
+            || filters.created_by.is_some();
+        match filters.from_date {
+            Some(from_date) => {
+                builder = builder.filter(experiments::last_modified.ge(from_date))
+            }
+            None if !identified => {
+                builder = builder
+                    .filter(experiments::last_modified.ge(now - Duration::hours(24)))
+            }
+            None => (),
+        }
+        builder.filter(experiments::last_modified.le(filters.to_date.unwrap_or(now)))
     };
-    let filters = filters.into_inner();
     let base_query = query_builder(&filters);
     let count_query = query_builder(&filters);
 
     let limit = filters.count.unwrap_or(10);
     let offset = (filters.page.unwrap_or(1) - 1) * limit;
-    let query = base_query
-        .order(experiments::last_modified.desc())
-        .limit(limit)
-        .offset(offset);
+    let sort_by = filters.sort_by.unwrap_or_default();
+    let base_query = match (filters.sort_on.unwrap_or_default(), sort_by) {
+        (ExperimentSortOn::CreatedAt, SortBy::Asc) => {
+            base_query.order(experiments::created_at.asc())
+        }
+        (ExperimentSortOn::CreatedAt, SortBy::Desc) => {
+            base_query.order(experiments::created_at.desc())
+        }
+        (ExperimentSortOn::LastModified, SortBy::Asc) => {
+            base_query.order(experiments::last_modified.asc())
+        }
+        (ExperimentSortOn::LastModified, SortBy::Desc) => {
+            base_query.order(experiments::last_modified.desc())
+        }
+        (ExperimentSortOn::Name, SortBy::Asc) => {
+            base_query.order(experiments::name.asc())
+        }
+        (ExperimentSortOn::Name, SortBy::Desc) => {
+            base_query.order(experiments::name.desc())
+        }
+    };
+    let query = base_query.limit(limit).offset(offset);
 
     let number_of_experiments = count_query.count().get_result(&mut conn)?;
 
@@ -431,6 +753,54 @@ async fn list_experiments(
     }))
 }
 
+fn parse_dimension_filter(filter: &str) -> superposition::Result<(String, Value)> {
+    let (dimension, value) = filter.split_once('=').ok_or_else(|| {
+        bad_argument!(
+            "dimension filter {} should be of the form dimension=value",
+            filter
+        )
+    })?;
+    // values are compared as JSON so that numbers and booleans in the
+    // context match, anything that isn't valid JSON is treated as a string
+    let value =
+        serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.to_string()));
+    Ok((dimension.to_string(), value))
+}
+
+// Escapes the wildcards of LIKE patterns so that `value` is matched literally
+fn escape_like_pattern(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+// The text array columns of experiments hold the not_null_text domain, which
+// postgres cannot compare with a text[] directly, so the column is cast first
+fn array_column_contains(
+    column: &str,
+    values: Vec<String>,
+) -> Box<dyn BoxableExpression<experiments::experiments, Pg, SqlType = Bool>> {
+    Box::new(sql::<Bool>(&format!("{column}::text[] @> ")).bind::<Array<Text>, _>(values))
+}
+
+// Matches experiments having a condition on `dimension` whose operands
+// include `value`, be it an `==` comparison or one of the values of an `in`.
+// The path is strict as lax mode unwraps the arrays before filtering them
+fn context_has_dimension(
+    dimension: &str,
+    value: &Value,
+) -> Box<dyn BoxableExpression<experiments::experiments, Pg, SqlType = Bool>> {
+    let expression = sql::<Bool>(
+        "jsonb_path_exists(context::jsonb, 'strict $.** ? (@.type() == \"array\" && exists(@[*] ? (@.var == $name)) && exists(@[*] ? (@ == $value || (@.type() == \"array\" && exists(@[*] ? (@ == $value))))))', jsonb_build_object('name', ",
+    )
+    .bind::<Text, _>(dimension.to_string())
+    .sql(", 'value', ")
+    .bind::<Jsonb, _>(value.clone())
+    .sql("))");
+    Box::new(expression)
+}
+
 #[get("/{id}")]
 async fn get_experiment_handler(
     params: web::Path<i64>,
@@ -455,55 +825,74 @@ pub fn get_experiment(
 
 #[patch("/{id}/ramp")]
 async fn ramp(
+    state: Data<AppState>,
     params: web::Path<i64>,
     req: web::Json<RampRequest>,
     db_conn: DbConnection,
+    tenant: Tenant,
     user: User,
-) -> superposition::Result<Json<ExperimentResponse>> {
+) -> superposition::Result<HttpResponse> {
     let DbConnection(mut conn) = db_conn;
     let exp_id = params.into_inner();
+    let req = req.into_inner();
+
+    let approval = &state.experiment_approval;
+    if approval.enabled && req.traffic_percentage > approval.ramp_threshold as u64 {
+        let experiment = get_experiment(exp_id, &mut conn)?;
+        validate_ramp_request(&experiment, req.traffic_percentage)?;
+        let change_request = create_change_request(
+            &state,
+            exp_id,
+            ChangeRequestAction::RAMP,
+            &req,
+            &user,
+            &mut conn,
+        )?;
+        return Ok(
+            HttpResponse::Accepted().json(ChangeRequestResponse::from(change_request))
+        );
+    }
 
-    let experiment: Experiment = experiments::experiments
-        .find(exp_id)
-        .get_result::<Experiment>(&mut conn)?;
+    let updated_experiment = ramp_experiment(
+        &state,
+        exp_id,
+        req.traffic_percentage,
+        &mut conn,
+        &tenant,
+        &user,
+    )?;
+    Ok(HttpResponse::Ok().json(ExperimentResponse::from(updated_experiment)))
+}
 
-    let old_traffic_percentage = experiment.traffic_percentage as u8;
-    let new_traffic_percentage = req.traffic_percentage as u8;
-    let experiment_variants: Vec<Variant> = serde_json::from_value(experiment.variants)
-        .map_err(|e| {
-        log::error!(
-            "failed to parse existing experiment variants while ramping {}",
-            e
-        );
-        unexpected_error!("Something went wrong, failed to ramp traffic percentage")
-    })?;
-    let variants_count = experiment_variants.len() as u8;
-    let max = 100 / variants_count;
+pub fn ramp_experiment(
+    state: &Data<AppState>,
+    experiment_id: i64,
+    traffic_percentage: u64,
+    conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+    tenant: &Tenant,
+    user: &User,
+) -> superposition::Result<Experiment> {
+    let experiment = get_experiment(experiment_id, conn)?;
+    validate_ramp_request(&experiment, traffic_percentage)?;
 
-    if matches!(experiment.status, ExperimentStatusType::CONCLUDED) {
-        return Err(bad_argument!(
-            "experiment already concluded, cannot ramp a concluded experiment"
-        ));
-    } else if new_traffic_percentage > max {
-        return Err(bad_argument!(
-            "The traffic_percentage cannot exceed {}. Provide a traffic percentage less than {}", max, max
-        ))?;
-    } else if new_traffic_percentage != 0
-        && new_traffic_percentage == old_traffic_percentage
-    {
-        return Err(bad_argument!("The traffic_percentage is same as provided"))?;
-    }
     let updated_experiment: Experiment = diesel::update(experiments::experiments)
-        .filter(experiments::id.eq(exp_id))
+        .filter(experiments::id.eq(experiment_id))
         .set((
-            experiments::traffic_percentage.eq(req.traffic_percentage as i32),
+            experiments::traffic_percentage.eq(traffic_percentage as i32),
             experiments::last_modified.eq(Utc::now()),
             experiments::last_modified_by.eq(user.get_email()),
             experiments::status.eq(ExperimentStatusType::INPROGRESS),
         ))
-        .get_result(&mut conn)?;
+        .get_result(conn)?;
 
-    return Ok(Json(ExperimentResponse::from(updated_experiment)));
+    trigger_webhooks(
+        state,
+        tenant,
+        WebhookEvent::RAMPED,
+        &updated_experiment,
+        conn,
+    );
+    Ok(updated_experiment)
 }
 
 #[put("/{id}/overrides")]
@@ -582,6 +971,7 @@ async fn update_overrides(
                 overrides: variant.overrides,
                 override_id: None,
                 context_id: None,
+                weight: existing_variant.weight,
             }
         })
         .collect();
@@ -607,6 +997,7 @@ async fn update_overrides(
         &experiment.context,
         &override_keys,
         Some(experiment_id),
+        experiment.layer.as_deref(),
         &flags,
         &mut conn,
     )?;
@@ -651,54 +1042,82 @@ async fn update_overrides(
         cac_operations.push(ContextAction::PUT(payload));
     }
 
-    let http_client = reqwest::Client::new();
-    let url = state.cac_host.clone() + "/context/bulk-operations";
+    let last_modified_by = user.get_email();
+    let update_experiment = |cac_response: Vec<ContextBulkResponse>,
+                             conn: &mut PooledConnection<
+        ConnectionManager<PgConnection>,
+    >| {
+        let created_contexts =
+            cac_response.into_iter().fold(Vec::new(), |mut acc, item| {
+                if let ContextBulkResponse::PUT(context) = item {
+                    acc.push(context);
+                } else {
+                    log::error!("Unexpected response item: {:?}", item);
+                }
+                acc
+            });
+        for i in 0..created_contexts.len() {
+            let created_context = &created_contexts[i];
+
+            new_variants[i].context_id = Some(created_context.context_id.clone());
+            new_variants[i].override_id = Some(created_context.override_id.clone());
+        }
 
-    let response = http_client
-        .put(&url)
-        .header("x-tenant", tenant.as_str())
-        .header(
-            "Authorization",
-            format!("{} {}", user.get_auth_type(), user.get_auth_token()),
-        )
-        .json(&cac_operations)
-        .send()
-        .await;
+        /*************************** Updating experiment in DB **************************/
+        let new_variants_json = serde_json::to_value(new_variants).map_err(|e| {
+            log::error!("failed to serialize new variants to json with error: {e}");
+            bad_argument!("failed to update experiment, bad variant data")
+        })?;
+        let updated_experiment =
+            diesel::update(experiments::experiments.find(experiment_id))
+                .set((
+                    experiments::variants.eq(new_variants_json),
+                    experiments::override_keys.eq(override_keys),
+                    experiments::last_modified.eq(Utc::now()),
+                    experiments::last_modified_by.eq(last_modified_by),
+                ))
+                .get_result::<Experiment>(conn)?;
+        Ok(updated_experiment)
+    };
 
-    // directly return an error response if not a 200 response
-    let created_contexts = process_cac_http_response(response).await?.into_iter().fold(
-        Vec::new(),
-        |mut acc, item| {
-            if let ContextBulkResponse::PUT(context) = item {
-                acc.push(context);
-            } else {
-                log::error!("Unexpected response item: {:?}", item);
-            }
-            acc
-        },
-    );
-    for i in 0..created_contexts.len() {
-        let created_context = &created_contexts[i];
+    let updated_experiment = apply_cac_operations(
+        &state,
+        cac_operations,
+        &mut conn,
+        &tenant,
+        &user,
+        update_experiment,
+    )
+    .await?;
 
-        new_variants[i].context_id = Some(created_context.context_id.clone());
-        new_variants[i].override_id = Some(created_context.override_id.clone());
-    }
+    Ok(Json(ExperimentResponse::from(updated_experiment)))
+}
+
+#[patch("/{id}/metadata")]
+async fn update_metadata(
+    params: web::Path<i64>,
+    req: web::Json<ExperimentMetadataUpdateRequest>,
+    db_conn: DbConnection,
+    user: User,
+) -> superposition::Result<Json<ExperimentResponse>> {
+    let DbConnection(mut conn) = db_conn;
+    let experiment_id = params.into_inner();
+    let req = req.into_inner();
+
+    validate_experiment_metadata(
+        req.tags.as_deref().unwrap_or_default(),
+        req.external_links.as_deref().unwrap_or_default(),
+    )?;
 
-    /*************************** Updating experiment in DB **************************/
-    let new_variants_json = serde_json::to_value(new_variants).map_err(|e| {
-        log::error!("failed to serialize new variants to json with error: {e}");
-        bad_argument!("failed to update experiment, bad variant data")
-    })?;
     let updated_experiment = diesel::update(experiments::experiments.find(experiment_id))
         .set((
-            experiments::variants.eq(new_variants_json),
-            experiments::override_keys.eq(override_keys),
+            &req,
             experiments::last_modified.eq(Utc::now()),
             experiments::last_modified_by.eq(user.get_email()),
         ))
         .get_result::<Experiment>(&mut conn)?;
 
-    return Ok(Json(ExperimentResponse::from(updated_experiment)));
+    Ok(Json(ExperimentResponse::from(updated_experiment)))
 }
 
 #[get("/audit")]
@@ -750,3 +1169,30 @@ async fn get_audit_logs(
         "data": logs
     })))
 }
+
+#[get("/{id}/history")]
+async fn get_experiment_history(
+    params: web::Path<i64>,
+    db_conn: DbConnection,
+) -> superposition::Result<Json<ExperimentHistoryResponse>> {
+    let DbConnection(mut conn) = db_conn;
+    let experiment = get_experiment(params.into_inner(), &mut conn)?;
+    let experiment_id = experiment.id.to_string();
+
+    let logs: Vec<EventLog> = event_log::event_log
+        .filter(event_log::table_name.eq("experiments"))
+        .filter(
+            sql::<Bool>("((new_data ->> 'id') = ")
+                .bind::<Text, _>(experiment_id.clone())
+                .sql(" OR (original_data ->> 'id') = ")
+                .bind::<Text, _>(experiment_id.clone())
+                .sql(")"),
+        )
+        .order(event_log::timestamp.asc())
+        .load(&mut conn)?;
+
+    Ok(Json(ExperimentHistoryResponse {
+        experiment_id,
+        history: logs.into_iter().map(experiment_history_entry).collect(),
+    }))
+}
@@ -1,13 +1,17 @@
-use super::types::{Variant, VariantType};
-use crate::db::models::{Experiment, ExperimentStatusType};
+use super::types::{
+    ExperimentCloneRequest, ExperimentCreateRequest, FieldDiff, HistoryEntry,
+    HistoryEventType, Variant, VariantType,
+};
+use crate::db::models::{EventLog, Experiment, ExperimentStatusType};
+use chrono::Utc;
 use diesel::pg::PgConnection;
 use diesel::{BoolExpressionMethods, ExpressionMethods, QueryDsl, RunQueryDsl};
 use serde_json::{Map, Value};
 use service_utils::helpers::extract_dimensions;
-use service_utils::service::types::ExperimentationFlags;
-use std::collections::HashSet;
+use service_utils::service::types::{AppState, ExperimentationFlags, Tenant};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 
-use service_utils::{bad_argument, result as superposition};
+use service_utils::{bad_argument, result as superposition, unexpected_error};
 
 pub fn check_variant_types(variants: &Vec<Variant>) -> superposition::Result<()> {
     let mut experimental_variant_cnt = 0;
@@ -37,6 +41,243 @@ pub fn check_variant_types(variants: &Vec<Variant>) -> superposition::Result<()>
     Ok(())
 }
 
+/// Checks that the variants have exactly one control variant, unique ids and
+/// override the same keys, returning those keys.
+pub fn validate_variants(variants: &Vec<Variant>) -> superposition::Result<Vec<String>> {
+    // Checking if experiment has exactly 1 control variant, and
+    // atleast 1 experimental variant
+    check_variant_types(variants)?;
+    let unique_override_keys: Vec<String> = extract_override_keys(&variants[0].overrides)
+        .into_iter()
+        .collect();
+
+    let unique_ids_of_variants_from_req: HashSet<&str> =
+        HashSet::from_iter(variants.iter().map(|v| v.id.as_str()));
+
+    if unique_ids_of_variants_from_req.len() != variants.len() {
+        return Err(bad_argument!(
+            "Variant ids are expected to be unique. Provide unqiue variant IDs"
+        ));
+    }
+    validate_override_keys(&unique_override_keys)?;
+
+    // Checking if all the variants are overriding the mentioned keys
+    let variant_overrides = variants
+        .iter()
+        .map(|variant| &variant.overrides)
+        .collect::<Vec<&Map<String, Value>>>();
+    let are_valid_variants =
+        check_variants_override_coverage(&variant_overrides, &unique_override_keys);
+    if !are_valid_variants {
+        return Err(bad_argument!(
+            "all variants should contain the keys mentioned in override_keys. Check if any of the following keys [{}] are missing from keys in your variants",
+                unique_override_keys.join(",")
+            )
+        );
+    }
+
+    Ok(unique_override_keys)
+}
+
+/// Builds the request creating a copy of `experiment` in CREATED state. The
+/// variants keep their ids and overrides, the schedule is left out as it
+/// would usually be in the past.
+pub fn clone_create_request(
+    experiment: Experiment,
+    req: ExperimentCloneRequest,
+) -> superposition::Result<ExperimentCreateRequest> {
+    let experiment_variants: Vec<Variant> = serde_json::from_value(experiment.variants)
+        .map_err(|err| {
+        log::error!("failed to parse variants of experiment to clone: {}", err);
+        unexpected_error!("Something went wrong, failed to clone experiment")
+    })?;
+    // variant ids are stored prefixed with the id of their experiment
+    let id_prefix = format!("{}-", experiment.id);
+    let variants = experiment_variants
+        .into_iter()
+        .map(|variant| Variant {
+            id: variant
+                .id
+                .strip_prefix(&id_prefix)
+                .map(String::from)
+                .unwrap_or(variant.id),
+            variant_type: variant.variant_type,
+            context_id: None,
+            override_id: None,
+            overrides: variant.overrides,
+            weight: None,
+        })
+        .collect();
+
+    Ok(ExperimentCreateRequest {
+        name: req
+            .name
+            .unwrap_or_else(|| format!("{} (copy)", experiment.name)),
+        context: req.context.unwrap_or(experiment.context),
+        variants,
+        description: experiment.description,
+        hypothesis: experiment.hypothesis,
+        owner_team: experiment.owner_team,
+        tags: experiment.tags,
+        external_links: experiment.external_links,
+        start_at: None,
+        end_at: None,
+        start_traffic_percentage: None,
+        fallback_variant: None,
+        layer_allocation: experiment
+            .layer
+            .as_ref()
+            .and(experiment.layer_allocation)
+            .map(|allocation| allocation as u8),
+        layer: experiment.layer,
+        bandit: experiment.bandit,
+    })
+}
+
+pub fn validate_ramp_request(
+    experiment: &Experiment,
+    traffic_percentage: u64,
+) -> superposition::Result<()> {
+    let old_traffic_percentage = experiment.traffic_percentage as u64;
+    let experiment_variants: Vec<Variant> =
+        serde_json::from_value(experiment.variants.clone()).map_err(|e| {
+            log::error!(
+                "failed to parse existing experiment variants while ramping {}",
+                e
+            );
+            unexpected_error!("Something went wrong, failed to ramp traffic percentage")
+        })?;
+    let variants_count = experiment_variants.len() as u64;
+    let allocation = experiment
+        .layer_allocation
+        .map_or(100, |allocation| allocation as u64);
+    let max = allocation / variants_count;
+
+    if matches!(experiment.status, ExperimentStatusType::CONCLUDED) {
+        return Err(bad_argument!(
+            "experiment already concluded, cannot ramp a concluded experiment"
+        ));
+    } else if traffic_percentage > max {
+        return Err(bad_argument!(
+            "The traffic_percentage cannot exceed {}. Provide a traffic percentage less than {}", max, max
+        ));
+    } else if traffic_percentage != 0 && traffic_percentage == old_traffic_percentage {
+        return Err(bad_argument!("The traffic_percentage is same as provided"));
+    } else if traffic_percentage != 0
+        && experiment.end_at.is_some_and(|end_at| end_at <= Utc::now())
+    {
+        return Err(bad_argument!(
+            "experiment has ended, its traffic can only be ramped down to 0"
+        ));
+    }
+
+    Ok(())
+}
+
+pub fn validate_conclude_request(
+    experiment: &Experiment,
+    chosen_variant: &str,
+) -> superposition::Result<()> {
+    if matches!(experiment.status, ExperimentStatusType::CONCLUDED) {
+        return Err(bad_argument!(
+            "experiment with id {} is already concluded",
+            experiment.id
+        ));
+    }
+
+    let experiment_variants: Vec<Variant> =
+        serde_json::from_value(experiment.variants.clone()).map_err(|err| {
+            log::error!(
+            "failed parse eixisting experiment variant while concluding with error: {}",
+            err
+        );
+            unexpected_error!("Something went wrong, failed to conclude experiment")
+        })?;
+    if !experiment_variants
+        .iter()
+        .any(|variant| variant.id == chosen_variant)
+    {
+        return Err(bad_argument!(
+            "winner variant not found. A wrong variant id may have been sent, check and try again"
+        ));
+    }
+
+    Ok(())
+}
+
+pub fn validate_experiment_metadata(
+    tags: &[String],
+    external_links: &[String],
+) -> superposition::Result<()> {
+    if tags.iter().any(|tag| tag.trim().is_empty()) {
+        return Err(bad_argument!("Tags cannot be empty strings"));
+    }
+    if let Some(link) = external_links
+        .iter()
+        .find(|link| !(link.starts_with("http://") || link.starts_with("https://")))
+    {
+        return Err(bad_argument!(
+            "External link {} is not valid. Provide absolute http(s) links",
+            link
+        ));
+    }
+    Ok(())
+}
+
+pub fn validate_experiment_schedule(
+    req: &ExperimentCreateRequest,
+) -> superposition::Result<()> {
+    match (req.start_at, req.start_traffic_percentage) {
+        (Some(start_at), Some(traffic_percentage)) => {
+            if start_at <= Utc::now() {
+                return Err(bad_argument!("start_at should be a time in the future"));
+            }
+            let allocation = req.layer_allocation.unwrap_or(100) as u64;
+            let max = allocation / req.variants.len().max(1) as u64;
+            if traffic_percentage as u64 > max {
+                return Err(bad_argument!(
+                    "start_traffic_percentage cannot exceed {}. Provide a traffic percentage less than {}",
+                    max,
+                    max
+                ));
+            }
+        }
+        (None, None) => {}
+        _ => {
+            return Err(bad_argument!(
+                "start_at and start_traffic_percentage should be provided together"
+            ))
+        }
+    }
+
+    if let Some(end_at) = req.end_at {
+        if end_at <= req.start_at.unwrap_or_else(Utc::now) {
+            return Err(bad_argument!(
+                "end_at should be in the future and after start_at"
+            ));
+        }
+    }
+
+    if let Some(fallback_variant) = &req.fallback_variant {
+        if req.end_at.is_none() {
+            return Err(bad_argument!(
+                "fallback_variant can only be set along with end_at"
+            ));
+        }
+        if !req
+            .variants
+            .iter()
+            .any(|variant| &variant.id == fallback_variant)
+        {
+            return Err(bad_argument!(
+                "fallback_variant {} is not one of the experiment's variants",
+                fallback_variant
+            ));
+        }
+    }
+    Ok(())
+}
+
 pub fn validate_context(context: &Value) -> superposition::Result<()> {
     let dimensions = extract_dimensions(context)?;
     if dimensions.contains_key("variantIds") {
@@ -179,6 +420,7 @@ pub fn validate_experiment(
     context: &Value,
     override_keys: &Vec<String>,
     experiment_id: Option<i64>,
+    layer: Option<&str>,
     flags: &ExperimentationFlags,
     conn: &mut PgConnection,
 ) -> superposition::Result<(bool, String)> {
@@ -195,9 +437,91 @@ pub fn validate_experiment(
         )
         .load(conn)?;
 
+    // experiments in the same layer never share units, so they cannot
+    // conflict however their contexts and keys overlap
+    let active_experiments: Vec<Experiment> = active_experiments
+        .into_iter()
+        .filter(|experiment| layer.is_none() || experiment.layer.as_deref() != layer)
+        .collect();
+
     is_valid_experiment(context, override_keys, flags, &active_experiments)
 }
 
+pub fn validate_experiment_layer(
+    layer: Option<&String>,
+    layer_allocation: Option<u8>,
+) -> superposition::Result<()> {
+    match (layer, layer_allocation) {
+        (Some(layer), Some(allocation)) => {
+            if layer.trim().is_empty() {
+                return Err(bad_argument!("layer cannot be an empty string"));
+            }
+            if allocation == 0 || allocation > 100 {
+                return Err(bad_argument!(
+                    "layer_allocation should be a percentage between 1 and 100"
+                ));
+            }
+            Ok(())
+        }
+        (None, None) => Ok(()),
+        _ => Err(bad_argument!(
+            "layer and layer_allocation should be provided together"
+        )),
+    }
+}
+
+/// Takes a lock on `layer` held until the end of the transaction `conn` is
+/// in, so that experiments created concurrently in the layer are given their
+/// slices one after another rather than the same free slice. The lock is
+/// shared by layers of the same name across tenants, which only makes them
+/// wait on each other.
+pub fn lock_layer(layer: &str, conn: &mut PgConnection) -> superposition::Result<()> {
+    diesel::sql_query("SELECT pg_advisory_xact_lock(hashtext($1))")
+        .bind::<diesel::sql_types::Text, _>(format!("experiment_layer:{}", layer))
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Finds the first slice of `allocation` percent of `layer` that no active
+/// experiment in the layer is using, returning where it starts.
+pub fn find_layer_offset(
+    layer: &str,
+    allocation: u8,
+    conn: &mut PgConnection,
+) -> superposition::Result<i32> {
+    use crate::db::schema::experiments::dsl as experiments_dsl;
+
+    let mut used_slices: Vec<(i32, i32)> = experiments_dsl::experiments
+        .filter(experiments_dsl::layer.eq(layer))
+        .filter(experiments_dsl::status.ne(ExperimentStatusType::CONCLUDED))
+        .select((
+            experiments_dsl::layer_offset,
+            experiments_dsl::layer_allocation,
+        ))
+        .load::<(Option<i32>, Option<i32>)>(conn)?
+        .into_iter()
+        .filter_map(|(offset, allocation)| Some((offset?, allocation?)))
+        .collect();
+    used_slices.sort();
+
+    let allocation = allocation as i32;
+    let mut offset = 0;
+    for (used_offset, used_allocation) in used_slices {
+        if used_offset - offset >= allocation {
+            break;
+        }
+        offset = offset.max(used_offset + used_allocation);
+    }
+    if offset + allocation > 100 {
+        return Err(bad_argument!(
+            "layer {} does not have {}% of its traffic free, conclude an experiment in the layer or request a smaller allocation",
+            layer,
+            allocation
+        ));
+    }
+    Ok(offset)
+}
+
 pub fn add_variant_dimension_to_ctx(
     context_json: &Value,
     variant: String,
@@ -238,3 +562,134 @@ pub fn add_variant_dimension_to_ctx(
 pub fn extract_override_keys(overrides: &Map<String, Value>) -> HashSet<String> {
     overrides.keys().map(String::from).collect()
 }
+
+/// Tenants that background jobs should run for, when tenancy is disabled
+/// requests are served from a single default tenant.
+pub fn get_job_tenants(state: &AppState) -> Vec<Tenant> {
+    if state.enable_tenant_and_scope {
+        state.tenants.iter().cloned().map(Tenant).collect()
+    } else {
+        vec![Tenant("mjos".into())]
+    }
+}
+
+// bookkeeping columns which change on every update, diffing them only adds
+// noise to the history
+const HISTORY_IGNORED_FIELDS: [&str; 2] = ["last_modified", "last_modified_by"];
+
+fn is_keyed_by_id(items: &[Value]) -> bool {
+    items
+        .iter()
+        .all(|item| item.get("id").is_some_and(Value::is_string))
+}
+
+fn by_id(items: &[Value]) -> BTreeMap<String, &Value> {
+    items
+        .iter()
+        .filter_map(|item| Some((item.get("id")?.as_str()?.to_string(), item)))
+        .collect()
+}
+
+/// Appends the differences between `old` and `new` to `diffs`. Objects are
+/// compared field by field and lists of objects with an `id`, like the
+/// variants, entry by entry, anything else is reported as a whole.
+pub fn diff_json(
+    path: &str,
+    old: Option<&Value>,
+    new: Option<&Value>,
+    diffs: &mut Vec<FieldDiff>,
+) {
+    // a missing field and a null one are the same column of the row
+    if old.unwrap_or(&Value::Null) == new.unwrap_or(&Value::Null) {
+        return;
+    }
+    let field_path = |key: &str| {
+        if path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", path, key)
+        }
+    };
+    match (old, new) {
+        (Some(Value::Object(old)), Some(Value::Object(new))) => {
+            let keys = old.keys().chain(new.keys()).collect::<BTreeSet<&String>>();
+            for key in keys {
+                diff_json(&field_path(key), old.get(key), new.get(key), diffs);
+            }
+        }
+        (Some(Value::Array(old)), Some(Value::Array(new)))
+            if is_keyed_by_id(old) && is_keyed_by_id(new) =>
+        {
+            let (old, new) = (by_id(old), by_id(new));
+            let ids = old.keys().chain(new.keys()).collect::<BTreeSet<&String>>();
+            for id in ids {
+                diff_json(
+                    &format!("{}[{}]", path, id),
+                    old.get(id).copied(),
+                    new.get(id).copied(),
+                    diffs,
+                );
+            }
+        }
+        _ => diffs.push(FieldDiff {
+            field: path.to_string(),
+            old_value: old.cloned(),
+            new_value: new.cloned(),
+        }),
+    }
+}
+
+fn history_event_type(action: &str, diffs: &[FieldDiff]) -> HistoryEventType {
+    let changed = |predicate: &dyn Fn(&str) -> bool| {
+        diffs.iter().any(|diff| predicate(&diff.field))
+    };
+    let concluded = diffs.iter().any(|diff| {
+        diff.field == "status"
+            && diff.new_value == Some(Value::String("CONCLUDED".to_string()))
+    });
+    match action {
+        "INSERT" => HistoryEventType::Created,
+        "DELETE" => HistoryEventType::Deleted,
+        _ if concluded => HistoryEventType::Concluded,
+        _ if changed(&|field| field == "traffic_percentage") => HistoryEventType::Ramped,
+        _ if changed(&|field| {
+            field.starts_with("override_keys")
+                || (field.starts_with("variants") && field.contains(".overrides"))
+        }) =>
+        {
+            HistoryEventType::OverridesUpdated
+        }
+        _ => HistoryEventType::Updated,
+    }
+}
+
+/// Turns an `event_log` row of the experiments table into a history entry
+/// with the field level changes it made.
+pub fn experiment_history_entry(log: EventLog) -> HistoryEntry {
+    let empty = Value::Object(Map::new());
+    let mut diffs = Vec::new();
+    diff_json(
+        "",
+        Some(log.original_data.as_ref().unwrap_or(&empty)),
+        Some(log.new_data.as_ref().unwrap_or(&empty)),
+        &mut diffs,
+    );
+    diffs.retain(|diff| !HISTORY_IGNORED_FIELDS.contains(&diff.field.as_str()));
+
+    // rows are written by the service's database user, the user who made the
+    // change is recorded on the experiment itself
+    let changed_by = log
+        .new_data
+        .as_ref()
+        .and_then(|data| data.get("last_modified_by"))
+        .and_then(Value::as_str)
+        .map(String::from)
+        .unwrap_or(log.user_name);
+
+    HistoryEntry {
+        event_type: history_event_type(&log.action, &diffs),
+        timestamp: log.timestamp,
+        changed_by,
+        diffs,
+    }
+}
@@ -1,4 +1,6 @@
 pub mod handlers;
 pub mod helpers;
+pub mod scheduler;
 pub mod types;
 pub use handlers::endpoints;
+pub use scheduler::run_experiment_scheduler_job;
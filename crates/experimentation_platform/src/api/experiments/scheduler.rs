@@ -0,0 +1,176 @@
+use std::time::Duration;
+
+use actix_web::web::Data;
+use chrono::Utc;
+use diesel::{
+    r2d2::{ConnectionManager, PooledConnection},
+    BoolExpressionMethods, ExpressionMethods, PgConnection, QueryDsl, RunQueryDsl,
+};
+use service_utils::{
+    result as superposition,
+    service::types::{AppExecutionNamespace, AppScope, AppState, Tenant},
+};
+use superposition_types::User;
+
+use super::{
+    handlers::{conclude, ramp_experiment},
+    helpers::get_job_tenants,
+    types::ConcludeExperimentRequest,
+};
+use crate::db::{
+    models::{Experiment, ExperimentStatusType},
+    schema::experiments::dsl as experiments,
+};
+
+/// Experiments that are still waiting to be started and whose `start_at`
+/// has passed, experiments which ended before they could be started are
+/// left to `ended_experiments`.
+fn experiments_due_to_start(
+    conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+) -> superposition::Result<Vec<Experiment>> {
+    let now = Utc::now();
+    let due = experiments::experiments
+        .filter(experiments::status.eq(ExperimentStatusType::CREATED))
+        .filter(experiments::start_at.le(now))
+        .filter(experiments::start_traffic_percentage.is_not_null())
+        .filter(
+            experiments::end_at
+                .is_null()
+                .or(experiments::end_at.gt(now)),
+        )
+        .load::<Experiment>(conn)?;
+    Ok(due)
+}
+
+/// Experiments past their `end_at` that are still serving traffic or have
+/// a fallback variant to be concluded with.
+fn ended_experiments(
+    conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+) -> superposition::Result<Vec<Experiment>> {
+    let ended = experiments::experiments
+        .filter(experiments::status.ne(ExperimentStatusType::CONCLUDED))
+        .filter(experiments::end_at.le(Utc::now()))
+        .filter(
+            experiments::fallback_variant
+                .is_not_null()
+                .or(experiments::traffic_percentage.gt(0)),
+        )
+        .load::<Experiment>(conn)?;
+    Ok(ended)
+}
+
+async fn end_experiment(
+    state: &Data<AppState>,
+    experiment: Experiment,
+    conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+    tenant: &Tenant,
+    user: &User,
+) -> superposition::Result<Experiment> {
+    match experiment.fallback_variant {
+        Some(chosen_variant) => {
+            let req = ConcludeExperimentRequest {
+                chosen_variant,
+                promote_to_default: false,
+            };
+            conclude(
+                state.clone(),
+                experiment.id,
+                req,
+                conn,
+                tenant.clone(),
+                user.clone(),
+            )
+            .await
+        }
+        None => ramp_experiment(state, experiment.id, 0, conn, tenant, user),
+    }
+}
+
+// The schedule is part of the experiment as it was created, and reviewed
+// along with it when experiments need approval, so scheduled actions are
+// carried out on behalf of the experiment's creator. `user` only provides the
+// credentials used to call CAC.
+fn schedule_owner(experiment: &Experiment, user: &User) -> User {
+    User {
+        email: experiment.created_by.clone(),
+        ..user.clone()
+    }
+}
+
+/// Starts the experiments of the tenant `conn` is scoped to whose `start_at`
+/// has passed and ends the ones past their `end_at`.
+pub async fn run_schedule(
+    state: &Data<AppState>,
+    conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+    tenant: &Tenant,
+    user: &User,
+) -> superposition::Result<()> {
+    for experiment in experiments_due_to_start(conn)? {
+        let traffic_percentage =
+            experiment.start_traffic_percentage.unwrap_or_default() as u64;
+        let owner = schedule_owner(&experiment, user);
+        match ramp_experiment(
+            state,
+            experiment.id,
+            traffic_percentage,
+            conn,
+            tenant,
+            &owner,
+        ) {
+            Ok(_) => log::info!(
+                "scheduler: started experiment {} with {}% traffic",
+                experiment.id,
+                traffic_percentage
+            ),
+            Err(err) => log::error!(
+                "scheduler: failed to start experiment {}: {}",
+                experiment.id,
+                err
+            ),
+        }
+    }
+
+    for experiment in ended_experiments(conn)? {
+        let experiment_id = experiment.id;
+        let owner = schedule_owner(&experiment, user);
+        match end_experiment(state, experiment, conn, tenant, &owner).await {
+            Ok(_) => log::info!("scheduler: ended experiment {}", experiment_id),
+            Err(err) => log::error!(
+                "scheduler: failed to end experiment {}: {}",
+                experiment_id,
+                err
+            ),
+        }
+    }
+    Ok(())
+}
+
+/// Periodically starts experiments whose `start_at` has passed and ends the
+/// ones past their `end_at`, for all tenants.
+pub async fn run_experiment_scheduler_job(state: Data<AppState>, interval: Duration) {
+    let tenants = get_job_tenants(&state);
+    let user = User::default();
+    let mut ticker = actix_web::rt::time::interval(interval);
+
+    loop {
+        ticker.tick().await;
+        for tenant in &tenants {
+            let namespace =
+                AppExecutionNamespace::new(&state, tenant, AppScope::EXPERIMENTATION);
+            let mut conn = match state.db_pool.get_conn(namespace.to_string()) {
+                Ok(conn) => conn,
+                Err(err) => {
+                    log::error!(
+                        "scheduler: unable to get db connection for {}: {}",
+                        tenant.as_str(),
+                        err
+                    );
+                    continue;
+                }
+            };
+            if let Err(err) = run_schedule(&state, &mut conn, tenant, &user).await {
+                log::error!("scheduler failed for tenant {}: {}", tenant.as_str(), err);
+            }
+        }
+    }
+}
@@ -1,9 +1,13 @@
 use chrono::{DateTime, NaiveDateTime, Utc};
-use serde::{Deserialize, Serialize};
+use diesel::AsChangeset;
+use serde::{Deserialize, Deserializer, Serialize};
 use serde_json::{Map, Value};
 use service_utils::helpers::deserialize_stringified_list;
 
-use crate::db::models::{self, ExperimentStatusType};
+use crate::db::{
+    models::{self, ExperimentStatusType},
+    schema::experiments,
+};
 
 #[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
 pub enum VariantType {
@@ -18,16 +22,54 @@ pub struct Variant {
     pub context_id: Option<String>,
     pub override_id: Option<String>,
     pub overrides: Map<String, Value>,
+    // share of the experiment's traffic given to the variant, set for bandit
+    // experiments once traffic is reallocated, variants split it equally
+    // otherwise
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weight: Option<u8>,
 }
 
 /********** Experiment Create Req Types ************/
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
 pub struct ExperimentCreateRequest {
     pub name: String,
 
     pub context: Value,
     pub variants: Vec<Variant>,
+
+    pub description: Option<String>,
+    pub hypothesis: Option<String>,
+    pub owner_team: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub external_links: Vec<String>,
+
+    // traffic is ramped to `start_traffic_percentage` at `start_at`, and at
+    // `end_at` the experiment is concluded with `fallback_variant` when set
+    // or ramped down to 0 otherwise
+    pub start_at: Option<DateTime<Utc>>,
+    pub end_at: Option<DateTime<Utc>>,
+    pub start_traffic_percentage: Option<u8>,
+    pub fallback_variant: Option<String>,
+
+    // experiments in the same layer are given disjoint slices of the traffic,
+    // `layer_allocation` is the percentage of the layer reserved for this one
+    pub layer: Option<String>,
+    pub layer_allocation: Option<u8>,
+
+    // shifts traffic towards the better performing variants, see bandit api
+    #[serde(default)]
+    pub bandit: bool,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ExperimentCloneRequest {
+    // defaults to the name of the experiment being cloned suffixed with (copy)
+    pub name: Option<String>,
+    // defaults to the context of the experiment being cloned
+    pub context: Option<Value>,
 }
 
 #[derive(Serialize)]
@@ -62,6 +104,23 @@ pub struct ExperimentResponse {
     pub context: Value,
     pub variants: Value,
     pub chosen_variant: Option<String>,
+
+    pub description: Option<String>,
+    pub hypothesis: Option<String>,
+    pub owner_team: Option<String>,
+    pub tags: Vec<String>,
+    pub external_links: Vec<String>,
+
+    pub start_at: Option<DateTime<Utc>>,
+    pub end_at: Option<DateTime<Utc>>,
+    pub start_traffic_percentage: Option<i32>,
+    pub fallback_variant: Option<String>,
+
+    pub layer: Option<String>,
+    pub layer_offset: Option<i32>,
+    pub layer_allocation: Option<i32>,
+
+    pub bandit: bool,
 }
 
 impl From<models::Experiment> for ExperimentResponse {
@@ -80,6 +139,23 @@ impl From<models::Experiment> for ExperimentResponse {
             context: experiment.context,
             variants: experiment.variants,
             chosen_variant: experiment.chosen_variant,
+
+            description: experiment.description,
+            hypothesis: experiment.hypothesis,
+            owner_team: experiment.owner_team,
+            tags: experiment.tags,
+            external_links: experiment.external_links,
+
+            start_at: experiment.start_at,
+            end_at: experiment.end_at,
+            start_traffic_percentage: experiment.start_traffic_percentage,
+            fallback_variant: experiment.fallback_variant,
+
+            layer: experiment.layer,
+            layer_offset: experiment.layer_offset,
+            layer_allocation: experiment.layer_allocation,
+
+            bandit: experiment.bandit,
         }
     }
 }
@@ -93,9 +169,13 @@ pub struct ExperimentsResponse {
 
 /********** Experiment Conclude Req Types **********/
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 pub struct ConcludeExperimentRequest {
     pub chosen_variant: String,
+    // writes the chosen variant's overrides into the default config instead
+    // of keeping them in a context equal to the experiment's context
+    #[serde(default)]
+    pub promote_to_default: bool,
 }
 
 /********** Context Bulk API Type *************/
@@ -135,17 +215,45 @@ pub struct StatusTypes(
     pub  Vec<ExperimentStatusType>,
 );
 
+#[derive(Deserialize, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ExperimentSortOn {
+    CreatedAt,
+    #[default]
+    LastModified,
+    Name,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SortBy {
+    Asc,
+    #[default]
+    Desc,
+}
+
 #[derive(Deserialize, Debug)]
 pub struct ListFilters {
     pub status: Option<StatusTypes>,
     pub from_date: Option<DateTime<Utc>>,
     pub to_date: Option<DateTime<Utc>>,
+    pub experiment_name: Option<String>,
+    pub experiment_ids: Option<StringArgs>,
+    pub created_by: Option<String>,
+    pub override_keys: Option<StringArgs>,
+    pub owner_team: Option<String>,
+    pub tags: Option<StringArgs>,
+    pub layer: Option<String>,
+    // `dimension=value` pairs, all of which have to be part of the context
+    pub dimensions: Option<StringArgs>,
+    pub sort_on: Option<ExperimentSortOn>,
+    pub sort_by: Option<SortBy>,
     pub page: Option<i64>,
     pub count: Option<i64>,
 }
 
 /********** Ramp API type **********/
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 pub struct RampRequest {
     pub traffic_percentage: u64,
 }
@@ -163,6 +271,29 @@ pub struct OverrideKeysUpdateRequest {
     pub variants: Vec<VariantUpdateRequest>,
 }
 
+// fields left out are kept as they are, while the text fields can be cleared
+// by setting them to null
+#[derive(Deserialize, Debug, AsChangeset)]
+#[diesel(table_name = experiments)]
+pub struct ExperimentMetadataUpdateRequest {
+    #[serde(default, deserialize_with = "deserialize_nullable")]
+    pub description: Option<Option<String>>,
+    #[serde(default, deserialize_with = "deserialize_nullable")]
+    pub hypothesis: Option<Option<String>>,
+    #[serde(default, deserialize_with = "deserialize_nullable")]
+    pub owner_team: Option<Option<String>>,
+    pub tags: Option<Vec<String>>,
+    pub external_links: Option<Vec<String>>,
+}
+
+fn deserialize_nullable<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
 #[derive(Deserialize, Serialize, Clone)]
 pub struct ContextMoveReq {
     pub context: serde_json::Map<String, Value>,
@@ -185,3 +316,39 @@ pub struct AuditQueryFilters {
     pub count: Option<i64>,
     pub page: Option<i64>,
 }
+
+/*********** Experiment History Type **************/
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum HistoryEventType {
+    Created,
+    Ramped,
+    OverridesUpdated,
+    Concluded,
+    Updated,
+    Deleted,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct FieldDiff {
+    // dotted path of the changed field, entries of lists keyed by `id`, like
+    // the variants, are addressed as `variants[<id>]`
+    pub field: String,
+    pub old_value: Option<Value>,
+    pub new_value: Option<Value>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct HistoryEntry {
+    pub event_type: HistoryEventType,
+    pub timestamp: NaiveDateTime,
+    pub changed_by: String,
+    pub diffs: Vec<FieldDiff>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ExperimentHistoryResponse {
+    pub experiment_id: String,
+    pub history: Vec<HistoryEntry>,
+}
@@ -0,0 +1,86 @@
+use actix_web::{
+    delete, get, put,
+    web::{self, Json},
+    HttpResponse, Scope,
+};
+use chrono::Utc;
+use diesel::{upsert::excluded, ExpressionMethods, QueryDsl, RunQueryDsl};
+use service_utils::{not_found, result as superposition, service::types::DbConnection};
+use superposition_types::{SuperpositionUser, User};
+
+use super::{helpers::validate_holdout_request, types::HoldoutPutRequest};
+use crate::db::{models::Holdout, schema::experiment_holdouts::dsl};
+
+pub fn endpoints(scope: Scope) -> Scope {
+    scope
+        .service(list_holdouts)
+        .service(put_holdout)
+        .service(delete_holdout)
+}
+
+#[get("")]
+async fn list_holdouts(
+    db_conn: DbConnection,
+) -> superposition::Result<Json<Vec<Holdout>>> {
+    let DbConnection(mut conn) = db_conn;
+    let holdouts = dsl::experiment_holdouts
+        .order(dsl::name.asc())
+        .load::<Holdout>(&mut conn)?;
+    Ok(Json(holdouts))
+}
+
+#[put("/{name}")]
+async fn put_holdout(
+    path: web::Path<String>,
+    req: Json<HoldoutPutRequest>,
+    db_conn: DbConnection,
+    user: User,
+) -> superposition::Result<Json<Holdout>> {
+    let DbConnection(mut conn) = db_conn;
+    let name = path.into_inner();
+    let req = req.into_inner();
+    validate_holdout_request(&name, &req)?;
+
+    let holdout = Holdout {
+        name,
+        layer: req.layer,
+        percentage: req.percentage as i32,
+        enabled: req.enabled.unwrap_or(true),
+        created_at: Utc::now(),
+        created_by: user.get_email(),
+        last_modified: Utc::now(),
+        last_modified_by: user.get_email(),
+    };
+
+    let upserted = diesel::insert_into(dsl::experiment_holdouts)
+        .values(&holdout)
+        .on_conflict(dsl::name)
+        .do_update()
+        .set((
+            dsl::layer.eq(excluded(dsl::layer)),
+            dsl::percentage.eq(excluded(dsl::percentage)),
+            dsl::enabled.eq(excluded(dsl::enabled)),
+            dsl::last_modified.eq(excluded(dsl::last_modified)),
+            dsl::last_modified_by.eq(excluded(dsl::last_modified_by)),
+        ))
+        .get_result::<Holdout>(&mut conn)?;
+    Ok(Json(upserted))
+}
+
+#[delete("/{name}")]
+async fn delete_holdout(
+    path: web::Path<String>,
+    db_conn: DbConnection,
+    user: User,
+) -> superposition::Result<HttpResponse> {
+    let DbConnection(mut conn) = db_conn;
+    let name = path.into_inner();
+    let deleted = diesel::delete(dsl::experiment_holdouts)
+        .filter(dsl::name.eq(&name))
+        .execute(&mut conn)?;
+    if deleted == 0 {
+        return Err(not_found!("holdout {} does not exist", name));
+    }
+    log::info!("holdout {} deleted by {}", name, user.get_email());
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -0,0 +1,25 @@
+use service_utils::{bad_argument, result as superposition};
+
+use super::types::HoldoutPutRequest;
+
+pub fn validate_holdout_request(
+    name: &str,
+    req: &HoldoutPutRequest,
+) -> superposition::Result<()> {
+    if name.trim().is_empty() {
+        return Err(bad_argument!("holdout name cannot be an empty string"));
+    }
+    if req.percentage > 100 {
+        return Err(bad_argument!(
+            "holdout percentage should be between 0 and 100"
+        ));
+    }
+    if req
+        .layer
+        .as_ref()
+        .is_some_and(|layer| layer.trim().is_empty())
+    {
+        return Err(bad_argument!("layer cannot be an empty string"));
+    }
+    Ok(())
+}
@@ -0,0 +1,11 @@
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug)]
+pub struct HoldoutPutRequest {
+    // percentage of units held out of the experiments the holdout covers
+    pub percentage: u8,
+    // when absent the holdout covers every experiment of the tenant,
+    // otherwise only the experiments in this layer
+    pub layer: Option<String>,
+    pub enabled: Option<bool>,
+}
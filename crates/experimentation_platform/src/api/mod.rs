@@ -1 +1,7 @@
+pub mod bandit;
+pub mod change_requests;
+pub mod drift;
 pub mod experiments;
+pub mod holdouts;
+pub mod templates;
+pub mod webhooks;
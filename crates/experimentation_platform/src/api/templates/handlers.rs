@@ -0,0 +1,119 @@
+use actix_web::{
+    delete, get, put,
+    web::{self, Json},
+    HttpResponse, Scope,
+};
+use chrono::Utc;
+use diesel::{upsert::excluded, ExpressionMethods, QueryDsl, RunQueryDsl};
+use service_utils::{
+    not_found, result as superposition, service::types::DbConnection, unexpected_error,
+};
+use superposition_types::{SuperpositionUser, User};
+
+use super::{helpers::validate_template_request, types::TemplatePutRequest};
+use crate::{
+    api::experiments::types::Variant,
+    db::{models::Template, schema::experiment_templates::dsl},
+};
+
+pub fn endpoints(scope: Scope) -> Scope {
+    scope
+        .service(list_templates)
+        .service(get_template)
+        .service(put_template)
+        .service(delete_template)
+}
+
+#[get("")]
+async fn list_templates(
+    db_conn: DbConnection,
+) -> superposition::Result<Json<Vec<Template>>> {
+    let DbConnection(mut conn) = db_conn;
+    let templates = dsl::experiment_templates
+        .order(dsl::name.asc())
+        .load::<Template>(&mut conn)?;
+    Ok(Json(templates))
+}
+
+#[get("/{name}")]
+async fn get_template(
+    path: web::Path<String>,
+    db_conn: DbConnection,
+) -> superposition::Result<Json<Template>> {
+    let DbConnection(mut conn) = db_conn;
+    let template = dsl::experiment_templates
+        .find(path.into_inner())
+        .get_result::<Template>(&mut conn)?;
+    Ok(Json(template))
+}
+
+#[put("/{name}")]
+async fn put_template(
+    path: web::Path<String>,
+    req: Json<TemplatePutRequest>,
+    db_conn: DbConnection,
+    user: User,
+) -> superposition::Result<Json<Template>> {
+    let DbConnection(mut conn) = db_conn;
+    let name = path.into_inner();
+    let req = req.into_inner();
+    validate_template_request(&name, &req)?;
+
+    // templates only describe the shape of the variants, anything tied to a
+    // running experiment is dropped
+    let variants = req
+        .variants
+        .into_iter()
+        .map(|variant| Variant {
+            context_id: None,
+            override_id: None,
+            weight: None,
+            ..variant
+        })
+        .collect::<Vec<Variant>>();
+    let variants = serde_json::to_value(variants).map_err(|err| {
+        log::error!("failed to serialize template variants: {}", err);
+        unexpected_error!("Something went wrong, failed to save template")
+    })?;
+
+    let template = Template {
+        name,
+        description: req.description,
+        variants,
+        created_at: Utc::now(),
+        created_by: user.get_email(),
+        last_modified: Utc::now(),
+        last_modified_by: user.get_email(),
+    };
+
+    let upserted = diesel::insert_into(dsl::experiment_templates)
+        .values(&template)
+        .on_conflict(dsl::name)
+        .do_update()
+        .set((
+            dsl::description.eq(excluded(dsl::description)),
+            dsl::variants.eq(excluded(dsl::variants)),
+            dsl::last_modified.eq(excluded(dsl::last_modified)),
+            dsl::last_modified_by.eq(excluded(dsl::last_modified_by)),
+        ))
+        .get_result::<Template>(&mut conn)?;
+    Ok(Json(upserted))
+}
+
+#[delete("/{name}")]
+async fn delete_template(
+    path: web::Path<String>,
+    db_conn: DbConnection,
+    user: User,
+) -> superposition::Result<HttpResponse> {
+    let DbConnection(mut conn) = db_conn;
+    let name = path.into_inner();
+    let deleted = diesel::delete(dsl::experiment_templates)
+        .filter(dsl::name.eq(&name))
+        .execute(&mut conn)?;
+    if deleted == 0 {
+        return Err(not_found!("template {} does not exist", name));
+    }
+    log::info!("template {} deleted by {}", name, user.get_email());
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -0,0 +1,15 @@
+use service_utils::{bad_argument, result as superposition};
+
+use super::types::TemplatePutRequest;
+use crate::api::experiments::helpers::validate_variants;
+
+pub fn validate_template_request(
+    name: &str,
+    req: &TemplatePutRequest,
+) -> superposition::Result<()> {
+    if name.trim().is_empty() {
+        return Err(bad_argument!("template name cannot be an empty string"));
+    }
+    validate_variants(&req.variants)?;
+    Ok(())
+}
@@ -0,0 +1,4 @@
+pub mod handlers;
+pub mod helpers;
+pub mod types;
+pub use handlers::endpoints;
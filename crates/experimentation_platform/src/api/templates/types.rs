@@ -0,0 +1,11 @@
+use serde::Deserialize;
+
+use crate::api::experiments::types::Variant;
+
+#[derive(Deserialize)]
+pub struct TemplatePutRequest {
+    pub description: Option<String>,
+    // variants an experiment created from the template starts with, the
+    // experiment form lets these be edited before the experiment is created
+    pub variants: Vec<Variant>,
+}
@@ -0,0 +1,212 @@
+use actix_web::{
+    delete, get, patch, post,
+    web::{self, Data, Json, Query},
+    HttpResponse, Scope,
+};
+use chrono::Utc;
+use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
+use serde_json::json;
+use service_utils::{
+    bad_argument, result as superposition,
+    service::types::{AppState, DbConnection, Tenant},
+};
+use superposition_types::{SuperpositionUser, User};
+
+use super::{
+    helpers::{create_delivery, deliver_once, validate_webhook_request},
+    types::{
+        DeliveryFilters, WebhookCreateRequest, WebhookDeliveriesResponse,
+        WebhookDeliveryResponse, WebhookResponse, WebhookUpdateRequest,
+    },
+};
+use crate::db::{
+    models::{Webhook, WebhookDelivery, WebhookEvent},
+    schema::{
+        experiment_webhook_deliveries::dsl as deliveries,
+        experiment_webhooks::dsl as webhooks,
+    },
+};
+
+pub fn endpoints(scope: Scope) -> Scope {
+    scope
+        .service(create)
+        .service(list_webhooks)
+        .service(get_webhook)
+        .service(update)
+        .service(delete_webhook)
+        .service(list_deliveries)
+        .service(test_fire)
+}
+
+#[post("")]
+async fn create(
+    state: Data<AppState>,
+    req: Json<WebhookCreateRequest>,
+    db_conn: DbConnection,
+    user: User,
+) -> superposition::Result<Json<WebhookResponse>> {
+    let DbConnection(mut conn) = db_conn;
+    let req = req.into_inner();
+    validate_webhook_request(Some(&req.url), Some(&req.events))?;
+
+    let id = {
+        let mut snowflake_generator = state.snowflake_generator.lock().unwrap();
+        snowflake_generator.real_time_generate()
+    };
+    let secret = req
+        .secret
+        .unwrap_or_else(|| uuid::Uuid::new_v4().simple().to_string());
+    let webhook = Webhook {
+        id,
+        url: req.url,
+        secret: secret.clone(),
+        events: req.events,
+        enabled: req.enabled.unwrap_or(true),
+        created_at: Utc::now(),
+        created_by: user.get_email(),
+        last_modified: Utc::now(),
+        last_modified_by: user.get_email(),
+    };
+
+    let inserted = diesel::insert_into(webhooks::experiment_webhooks)
+        .values(&webhook)
+        .get_result::<Webhook>(&mut conn)?;
+
+    let mut response = WebhookResponse::from(inserted);
+    response.secret = Some(secret);
+    Ok(Json(response))
+}
+
+#[get("")]
+async fn list_webhooks(
+    db_conn: DbConnection,
+) -> superposition::Result<Json<Vec<WebhookResponse>>> {
+    let DbConnection(mut conn) = db_conn;
+    let result = webhooks::experiment_webhooks
+        .order(webhooks::created_at.desc())
+        .load::<Webhook>(&mut conn)?;
+    Ok(Json(
+        result.into_iter().map(WebhookResponse::from).collect(),
+    ))
+}
+
+#[get("/{id}")]
+async fn get_webhook(
+    params: web::Path<i64>,
+    db_conn: DbConnection,
+) -> superposition::Result<Json<WebhookResponse>> {
+    let DbConnection(mut conn) = db_conn;
+    let webhook = webhooks::experiment_webhooks
+        .find(params.into_inner())
+        .get_result::<Webhook>(&mut conn)?;
+    Ok(Json(WebhookResponse::from(webhook)))
+}
+
+#[patch("/{id}")]
+async fn update(
+    params: web::Path<i64>,
+    req: Json<WebhookUpdateRequest>,
+    db_conn: DbConnection,
+    user: User,
+) -> superposition::Result<Json<WebhookResponse>> {
+    let DbConnection(mut conn) = db_conn;
+    let webhook_id = params.into_inner();
+    let req = req.into_inner();
+    validate_webhook_request(req.url.as_ref(), req.events.as_ref())?;
+
+    let webhook = webhooks::experiment_webhooks
+        .find(webhook_id)
+        .get_result::<Webhook>(&mut conn)?;
+
+    let updated = diesel::update(webhooks::experiment_webhooks)
+        .filter(webhooks::id.eq(webhook_id))
+        .set((
+            webhooks::url.eq(req.url.unwrap_or(webhook.url)),
+            webhooks::secret.eq(req.secret.unwrap_or(webhook.secret)),
+            webhooks::events.eq(req.events.unwrap_or(webhook.events)),
+            webhooks::enabled.eq(req.enabled.unwrap_or(webhook.enabled)),
+            webhooks::last_modified.eq(Utc::now()),
+            webhooks::last_modified_by.eq(user.get_email()),
+        ))
+        .get_result::<Webhook>(&mut conn)?;
+    Ok(Json(WebhookResponse::from(updated)))
+}
+
+#[delete("/{id}")]
+async fn delete_webhook(
+    params: web::Path<i64>,
+    db_conn: DbConnection,
+) -> superposition::Result<HttpResponse> {
+    let DbConnection(mut conn) = db_conn;
+    let webhook_id = params.into_inner();
+    let deleted = diesel::delete(webhooks::experiment_webhooks)
+        .filter(webhooks::id.eq(webhook_id))
+        .execute(&mut conn)?;
+    if deleted == 0 {
+        return Err(bad_argument!("webhook {} does not exist", webhook_id));
+    }
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[get("/{id}/deliveries")]
+async fn list_deliveries(
+    params: web::Path<i64>,
+    filters: Query<DeliveryFilters>,
+    db_conn: DbConnection,
+) -> superposition::Result<Json<WebhookDeliveriesResponse>> {
+    let DbConnection(mut conn) = db_conn;
+    let webhook_id = params.into_inner();
+
+    let limit = filters.count.unwrap_or(10);
+    let offset = (filters.page.unwrap_or(1) - 1) * limit;
+
+    let total_items: i64 = deliveries::experiment_webhook_deliveries
+        .filter(deliveries::webhook_id.eq(webhook_id))
+        .count()
+        .get_result(&mut conn)?;
+    let result = deliveries::experiment_webhook_deliveries
+        .filter(deliveries::webhook_id.eq(webhook_id))
+        .order(deliveries::created_at.desc())
+        .limit(limit)
+        .offset(offset)
+        .load::<WebhookDelivery>(&mut conn)?;
+    let total_pages = (total_items as f64 / limit as f64).ceil() as i64;
+
+    Ok(Json(WebhookDeliveriesResponse {
+        total_items,
+        total_pages,
+        data: result
+            .into_iter()
+            .map(WebhookDeliveryResponse::from)
+            .collect(),
+    }))
+}
+
+#[post("/{id}/test")]
+async fn test_fire(
+    state: Data<AppState>,
+    params: web::Path<i64>,
+    db_conn: DbConnection,
+    tenant: Tenant,
+    user: User,
+) -> superposition::Result<Json<WebhookDeliveryResponse>> {
+    let DbConnection(mut conn) = db_conn;
+    let webhook = webhooks::experiment_webhooks
+        .find(params.into_inner())
+        .get_result::<Webhook>(&mut conn)?;
+
+    let data = json!({
+        "webhook_id": webhook.id.to_string(),
+        "triggered_by": user.get_email(),
+    });
+    let delivery = create_delivery(
+        &state,
+        &webhook,
+        WebhookEvent::TEST,
+        data,
+        &tenant,
+        &mut conn,
+    )?;
+    let delivery = deliver_once(&webhook, &delivery, &mut conn).await?;
+    Ok(Json(WebhookDeliveryResponse::from(delivery)))
+}
@@ -0,0 +1,431 @@
+use std::time::Duration;
+
+use actix_web::web::Data;
+use chrono::Utc;
+use diesel::{
+    r2d2::{ConnectionManager, PooledConnection},
+    ExpressionMethods, OptionalExtension, PgArrayExpressionMethods, PgConnection,
+    QueryDsl, RunQueryDsl, SelectableHelper,
+};
+use hmac::{Hmac, Mac, NewMac};
+use serde_json::Value;
+use service_utils::{
+    bad_argument, result as superposition,
+    service::types::{AppExecutionNamespace, AppScope, AppState, Tenant},
+    unexpected_error,
+};
+use sha2::Sha256;
+
+use super::types::WebhookPayload;
+use crate::{
+    api::experiments::{helpers::get_job_tenants, types::ExperimentResponse},
+    db::{
+        models::{
+            Experiment, Webhook, WebhookDelivery, WebhookDeliveryStatus, WebhookEvent,
+        },
+        schema::{
+            experiment_webhook_deliveries::dsl as deliveries,
+            experiment_webhooks::dsl as webhooks,
+        },
+    },
+};
+
+type HmacSha256 = Hmac<Sha256>;
+
+pub const SIGNATURE_HEADER: &str = "x-webhook-signature";
+pub const EVENT_HEADER: &str = "x-webhook-event";
+pub const DELIVERY_HEADER: &str = "x-webhook-delivery";
+
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+// response bodies of failed deliveries are stored in the delivery log,
+// this keeps a misbehaving endpoint from bloating the table
+const MAX_ERROR_LENGTH: usize = 1024;
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(60 * 60);
+// a claimed delivery is not picked up again for this long, long enough for
+// the attempt to time out and be recorded
+const DELIVERY_CLAIM_LEASE: Duration = Duration::from_secs(60);
+
+pub fn validate_webhook_request(
+    url: Option<&String>,
+    events: Option<&Vec<WebhookEvent>>,
+) -> superposition::Result<()> {
+    if let Some(url) = url {
+        let is_http = reqwest::Url::parse(url)
+            .map(|url| url.scheme() == "http" || url.scheme() == "https")
+            .unwrap_or(false);
+        if !is_http {
+            return Err(bad_argument!(
+                "Webhook url {} is not valid. Provide an absolute http(s) url",
+                url
+            ));
+        }
+    }
+    if let Some(events) = events {
+        if events.is_empty() {
+            return Err(bad_argument!(
+                "Webhooks should subscribe to atleast one event"
+            ));
+        }
+        if events.contains(&WebhookEvent::TEST) {
+            return Err(bad_argument!(
+                "TEST events are only sent through the test endpoint and cannot be subscribed to"
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Hex encoded HMAC-SHA256 of `body` keyed with the webhook's secret, sent as
+/// `sha256=<signature>` so receivers can verify the payload came from us.
+pub fn sign_payload(secret: &str, body: &[u8]) -> superposition::Result<String> {
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).map_err(|err| {
+        log::error!("failed to initialise webhook signer: {}", err);
+        unexpected_error!("Something went wrong, failed to sign webhook payload")
+    })?;
+    mac.update(body);
+    Ok(hex::encode(mac.finalize().into_bytes()))
+}
+
+pub fn create_delivery(
+    state: &Data<AppState>,
+    webhook: &Webhook,
+    event: WebhookEvent,
+    data: Value,
+    tenant: &Tenant,
+    conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+) -> superposition::Result<WebhookDelivery> {
+    let id = {
+        let mut snowflake_generator = state.snowflake_generator.lock().unwrap();
+        snowflake_generator.real_time_generate()
+    };
+    let payload = WebhookPayload {
+        delivery_id: id.to_string(),
+        event,
+        tenant: tenant.as_str().to_string(),
+        timestamp: Utc::now(),
+        data,
+    };
+    let payload = serde_json::to_value(payload).map_err(|err| {
+        log::error!("failed to serialize webhook payload: {}", err);
+        unexpected_error!("Something went wrong, failed to create webhook delivery")
+    })?;
+
+    let delivery = WebhookDelivery {
+        id,
+        webhook_id: webhook.id,
+        event,
+        payload,
+        status: WebhookDeliveryStatus::PENDING,
+        attempts: 0,
+        response_code: None,
+        error: None,
+        created_at: Utc::now(),
+        last_attempted_at: None,
+        // the delivery is created claimed by its caller, the retry job only
+        // picks it up if the first attempt is never recorded
+        next_attempt_at: Some(Utc::now() + to_chrono_duration(DELIVERY_CLAIM_LEASE)),
+    };
+
+    let inserted = diesel::insert_into(deliveries::experiment_webhook_deliveries)
+        .values(&delivery)
+        .get_result::<WebhookDelivery>(conn)?;
+    Ok(inserted)
+}
+
+/// Outcome of a single attempt, the response code is present whenever the
+/// endpoint responded at all.
+struct AttemptResult {
+    success: bool,
+    response_code: Option<i32>,
+    error: Option<String>,
+}
+
+async fn attempt_delivery(
+    webhook: &Webhook,
+    delivery: &WebhookDelivery,
+) -> superposition::Result<AttemptResult> {
+    let body = serde_json::to_vec(&delivery.payload).map_err(|err| {
+        log::error!("failed to serialize webhook payload: {}", err);
+        unexpected_error!("Something went wrong, failed to deliver webhook")
+    })?;
+    let signature = sign_payload(&webhook.secret, &body)?;
+    let event = serde_json::to_value(delivery.event)
+        .ok()
+        .and_then(|event| event.as_str().map(String::from))
+        .unwrap_or_default();
+
+    let http_client = reqwest::Client::builder()
+        .timeout(DELIVERY_TIMEOUT)
+        .build()
+        .map_err(|err| {
+            log::error!("failed to build webhook http client: {}", err);
+            unexpected_error!("Something went wrong, failed to deliver webhook")
+        })?;
+    let response = http_client
+        .post(&webhook.url)
+        .header("Content-Type", "application/json")
+        .header(SIGNATURE_HEADER, format!("sha256={}", signature))
+        .header(EVENT_HEADER, event)
+        .header(DELIVERY_HEADER, delivery.id.to_string())
+        .body(body)
+        .send()
+        .await;
+
+    let result = match response {
+        Ok(response) if response.status().is_success() => AttemptResult {
+            success: true,
+            response_code: Some(response.status().as_u16() as i32),
+            error: None,
+        },
+        Ok(response) => {
+            let response_code = Some(response.status().as_u16() as i32);
+            let body = response.text().await.unwrap_or_default();
+            AttemptResult {
+                success: false,
+                response_code,
+                error: Some(body.chars().take(MAX_ERROR_LENGTH).collect()),
+            }
+        }
+        Err(err) => AttemptResult {
+            success: false,
+            response_code: None,
+            error: Some(err.to_string()),
+        },
+    };
+    Ok(result)
+}
+
+// Records an attempt of `delivery`, which is retried after `retry_after` if it
+// failed, or marked as failed when no retry is left.
+fn record_attempt(
+    delivery: &WebhookDelivery,
+    result: AttemptResult,
+    retry_after: Option<Duration>,
+    conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+) -> superposition::Result<WebhookDelivery> {
+    let (status, next_attempt_at) = match (result.success, retry_after) {
+        (true, _) => (WebhookDeliveryStatus::SUCCEEDED, None),
+        (false, None) => (WebhookDeliveryStatus::FAILED, None),
+        (false, Some(retry_after)) => (
+            WebhookDeliveryStatus::PENDING,
+            Some(Utc::now() + to_chrono_duration(retry_after)),
+        ),
+    };
+    let updated = diesel::update(deliveries::experiment_webhook_deliveries)
+        .filter(deliveries::id.eq(delivery.id))
+        .set((
+            deliveries::status.eq(status),
+            deliveries::attempts.eq(deliveries::attempts + 1),
+            deliveries::response_code.eq(result.response_code),
+            deliveries::error.eq(result.error),
+            deliveries::last_attempted_at.eq(Some(Utc::now())),
+            deliveries::next_attempt_at.eq(next_attempt_at),
+        ))
+        .get_result::<WebhookDelivery>(conn)?;
+    Ok(updated)
+}
+
+fn to_chrono_duration(duration: Duration) -> chrono::Duration {
+    chrono::Duration::from_std(duration).unwrap_or_else(|_| chrono::Duration::zero())
+}
+
+/// Makes a single delivery attempt and records it, used by the test endpoint
+/// so that callers see the outcome in the response.
+pub async fn deliver_once(
+    webhook: &Webhook,
+    delivery: &WebhookDelivery,
+    conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+) -> superposition::Result<WebhookDelivery> {
+    let result = attempt_delivery(webhook, delivery).await?;
+    record_attempt(delivery, result, None, conn)
+}
+
+/// Time to wait before retrying after the failed `attempt`, doubling from
+/// `base_secs` with every attempt up to `MAX_RETRY_BACKOFF`.
+pub fn retry_backoff(base_secs: u64, attempt: u32) -> Duration {
+    let factor = 2_u64.saturating_pow(attempt.saturating_sub(1));
+    Duration::from_secs(base_secs.saturating_mul(factor)).min(MAX_RETRY_BACKOFF)
+}
+
+// Takes the delivery if it is still pending and due, pushing its next attempt
+// past the lease so that neither the retry job nor another instance attempts
+// it concurrently. Returns `None` when it was taken already.
+fn claim_delivery(
+    delivery_id: i64,
+    conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+) -> superposition::Result<Option<WebhookDelivery>> {
+    let now = Utc::now();
+    let claimed = diesel::update(deliveries::experiment_webhook_deliveries)
+        .filter(deliveries::id.eq(delivery_id))
+        .filter(deliveries::status.eq(WebhookDeliveryStatus::PENDING))
+        .filter(deliveries::next_attempt_at.le(now))
+        .set(
+            deliveries::next_attempt_at
+                .eq(Some(now + to_chrono_duration(DELIVERY_CLAIM_LEASE))),
+        )
+        .get_result::<WebhookDelivery>(conn)
+        .optional()?;
+    Ok(claimed)
+}
+
+// Makes the next attempt of a claimed delivery, scheduling a retry with
+// exponential backoff on failure until `max_attempts` is exhausted. The
+// schedule is kept on the delivery, so retries survive restarts.
+async fn attempt_claimed_delivery(
+    state: &Data<AppState>,
+    webhook: &Webhook,
+    delivery: &WebhookDelivery,
+    conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+) -> superposition::Result<()> {
+    let result = attempt_delivery(webhook, delivery).await?;
+    let attempt = u32::try_from(delivery.attempts).unwrap_or(0) + 1;
+    let retry_after = (attempt < state.webhook.max_attempts)
+        .then(|| retry_backoff(state.webhook.retry_backoff_secs, attempt));
+    let recorded = record_attempt(delivery, result, retry_after, conn)?;
+    if recorded.status == WebhookDeliveryStatus::FAILED {
+        log::warn!(
+            "webhook delivery {} to {} failed after {} attempts",
+            delivery.id,
+            webhook.url,
+            recorded.attempts
+        );
+    }
+    Ok(())
+}
+
+// Makes the first attempt of a new delivery in the background, retries are
+// left to the retry job. The connection is taken from the pool here so that
+// none is held by the request that triggered the delivery.
+async fn deliver_in_background(
+    state: Data<AppState>,
+    tenant: Tenant,
+    webhook: Webhook,
+    delivery: WebhookDelivery,
+) {
+    let namespace =
+        AppExecutionNamespace::new(&state, &tenant, AppScope::EXPERIMENTATION);
+    let delivered = match state.db_pool.get_conn(namespace.to_string()) {
+        Ok(mut conn) => attempt_claimed_delivery(&state, &webhook, &delivery, &mut conn)
+            .await
+            .map_err(|err| err.to_string()),
+        Err(err) => Err(err.to_string()),
+    };
+    if let Err(err) = delivered {
+        log::error!("webhook delivery {} failed: {}", delivery.id, err);
+    }
+}
+
+/// Attempts every pending delivery whose next attempt is due, for the tenant
+/// `conn` is scoped to.
+pub async fn retry_due_deliveries(
+    state: &Data<AppState>,
+    conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+) -> superposition::Result<()> {
+    let due: Vec<(i64, Webhook)> = deliveries::experiment_webhook_deliveries
+        .inner_join(webhooks::experiment_webhooks)
+        .filter(deliveries::status.eq(WebhookDeliveryStatus::PENDING))
+        .filter(deliveries::next_attempt_at.le(Utc::now()))
+        .filter(webhooks::enabled.eq(true))
+        .order(deliveries::next_attempt_at.asc())
+        .select((deliveries::id, Webhook::as_select()))
+        .load(conn)?;
+    for (delivery_id, webhook) in due {
+        let Some(delivery) = claim_delivery(delivery_id, conn)? else {
+            continue;
+        };
+        if let Err(err) = attempt_claimed_delivery(state, &webhook, &delivery, conn).await
+        {
+            log::error!("webhook delivery {} failed: {}", delivery_id, err);
+        }
+    }
+    Ok(())
+}
+
+/// Periodically retries the failed webhook deliveries of all tenants which are
+/// due for another attempt.
+pub async fn run_webhook_retry_job(state: Data<AppState>, interval: Duration) {
+    let tenants = get_job_tenants(&state);
+    let mut ticker = actix_web::rt::time::interval(interval);
+
+    loop {
+        ticker.tick().await;
+        for tenant in &tenants {
+            let namespace =
+                AppExecutionNamespace::new(&state, tenant, AppScope::EXPERIMENTATION);
+            let mut conn = match state.db_pool.get_conn(namespace.to_string()) {
+                Ok(conn) => conn,
+                Err(err) => {
+                    log::error!(
+                        "webhook retries: unable to get db connection for {}: {}",
+                        tenant.as_str(),
+                        err
+                    );
+                    continue;
+                }
+            };
+            if let Err(err) = retry_due_deliveries(&state, &mut conn).await {
+                log::error!(
+                    "webhook retries failed for tenant {}: {}",
+                    tenant.as_str(),
+                    err
+                );
+            }
+        }
+    }
+}
+
+/// Queues a delivery of `event` for every enabled webhook subscribed to it,
+/// with the experiment as its data.
+pub fn trigger_webhooks(
+    state: &Data<AppState>,
+    tenant: &Tenant,
+    event: WebhookEvent,
+    experiment: &Experiment,
+    conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+) {
+    match serde_json::to_value(ExperimentResponse::from(experiment.clone())) {
+        Ok(data) => notify_webhooks(state, tenant, event, data, conn),
+        Err(err) => log::error!("failed to serialize experiment for webhooks: {}", err),
+    }
+}
+
+/// Queues a delivery of `event` carrying `data` for every enabled webhook
+/// subscribed to it. Deliveries happen in the background, failures to notify
+/// are logged and never fail the operation that triggered them.
+pub fn notify_webhooks(
+    state: &Data<AppState>,
+    tenant: &Tenant,
+    event: WebhookEvent,
+    data: Value,
+    conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+) {
+    let subscribed = webhooks::experiment_webhooks
+        .filter(webhooks::enabled.eq(true))
+        .filter(webhooks::events.contains(vec![event]))
+        .load::<Webhook>(conn);
+    let subscribed = match subscribed {
+        Ok(subscribed) => subscribed,
+        Err(err) => {
+            log::error!("failed to fetch webhooks for {:?} event: {}", event, err);
+            return;
+        }
+    };
+    for webhook in subscribed {
+        match create_delivery(state, &webhook, event, data.clone(), tenant, conn) {
+            Ok(delivery) => {
+                actix_web::rt::spawn(deliver_in_background(
+                    state.clone(),
+                    tenant.clone(),
+                    webhook,
+                    delivery,
+                ));
+            }
+            Err(err) => log::error!(
+                "failed to queue {:?} event for webhook {}: {}",
+                event,
+                webhook.id,
+                err
+            ),
+        }
+    }
+}
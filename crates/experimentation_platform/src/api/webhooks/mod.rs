@@ -0,0 +1,5 @@
+pub mod handlers;
+pub mod helpers;
+pub mod types;
+pub use handlers::endpoints;
+pub use helpers::{notify_webhooks, run_webhook_retry_job, trigger_webhooks};
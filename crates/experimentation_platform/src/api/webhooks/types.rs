@@ -0,0 +1,114 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::db::models::{self, WebhookDeliveryStatus, WebhookEvent};
+
+/********** Webhook Registration Types **************/
+
+#[derive(Deserialize, Debug)]
+pub struct WebhookCreateRequest {
+    pub url: String,
+    pub secret: Option<String>,
+    pub events: Vec<WebhookEvent>,
+    pub enabled: Option<bool>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct WebhookUpdateRequest {
+    pub url: Option<String>,
+    pub secret: Option<String>,
+    pub events: Option<Vec<WebhookEvent>>,
+    pub enabled: Option<bool>,
+}
+
+// the secret is only sent back in the response to the create call, it is
+// never exposed by the read APIs
+#[derive(Serialize)]
+pub struct WebhookResponse {
+    pub id: String,
+    pub url: String,
+    pub events: Vec<WebhookEvent>,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+    pub created_by: String,
+    pub last_modified: DateTime<Utc>,
+    pub last_modified_by: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+}
+
+impl From<models::Webhook> for WebhookResponse {
+    fn from(webhook: models::Webhook) -> Self {
+        WebhookResponse {
+            id: webhook.id.to_string(),
+            url: webhook.url,
+            events: webhook.events,
+            enabled: webhook.enabled,
+            created_at: webhook.created_at,
+            created_by: webhook.created_by,
+            last_modified: webhook.last_modified,
+            last_modified_by: webhook.last_modified_by,
+            secret: None,
+        }
+    }
+}
+
+/********** Delivery Types **************/
+
+#[derive(Serialize)]
+pub struct WebhookDeliveryResponse {
+    pub id: String,
+    pub webhook_id: String,
+    pub event: WebhookEvent,
+    pub payload: Value,
+    pub status: WebhookDeliveryStatus,
+    pub attempts: i32,
+    pub response_code: Option<i32>,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_attempted_at: Option<DateTime<Utc>>,
+    pub next_attempt_at: Option<DateTime<Utc>>,
+}
+
+impl From<models::WebhookDelivery> for WebhookDeliveryResponse {
+    fn from(delivery: models::WebhookDelivery) -> Self {
+        WebhookDeliveryResponse {
+            id: delivery.id.to_string(),
+            webhook_id: delivery.webhook_id.to_string(),
+            event: delivery.event,
+            payload: delivery.payload,
+            status: delivery.status,
+            attempts: delivery.attempts,
+            response_code: delivery.response_code,
+            error: delivery.error,
+            created_at: delivery.created_at,
+            last_attempted_at: delivery.last_attempted_at,
+            next_attempt_at: delivery.next_attempt_at,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct WebhookDeliveriesResponse {
+    pub total_items: i64,
+    pub total_pages: i64,
+    pub data: Vec<WebhookDeliveryResponse>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct DeliveryFilters {
+    pub page: Option<i64>,
+    pub count: Option<i64>,
+}
+
+/********** Payload sent to the webhook **************/
+
+#[derive(Serialize)]
+pub struct WebhookPayload {
+    pub delivery_id: String,
+    pub event: WebhookEvent,
+    pub tenant: String,
+    pub timestamp: DateTime<Utc>,
+    pub data: Value,
+}
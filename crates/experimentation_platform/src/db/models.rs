@@ -34,10 +34,166 @@ pub struct Experiment {
     pub variants: Value,
     pub last_modified_by: String,
     pub chosen_variant: Option<String>,
+
+    pub description: Option<String>,
+    pub hypothesis: Option<String>,
+    pub owner_team: Option<String>,
+    pub tags: Vec<String>,
+    pub external_links: Vec<String>,
+
+    pub start_at: Option<DateTime<Utc>>,
+    pub end_at: Option<DateTime<Utc>>,
+    pub start_traffic_percentage: Option<i32>,
+    pub fallback_variant: Option<String>,
+
+    pub layer: Option<String>,
+    pub layer_offset: Option<i32>,
+    pub layer_allocation: Option<i32>,
+
+    pub bandit: bool,
 }
 
 pub type Experiments = Vec<Experiment>;
 
+#[derive(
+    Debug, Clone, Copy, PartialEq, Deserialize, Serialize, diesel_derive_enum::DbEnum,
+)]
+#[DbValueStyle = "UPPERCASE"]
+#[ExistingTypePath = "crate::db::schema::sql_types::ExperimentChangeRequestAction"]
+pub enum ChangeRequestAction {
+    CREATE,
+    RAMP,
+    CONCLUDE,
+}
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Deserialize, Serialize, diesel_derive_enum::DbEnum,
+)]
+#[DbValueStyle = "UPPERCASE"]
+#[ExistingTypePath = "crate::db::schema::sql_types::ExperimentChangeRequestStatus"]
+pub enum ChangeRequestStatus {
+    PENDING,
+    APPROVED,
+    REJECTED,
+}
+
+#[derive(Queryable, Selectable, Insertable, Serialize, Clone, Debug)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+#[diesel(table_name = experiment_change_requests)]
+#[diesel(primary_key(id))]
+pub struct ExperimentChangeRequest {
+    pub id: i64,
+    pub experiment_id: i64,
+    pub action: ChangeRequestAction,
+    pub payload: Value,
+    pub status: ChangeRequestStatus,
+    pub created_at: DateTime<Utc>,
+    pub created_by: String,
+    pub reviewed_at: Option<DateTime<Utc>>,
+    pub reviewed_by: Option<String>,
+    pub review_comment: Option<String>,
+}
+
+#[derive(Queryable, Selectable, Insertable, Serialize, Clone, Debug)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+#[diesel(table_name = experiment_variant_metrics)]
+#[diesel(primary_key(experiment_id, variant_id))]
+pub struct VariantMetrics {
+    pub experiment_id: i64,
+    pub variant_id: String,
+    pub trials: i64,
+    pub successes: i64,
+    pub last_updated: DateTime<Utc>,
+}
+
+#[derive(Queryable, Selectable, Insertable, Serialize, Clone, Debug)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+#[diesel(table_name = experiment_holdouts)]
+#[diesel(primary_key(name))]
+pub struct Holdout {
+    pub name: String,
+    pub layer: Option<String>,
+    pub percentage: i32,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+    pub created_by: String,
+    pub last_modified: DateTime<Utc>,
+    pub last_modified_by: String,
+}
+
+#[derive(Queryable, Selectable, Insertable, Serialize, Clone, Debug)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+#[diesel(table_name = experiment_templates)]
+#[diesel(primary_key(name))]
+pub struct Template {
+    pub name: String,
+    pub description: Option<String>,
+    pub variants: Value,
+    pub created_at: DateTime<Utc>,
+    pub created_by: String,
+    pub last_modified: DateTime<Utc>,
+    pub last_modified_by: String,
+}
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Deserialize, Serialize, diesel_derive_enum::DbEnum,
+)]
+#[DbValueStyle = "UPPERCASE"]
+#[ExistingTypePath = "crate::db::schema::sql_types::ExperimentWebhookEvent"]
+pub enum WebhookEvent {
+    CREATED,
+    RAMPED,
+    CONCLUDED,
+    // the change request creating the experiment was rejected
+    DISCARDED,
+    TEST,
+}
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Deserialize, Serialize, diesel_derive_enum::DbEnum,
+)]
+#[DbValueStyle = "UPPERCASE"]
+#[ExistingTypePath = "crate::db::schema::sql_types::ExperimentWebhookDeliveryStatus"]
+pub enum WebhookDeliveryStatus {
+    PENDING,
+    SUCCEEDED,
+    FAILED,
+}
+
+#[derive(Queryable, Selectable, Insertable, Clone, Debug)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+#[diesel(table_name = experiment_webhooks)]
+#[diesel(primary_key(id))]
+pub struct Webhook {
+    pub id: i64,
+    pub url: String,
+    pub secret: String,
+    pub events: Vec<WebhookEvent>,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+    pub created_by: String,
+    pub last_modified: DateTime<Utc>,
+    pub last_modified_by: String,
+}
+
+#[derive(Queryable, Selectable, Insertable, Serialize, Clone, Debug)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+#[diesel(table_name = experiment_webhook_deliveries)]
+#[diesel(primary_key(id))]
+pub struct WebhookDelivery {
+    pub id: i64,
+    pub webhook_id: i64,
+    pub event: WebhookEvent,
+    pub payload: Value,
+    pub status: WebhookDeliveryStatus,
+    pub attempts: i32,
+    pub response_code: Option<i32>,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_attempted_at: Option<DateTime<Utc>>,
+    pub next_attempt_at: Option<DateTime<Utc>>,
+}
+
 #[derive(Queryable, Selectable, Insertable, Serialize, Clone, Debug)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 #[diesel(table_name = event_log)]
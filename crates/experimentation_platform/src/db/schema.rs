@@ -1,9 +1,25 @@
 // @generated automatically by Diesel CLI.
 
 pub mod sql_types {
+    #[derive(diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "experiment_change_request_action"))]
+    pub struct ExperimentChangeRequestAction;
+
+    #[derive(diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "experiment_change_request_status"))]
+    pub struct ExperimentChangeRequestStatus;
+
     #[derive(diesel::sql_types::SqlType)]
     #[diesel(postgres_type(name = "experiment_status_type"))]
     pub struct ExperimentStatusType;
+
+    #[derive(diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "experiment_webhook_delivery_status"))]
+    pub struct ExperimentWebhookDeliveryStatus;
+
+    #[derive(diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "experiment_webhook_event"))]
+    pub struct ExperimentWebhookEvent;
 }
 
 diesel::table! {
@@ -552,6 +568,97 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::ExperimentChangeRequestAction;
+    use super::sql_types::ExperimentChangeRequestStatus;
+
+    experiment_change_requests (id) {
+        id -> Int8,
+        experiment_id -> Int8,
+        action -> ExperimentChangeRequestAction,
+        payload -> Json,
+        status -> ExperimentChangeRequestStatus,
+        created_at -> Timestamptz,
+        created_by -> Text,
+        reviewed_at -> Nullable<Timestamptz>,
+        reviewed_by -> Nullable<Text>,
+        review_comment -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    experiment_holdouts (name) {
+        name -> Text,
+        layer -> Nullable<Text>,
+        percentage -> Int4,
+        enabled -> Bool,
+        created_at -> Timestamptz,
+        created_by -> Text,
+        last_modified -> Timestamptz,
+        last_modified_by -> Text,
+    }
+}
+
+diesel::table! {
+    experiment_templates (name) {
+        name -> Text,
+        description -> Nullable<Text>,
+        variants -> Json,
+        created_at -> Timestamptz,
+        created_by -> Text,
+        last_modified -> Timestamptz,
+        last_modified_by -> Text,
+    }
+}
+
+diesel::table! {
+    experiment_variant_metrics (experiment_id, variant_id) {
+        experiment_id -> Int8,
+        variant_id -> Text,
+        trials -> Int8,
+        successes -> Int8,
+        last_updated -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::ExperimentWebhookEvent;
+    use super::sql_types::ExperimentWebhookDeliveryStatus;
+
+    experiment_webhook_deliveries (id) {
+        id -> Int8,
+        webhook_id -> Int8,
+        event -> ExperimentWebhookEvent,
+        payload -> Json,
+        status -> ExperimentWebhookDeliveryStatus,
+        attempts -> Int4,
+        response_code -> Nullable<Int4>,
+        error -> Nullable<Text>,
+        created_at -> Timestamptz,
+        last_attempted_at -> Nullable<Timestamptz>,
+        next_attempt_at -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::ExperimentWebhookEvent;
+
+    experiment_webhooks (id) {
+        id -> Int8,
+        url -> Text,
+        secret -> Text,
+        events -> Array<ExperimentWebhookEvent>,
+        enabled -> Bool,
+        created_at -> Timestamptz,
+        created_by -> Text,
+        last_modified -> Timestamptz,
+        last_modified_by -> Text,
+    }
+}
+
 diesel::table! {
     use diesel::sql_types::*;
     use super::sql_types::ExperimentStatusType;
@@ -569,9 +676,24 @@ diesel::table! {
         variants -> Json,
         last_modified_by -> Text,
         chosen_variant -> Nullable<Text>,
+        description -> Nullable<Text>,
+        hypothesis -> Nullable<Text>,
+        owner_team -> Nullable<Text>,
+        tags -> Array<Text>,
+        external_links -> Array<Text>,
+        start_at -> Nullable<Timestamptz>,
+        end_at -> Nullable<Timestamptz>,
+        start_traffic_percentage -> Nullable<Int4>,
+        fallback_variant -> Nullable<Text>,
+        layer -> Nullable<Text>,
+        layer_offset -> Nullable<Int4>,
+        layer_allocation -> Nullable<Int4>,
+        bandit -> Bool,
     }
 }
 
+diesel::joinable!(experiment_webhook_deliveries -> experiment_webhooks (webhook_id));
+
 diesel::allow_tables_to_appear_in_same_query!(
     event_log,
     event_log_y2023m08,
@@ -615,5 +737,11 @@ diesel::allow_tables_to_appear_in_same_query!(
     event_log_y2026m10,
     event_log_y2026m11,
     event_log_y2026m12,
+    experiment_change_requests,
+    experiment_holdouts,
+    experiment_templates,
+    experiment_variant_metrics,
+    experiment_webhook_deliveries,
+    experiment_webhooks,
     experiments,
 );
@@ -0,0 +1,923 @@
+// These tests call the API against a postgres database holding the schemas of
+// the `test` tenant (see `make test-tenant`), read from DATABASE_URL. They are
+// ignored by default, run them with
+// `cargo test -p experimentation_platform --test api_tests -- --ignored`
+use std::{
+    collections::HashSet,
+    sync::{Barrier, Mutex},
+    thread,
+    time::Duration,
+};
+
+use actix_web::{
+    dev::Service,
+    http::StatusCode,
+    test::{self, TestRequest},
+    web::{scope, Data},
+    App, HttpMessage,
+};
+use chrono::Utc;
+use context_aware_config::{
+    api::{default_config, dimension},
+    db::schema::contexts::dsl as contexts,
+    helpers::{get_default_config_validation_schema, get_meta_schema},
+};
+use diesel::{sql_query, Connection, ExpressionMethods, QueryDsl, RunQueryDsl};
+use experimentation_platform::api::{
+    change_requests, drift,
+    experiments::{
+        self,
+        handlers::create_experiment,
+        helpers::{find_layer_offset, lock_layer},
+        scheduler::run_schedule,
+        types::ExperimentCreateRequest,
+    },
+    webhooks::{
+        self,
+        helpers::{create_delivery, retry_due_deliveries},
+    },
+};
+use experimentation_platform::db::{
+    models::{
+        Experiment, ExperimentStatusType, Webhook, WebhookDelivery,
+        WebhookDeliveryStatus, WebhookEvent,
+    },
+    schema::{
+        experiment_webhook_deliveries::dsl as deliveries,
+        experiment_webhooks::dsl as webhooks_table,
+        experiments::dsl as experiments_table,
+    },
+};
+use serde_json::{json, Value};
+use service_utils::{
+    db::pgschema_manager::{ConnectionConfig, PgSchemaManager},
+    middlewares::{
+        app_scope::AppExecutionScopeMiddlewareFactory, tenant::TenantMiddlewareFactory,
+    },
+    service::types::{
+        AppEnv, AppScope, AppState, BanditConfig, ExperimentApprovalConfig,
+        ExperimentationFlags, Tenant, WebhookConfig,
+    },
+};
+use snowflake::SnowflakeIdGenerator;
+use superposition_types::User;
+
+const TENANT: &str = "test";
+const REVIEWER: &str = "reviewer@superposition.io";
+
+fn app_state(approval: ExperimentApprovalConfig) -> Data<AppState> {
+    app_state_with(
+        approval,
+        WebhookConfig {
+            max_attempts: 1,
+            retry_backoff_secs: 0,
+        },
+    )
+}
+
+fn app_state_with(
+    approval: ExperimentApprovalConfig,
+    webhook: WebhookConfig,
+) -> Data<AppState> {
+    dotenv::dotenv().ok();
+    let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL is not set");
+    let db_pool = PgSchemaManager::from(
+        ["test_cac", "test_experimentation"]
+            .into_iter()
+            .map(|namespace| {
+                ConnectionConfig::new(
+                    namespace.to_string(),
+                    database_url.clone(),
+                    namespace.to_string(),
+                    2,
+                )
+            })
+            .collect::<Vec<ConnectionConfig>>(),
+    );
+    Data::new(AppState {
+        cac_host: "http://localhost:8080".to_string(),
+        cac_in_process: true,
+        app_env: AppEnv::TEST,
+        tenants: HashSet::from([TENANT.to_string()]),
+        cac_version: "v0.1.0".to_string(),
+        db_pool,
+        default_config_validation_schema: get_default_config_validation_schema(),
+        meta_schema: get_meta_schema(),
+        experimentation_flags: ExperimentationFlags {
+            allow_same_keys_overlapping_ctx: true,
+            allow_diff_keys_overlapping_ctx: true,
+            allow_same_keys_non_overlapping_ctx: true,
+        },
+        experiment_approval: approval,
+        webhook,
+        bandit: BanditConfig {
+            min_weight: 5,
+            samples: 100,
+        },
+        snowflake_generator: Mutex::new(SnowflakeIdGenerator::new(1, 1)),
+        enable_tenant_and_scope: true,
+        tenant_middleware_exclusion_list: HashSet::new(),
+        service_prefix: String::new(),
+    })
+}
+
+fn without_approval() -> Data<AppState> {
+    app_state(ExperimentApprovalConfig {
+        enabled: false,
+        ramp_threshold: 0,
+    })
+}
+
+fn with_approval(ramp_threshold: u8) -> Data<AppState> {
+    app_state(ExperimentApprovalConfig {
+        enabled: true,
+        ramp_threshold,
+    })
+}
+
+// the user making a request is taken from the x-user header
+macro_rules! test_app {
+    ($state:expr) => {
+        test::init_service(
+            App::new()
+                .wrap_fn(|req, srv| {
+                    let email = req
+                        .headers()
+                        .get("x-user")
+                        .and_then(|email| email.to_str().ok())
+                        .map(String::from)
+                        .unwrap_or_else(|| User::default().email);
+                    req.extensions_mut().insert(User {
+                        email,
+                        ..User::default()
+                    });
+                    srv.call(req)
+                })
+                .wrap(TenantMiddlewareFactory)
+                .app_data($state)
+                .service(
+                    scope("/dimension")
+                        .wrap(AppExecutionScopeMiddlewareFactory::new(AppScope::CAC))
+                        .service(dimension::endpoints()),
+                )
+                .service(
+                    scope("/default-config")
+                        .wrap(AppExecutionScopeMiddlewareFactory::new(AppScope::CAC))
+                        .service(default_config::endpoints()),
+                )
+                .service(drift::endpoints(scope("/experiments/drift")).wrap(
+                    AppExecutionScopeMiddlewareFactory::new(AppScope::EXPERIMENTATION),
+                ))
+                .service(webhooks::endpoints(scope("/experiments/webhooks")).wrap(
+                    AppExecutionScopeMiddlewareFactory::new(AppScope::EXPERIMENTATION),
+                ))
+                .service(
+                    change_requests::endpoints(scope("/experiments/change-requests"))
+                        .wrap(AppExecutionScopeMiddlewareFactory::new(
+                            AppScope::EXPERIMENTATION,
+                        )),
+                )
+                .service(experiments::endpoints(scope("/experiments")).wrap(
+                    AppExecutionScopeMiddlewareFactory::new(AppScope::EXPERIMENTATION),
+                )),
+        )
+        .await
+    };
+}
+
+macro_rules! send {
+    ($app:expr, $req:expr) => {{
+        let resp = test::call_service(
+            &$app,
+            $req.insert_header(("x-tenant", TENANT)).to_request(),
+        )
+        .await;
+        let status = resp.status();
+        let body = test::read_body(resp).await;
+        (
+            status,
+            serde_json::from_slice::<Value>(&body).unwrap_or(Value::Null),
+        )
+    }};
+}
+
+fn unique(prefix: &str) -> String {
+    format!("{}-{}", prefix, Utc::now().timestamp_micros())
+}
+
+fn experiment_request(name: &str, client: &str) -> Value {
+    json!({
+        "name": name,
+        "context": {
+            "and": [
+                {"==": [{"var": "os"}, "ios"]},
+                {"==": [{"var": "client"}, client]}
+            ]
+        },
+        "variants": [
+            {
+                "id": "control",
+                "variant_type": "CONTROL",
+                "overrides": {"apiTestKey": "control"}
+            },
+            {
+                "id": "test",
+                "variant_type": "EXPERIMENTAL",
+                "overrides": {"apiTestKey": "test"}
+            }
+        ]
+    })
+}
+
+// creates the dimensions and default config the experiments here are set on
+macro_rules! setup_cac {
+    ($app:expr) => {{
+        for dimension in ["os", "client"] {
+            let (status, _) = send!(
+                $app,
+                TestRequest::put().uri("/dimension").set_json(json!({
+                    "dimension": dimension,
+                    "priority": 10,
+                    "schema": {"type": "string", "pattern": ".*"}
+                }))
+            );
+            assert!(status.is_success());
+        }
+        let (status, _) = send!(
+            $app,
+            TestRequest::put()
+                .uri("/default-config/apiTestKey")
+                .set_json(json!({
+                    "value": "default",
+                    "schema": {"type": "string", "pattern": ".*"}
+                }))
+        );
+        assert!(status.is_success());
+    }};
+}
+
+macro_rules! create_experiment {
+    ($app:expr) => {{
+        let (status, body) = send!(
+            $app,
+            TestRequest::post()
+                .uri("/experiments")
+                .set_json(experiment_request(&unique("api-test"), &unique("client")))
+        );
+        assert_eq!(status, StatusCode::OK, "{body}");
+        body["experiment_id"].as_str().unwrap().to_string()
+    }};
+}
+
+#[actix_web::test]
+#[ignore = "needs a database"]
+async fn test_self_approval_is_rejected() {
+    let app = test_app!(with_approval(0));
+    setup_cac!(app);
+
+    let (status, change_request) = send!(
+        app,
+        TestRequest::post()
+            .uri("/experiments")
+            .set_json(experiment_request(&unique("api-test"), &unique("client")))
+    );
+    assert_eq!(status, StatusCode::ACCEPTED);
+    let approve_uri = format!(
+        "/experiments/change-requests/{}/approve",
+        change_request["id"].as_str().unwrap()
+    );
+
+    let (status, _) = send!(app, TestRequest::patch().uri(&approve_uri));
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, approved) = send!(
+        app,
+        TestRequest::patch()
+            .uri(&approve_uri)
+            .insert_header(("x-user", REVIEWER))
+    );
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(approved["status"], "APPROVED");
+    assert_eq!(approved["reviewed_by"], REVIEWER);
+
+    let (status, experiment) = send!(
+        app,
+        TestRequest::get().uri(&format!(
+            "/experiments/{}",
+            change_request["experiment_id"].as_str().unwrap()
+        ))
+    );
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(experiment["created_by"], User::default().email);
+}
+
+#[actix_web::test]
+#[ignore = "needs a database"]
+async fn test_ramp_approval_threshold() {
+    let app = test_app!(without_approval());
+    setup_cac!(app);
+    let experiment_id = create_experiment!(app);
+    let ramp_uri = format!("/experiments/{experiment_id}/ramp");
+
+    let app = test_app!(with_approval(20));
+    let (status, experiment) = send!(
+        app,
+        TestRequest::patch()
+            .uri(&ramp_uri)
+            .set_json(json!({"traffic_percentage": 20}))
+    );
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(experiment["traffic_percentage"], 20);
+
+    let (status, change_request) = send!(
+        app,
+        TestRequest::patch()
+            .uri(&ramp_uri)
+            .set_json(json!({"traffic_percentage": 40}))
+    );
+    assert_eq!(status, StatusCode::ACCEPTED);
+    assert_eq!(change_request["action"], "RAMP");
+    assert_eq!(change_request["status"], "PENDING");
+
+    // only one change request can be pending at a time
+    let (status, _) = send!(
+        app,
+        TestRequest::patch()
+            .uri(&ramp_uri)
+            .set_json(json!({"traffic_percentage": 30}))
+    );
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, experiment) = send!(
+        app,
+        TestRequest::get().uri(&format!("/experiments/{experiment_id}"))
+    );
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(experiment["traffic_percentage"], 20);
+}
+
+#[actix_web::test]
+#[ignore = "needs a database"]
+async fn test_failed_approval_reverts_to_pending() {
+    let direct_app = test_app!(without_approval());
+    setup_cac!(direct_app);
+    let experiment_id = create_experiment!(direct_app);
+
+    let app = test_app!(with_approval(0));
+    let (status, change_request) = send!(
+        app,
+        TestRequest::patch()
+            .uri(&format!("/experiments/{experiment_id}/ramp"))
+            .set_json(json!({"traffic_percentage": 40}))
+    );
+    assert_eq!(status, StatusCode::ACCEPTED);
+    let change_request_uri = format!(
+        "/experiments/change-requests/{}",
+        change_request["id"].as_str().unwrap()
+    );
+
+    // concluding the experiment makes the ramp fail once it is approved
+    let (status, _) = send!(
+        direct_app,
+        TestRequest::patch()
+            .uri(&format!("/experiments/{experiment_id}/conclude"))
+            .set_json(json!({"chosen_variant": format!("{experiment_id}-control")}))
+    );
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _) = send!(
+        app,
+        TestRequest::patch()
+            .uri(&format!("{change_request_uri}/approve"))
+            .insert_header(("x-user", REVIEWER))
+    );
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, change_request) =
+        send!(app, TestRequest::get().uri(&change_request_uri));
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(change_request["status"], "PENDING");
+    assert_eq!(change_request["reviewed_by"], Value::Null);
+}
+
+#[actix_web::test]
+#[ignore = "needs a database"]
+async fn test_failed_experiment_insert_rolls_back_contexts() {
+    let state = without_approval();
+    let app = test_app!(state.clone());
+    setup_cac!(app);
+
+    // an experiment already holding the id makes the insert of the new one fail
+    // after its contexts have been created
+    let experiment_id = Utc::now().timestamp_micros();
+    let mut conn = state
+        .db_pool
+        .get_conn("test_experimentation".to_string())
+        .unwrap();
+    sql_query(format!(
+        "INSERT INTO experiments (id, created_by, name, override_keys, status, \
+         traffic_percentage, context, variants) VALUES ({experiment_id}, \
+         'user@superposition.io', 'taken', '{{}}', 'CREATED', 0, '{{}}', '[]')"
+    ))
+    .execute(&mut conn)
+    .unwrap();
+
+    let req: ExperimentCreateRequest = serde_json::from_value(experiment_request(
+        &unique("api-test"),
+        &unique("client"),
+    ))
+    .unwrap();
+    let result = create_experiment(
+        state.clone(),
+        experiment_id,
+        req,
+        &mut conn,
+        Tenant(TENANT.to_string()),
+        User::default(),
+        User::default().email,
+    )
+    .await;
+    assert!(result.is_err());
+
+    let mut cac_conn = state.db_pool.get_conn("test_cac".to_string()).unwrap();
+    let experiment_contexts = contexts::contexts
+        .select(contexts::value)
+        .load::<Value>(&mut cac_conn)
+        .unwrap()
+        .into_iter()
+        .filter(|condition| condition.to_string().contains(&experiment_id.to_string()))
+        .count();
+    assert_eq!(experiment_contexts, 0);
+}
+
+#[actix_web::test]
+#[ignore = "needs a database"]
+async fn test_list_experiment_filters() {
+    let state = without_approval();
+    let app = test_app!(state.clone());
+    setup_cac!(app);
+
+    let suffix = unique("");
+    let client = unique("client");
+    let mut experiment_ids = Vec::new();
+    for name in ["a_b", "axb", "c100%", "d1000"] {
+        let (status, body) = send!(
+            app,
+            TestRequest::post()
+                .uri("/experiments")
+                .set_json(experiment_request(&format!("{name}{suffix}"), &client))
+        );
+        assert_eq!(status, StatusCode::OK, "{body}");
+        experiment_ids.push(body["experiment_id"].as_str().unwrap().to_string());
+    }
+
+    let list = |query: String| TestRequest::get().uri(&format!("/experiments?{query}"));
+    let names = |body: &Value| {
+        body["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|experiment| experiment["name"].as_str().unwrap().to_string())
+            .collect::<Vec<String>>()
+    };
+
+    // wildcards in the name are matched literally
+    let (status, body) = send!(app, list(format!("experiment_name=a_b{suffix}")));
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(names(&body), vec![format!("a_b{suffix}")]);
+    let (_, body) = send!(app, list(format!("experiment_name=c100%25{suffix}")));
+    assert_eq!(names(&body), vec![format!("c100%{suffix}")]);
+
+    // experiments last modified before the default 24 hour window are still
+    // found by name, id or creator, but not by the other filters
+    let mut conn = state
+        .db_pool
+        .get_conn("test_experimentation".to_string())
+        .unwrap();
+    sql_query(format!(
+        "UPDATE experiments SET last_modified = now() - interval '2 days' WHERE id = {}",
+        experiment_ids[1]
+    ))
+    .execute(&mut conn)
+    .unwrap();
+
+    let (_, body) = send!(app, list(format!("experiment_name=axb{suffix}")));
+    assert_eq!(names(&body), vec![format!("axb{suffix}")]);
+    let (_, body) = send!(app, list(format!("experiment_ids={}", experiment_ids[1])));
+    assert_eq!(names(&body), vec![format!("axb{suffix}")]);
+    let (_, body) = send!(
+        app,
+        list(format!(
+            "created_by={}&dimensions=client={client}",
+            User::default().email
+        ))
+    );
+    assert_eq!(body["total_items"], 4);
+    let (status, body) = send!(
+        app,
+        list(format!(
+            "dimensions=client={client}&override_keys=apiTestKey&sort_on=name&sort_by=asc"
+        ))
+    );
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        names(&body),
+        vec![
+            format!("a_b{suffix}"),
+            format!("c100%{suffix}"),
+            format!("d1000{suffix}")
+        ]
+    );
+}
+
+#[actix_web::test]
+#[ignore = "needs a database"]
+async fn test_update_experiment_metadata() {
+    let app = test_app!(without_approval());
+    setup_cac!(app);
+
+    let tag = unique("tag");
+    let mut req = experiment_request(&unique("api-test"), &unique("client"));
+    req["description"] = json!("description");
+    req["hypothesis"] = json!("hypothesis");
+    req["owner_team"] = json!("team");
+    req["tags"] = json!([tag]);
+    let (status, body) =
+        send!(app, TestRequest::post().uri("/experiments").set_json(req));
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let experiment_id = body["experiment_id"].as_str().unwrap().to_string();
+
+    let (status, body) = send!(
+        app,
+        TestRequest::get().uri(&format!("/experiments?tags={tag}"))
+    );
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["total_items"], 1);
+
+    let metadata_uri = format!("/experiments/{experiment_id}/metadata");
+    let (status, experiment) = send!(
+        app,
+        TestRequest::patch()
+            .uri(&metadata_uri)
+            .set_json(json!({"hypothesis": "updated"}))
+    );
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(experiment["description"], "description");
+    assert_eq!(experiment["hypothesis"], "updated");
+    assert_eq!(experiment["owner_team"], "team");
+
+    let (status, experiment) = send!(
+        app,
+        TestRequest::patch()
+            .uri(&metadata_uri)
+            .set_json(json!({"description": null, "owner_team": null}))
+    );
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(experiment["description"], Value::Null);
+    assert_eq!(experiment["hypothesis"], "updated");
+    assert_eq!(experiment["owner_team"], Value::Null);
+    assert_eq!(experiment["tags"], json!([tag]));
+}
+
+#[actix_web::test]
+#[ignore = "needs a database"]
+async fn test_rejected_creation_is_discarded() {
+    let app = test_app!(with_approval(0));
+    setup_cac!(app);
+
+    let (status, webhook) = send!(
+        app,
+        TestRequest::post()
+            .uri("/experiments/webhooks")
+            .set_json(json!({
+                "url": "http://127.0.0.1:9/webhook",
+                "events": ["DISCARDED"]
+            }))
+    );
+    assert!(status.is_success(), "{webhook}");
+    let webhook_uri =
+        format!("/experiments/webhooks/{}", webhook["id"].as_str().unwrap());
+
+    let (status, change_request) = send!(
+        app,
+        TestRequest::post()
+            .uri("/experiments")
+            .set_json(experiment_request(&unique("api-test"), &unique("client")))
+    );
+    assert_eq!(status, StatusCode::ACCEPTED);
+    let (status, _) = send!(
+        app,
+        TestRequest::patch()
+            .uri(&format!(
+                "/experiments/change-requests/{}/reject",
+                change_request["id"].as_str().unwrap()
+            ))
+            .insert_header(("x-user", REVIEWER))
+    );
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, deliveries) = send!(
+        app,
+        TestRequest::get().uri(&format!("{webhook_uri}/deliveries"))
+    );
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(deliveries["total_items"], 1);
+    let delivery = &deliveries["data"][0];
+    assert_eq!(delivery["event"], "DISCARDED");
+    assert_eq!(delivery["payload"]["data"]["status"], "REJECTED");
+    assert_eq!(
+        delivery["payload"]["data"]["experiment_id"],
+        change_request["experiment_id"]
+    );
+
+    let (status, _) = send!(app, TestRequest::delete().uri(&webhook_uri));
+    assert!(status.is_success());
+}
+
+#[actix_web::test]
+#[ignore = "needs a database"]
+async fn test_drift_repair_restores_variant_contexts() {
+    let state = without_approval();
+    let app = test_app!(state.clone());
+    setup_cac!(app);
+    let experiment_id = create_experiment!(app);
+    let control_id = format!("{experiment_id}-control");
+    let test_id = format!("{experiment_id}-test");
+
+    let (_, experiment) = send!(
+        app,
+        TestRequest::get().uri(&format!("/experiments/{experiment_id}"))
+    );
+    let context_id_of = |experiment: &Value, variant_id: &str| {
+        experiment["variants"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|variant| variant["id"] == variant_id)
+            .and_then(|variant| variant["context_id"].as_str())
+            .unwrap()
+            .to_string()
+    };
+
+    // the control context is deleted and the experimental one is moved to
+    // another condition behind the experiment's back
+    let mut cac_conn = state.db_pool.get_conn("test_cac".to_string()).unwrap();
+    diesel::delete(contexts::contexts.find(context_id_of(&experiment, &control_id)))
+        .execute(&mut cac_conn)
+        .unwrap();
+    let test_context_id = context_id_of(&experiment, &test_id);
+    let test_condition: Value = contexts::contexts
+        .find(&test_context_id)
+        .select(contexts::value)
+        .first(&mut cac_conn)
+        .unwrap();
+    let moved_condition: Value =
+        serde_json::from_str(&test_condition.to_string().replace("ios", "android"))
+            .unwrap();
+    let moved_context_id = unique("moved");
+    diesel::update(contexts::contexts.find(&test_context_id))
+        .set((
+            contexts::id.eq(&moved_context_id),
+            contexts::value.eq(&moved_condition),
+        ))
+        .execute(&mut cac_conn)
+        .unwrap();
+
+    let (status, report) =
+        send!(app, TestRequest::post().uri("/experiments/drift/repair"));
+    assert_eq!(status, StatusCode::OK, "{report}");
+    let drifts: Vec<(&str, &str)> = report["drifts"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|drift| drift["experiment_id"] == experiment_id.as_str())
+        .map(|drift| {
+            (
+                drift["drift_type"].as_str().unwrap(),
+                drift["context_id"].as_str().unwrap(),
+            )
+        })
+        .collect();
+    assert!(
+        drifts.contains(&("MISSING_CONTEXT", &context_id_of(&experiment, &control_id)))
+    );
+    assert!(drifts.contains(&("MOVED_CONTEXT", moved_context_id.as_str())));
+    assert!(report["failures"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .all(|failure| failure["experiment_id"] != experiment_id.as_str()));
+
+    // both variants are backed by their original condition again, without the
+    // moved context left behind
+    let (_, repaired) = send!(
+        app,
+        TestRequest::get().uri(&format!("/experiments/{experiment_id}"))
+    );
+    for (variant_id, overrides) in [(&control_id, "control"), (&test_id, "test")] {
+        let (condition, override_): (Value, Value) = contexts::contexts
+            .find(context_id_of(&repaired, variant_id))
+            .select((contexts::value, contexts::override_))
+            .first(&mut cac_conn)
+            .unwrap();
+        assert!(condition.to_string().contains("ios"));
+        assert_eq!(override_, json!({"apiTestKey": overrides}));
+    }
+    let moved_left: i64 = contexts::contexts
+        .find(&moved_context_id)
+        .count()
+        .get_result(&mut cac_conn)
+        .unwrap();
+    assert_eq!(moved_left, 0);
+}
+
+#[actix_web::test]
+#[ignore = "needs a database"]
+async fn test_failed_deliveries_are_retried_when_due() {
+    let state = app_state_with(
+        ExperimentApprovalConfig {
+            enabled: false,
+            ramp_threshold: 0,
+        },
+        WebhookConfig {
+            max_attempts: 2,
+            retry_backoff_secs: 0,
+        },
+    );
+    let app = test_app!(state.clone());
+
+    let (status, webhook) = send!(
+        app,
+        TestRequest::post()
+            .uri("/experiments/webhooks")
+            .set_json(json!({
+                "url": "http://127.0.0.1:9/webhook",
+                "events": ["CREATED"]
+            }))
+    );
+    assert!(status.is_success(), "{webhook}");
+    let webhook_id: i64 = webhook["id"].as_str().unwrap().parse().unwrap();
+
+    let mut conn = state
+        .db_pool
+        .get_conn("test_experimentation".to_string())
+        .unwrap();
+    let webhook: Webhook = webhooks_table::experiment_webhooks
+        .find(webhook_id)
+        .first(&mut conn)
+        .unwrap();
+    let delivery = create_delivery(
+        &state,
+        &webhook,
+        WebhookEvent::CREATED,
+        json!({}),
+        &Tenant(TENANT.to_string()),
+        &mut conn,
+    )
+    .unwrap();
+    let fetch = |conn: &mut _| -> WebhookDelivery {
+        deliveries::experiment_webhook_deliveries
+            .find(delivery.id)
+            .first(conn)
+            .unwrap()
+    };
+
+    // a new delivery belongs to whoever created it, the retry job leaves it
+    retry_due_deliveries(&state, &mut conn).await.unwrap();
+    assert_eq!(fetch(&mut conn).attempts, 0);
+
+    // the creator went away before attempting it, e.g. on a restart
+    diesel::update(deliveries::experiment_webhook_deliveries.find(delivery.id))
+        .set(deliveries::next_attempt_at.eq(Some(Utc::now())))
+        .execute(&mut conn)
+        .unwrap();
+    retry_due_deliveries(&state, &mut conn).await.unwrap();
+    let retried = fetch(&mut conn);
+    assert_eq!(retried.attempts, 1);
+    assert_eq!(retried.status, WebhookDeliveryStatus::PENDING);
+    assert!(retried.next_attempt_at.is_some());
+
+    retry_due_deliveries(&state, &mut conn).await.unwrap();
+    let failed = fetch(&mut conn);
+    assert_eq!(failed.attempts, 2);
+    assert_eq!(failed.status, WebhookDeliveryStatus::FAILED);
+    assert_eq!(failed.next_attempt_at, None);
+
+    let (status, _) = send!(
+        app,
+        TestRequest::delete().uri(&format!("/experiments/webhooks/{webhook_id}"))
+    );
+    assert!(status.is_success());
+}
+
+#[actix_web::test]
+#[ignore = "needs a database"]
+async fn test_scheduler_acts_as_the_experiment_creator() {
+    let state = without_approval();
+    let app = test_app!(state.clone());
+    setup_cac!(app);
+    let creator = "scheduler-owner@superposition.io";
+
+    let mut request = experiment_request(&unique("api-test"), &unique("client"));
+    request["start_at"] = json!(Utc::now() + chrono::Duration::hours(1));
+    request["start_traffic_percentage"] = json!(10);
+    request["end_at"] = json!(Utc::now() + chrono::Duration::hours(2));
+    request["fallback_variant"] = json!("control");
+    let (status, body) = send!(
+        app,
+        TestRequest::post()
+            .uri("/experiments")
+            .insert_header(("x-user", creator))
+            .set_json(request)
+    );
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let experiment_id: i64 = body["experiment_id"].as_str().unwrap().parse().unwrap();
+
+    let mut conn = state
+        .db_pool
+        .get_conn("test_experimentation".to_string())
+        .unwrap();
+    let tenant = Tenant(TENANT.to_string());
+    let get_experiment = |conn: &mut _| -> Experiment {
+        experiments_table::experiments
+            .find(experiment_id)
+            .first(conn)
+            .unwrap()
+    };
+
+    // start_at passes
+    diesel::update(experiments_table::experiments.find(experiment_id))
+        .set(experiments_table::start_at.eq(Some(Utc::now())))
+        .execute(&mut conn)
+        .unwrap();
+    run_schedule(&state, &mut conn, &tenant, &User::default())
+        .await
+        .unwrap();
+    let started = get_experiment(&mut conn);
+    assert_eq!(started.status, ExperimentStatusType::INPROGRESS);
+    assert_eq!(started.traffic_percentage, 10);
+    assert_eq!(started.last_modified_by, creator);
+
+    // end_at passes, the experiment is concluded with the fallback variant
+    diesel::update(experiments_table::experiments.find(experiment_id))
+        .set(experiments_table::end_at.eq(Some(Utc::now())))
+        .execute(&mut conn)
+        .unwrap();
+    run_schedule(&state, &mut conn, &tenant, &User::default())
+        .await
+        .unwrap();
+    let ended = get_experiment(&mut conn);
+    assert_eq!(ended.status, ExperimentStatusType::CONCLUDED);
+    assert_eq!(
+        ended.chosen_variant,
+        Some(format!("{experiment_id}-control"))
+    );
+    assert_eq!(ended.last_modified_by, creator);
+}
+
+#[actix_web::test]
+#[ignore = "needs a database"]
+async fn test_concurrent_layer_allocations_get_disjoint_slices() {
+    let state = without_approval();
+    let layer = unique("layer");
+    let barrier = Barrier::new(2);
+
+    // both allocations look for a free slice at the same time, and hold on to
+    // it for a while before inserting their experiment
+    let mut offsets: Vec<i32> = thread::scope(|scope| {
+        let allocations: Vec<_> = (0..2)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut conn = state
+                        .db_pool
+                        .get_conn("test_experimentation".to_string())
+                        .unwrap();
+                    barrier.wait();
+                    conn.transaction::<_, diesel::result::Error, _>(|conn| {
+                        lock_layer(&layer, conn).unwrap();
+                        let offset = find_layer_offset(&layer, 50, conn).unwrap();
+                        thread::sleep(Duration::from_millis(200));
+                        sql_query(format!(
+                            "INSERT INTO experiments (id, created_by, name, override_keys, \
+                             status, traffic_percentage, context, variants, layer, \
+                             layer_offset, layer_allocation) VALUES ({}, \
+                             'user@superposition.io', 'layered', '{{}}', 'CREATED', 0, \
+                             '{{}}', '[]', '{layer}', {offset}, 50)",
+                            Utc::now().timestamp_nanos_opt().unwrap()
+                        ))
+                        .execute(conn)?;
+                        Ok(offset)
+                    })
+                    .unwrap()
+                })
+            })
+            .collect();
+        allocations
+            .into_iter()
+            .map(|allocation| allocation.join().unwrap())
+            .collect()
+    });
+
+    offsets.sort();
+    assert_eq!(offsets, vec![0, 50]);
+}
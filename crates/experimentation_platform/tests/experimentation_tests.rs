@@ -1,10 +1,31 @@
-use chrono::Utc;
+use chrono::{Duration, Utc};
+use experimentation_platform::api::bandit::helpers::thompson_sampling_weights;
+use experimentation_platform::api::drift::{
+    helpers::detect_drift,
+    types::{CacContext, DriftType},
+};
 use experimentation_platform::api::experiments::helpers;
-use experimentation_platform::db::models::{Experiment, ExperimentStatusType};
+use experimentation_platform::api::experiments::types::{
+    ExperimentCloneRequest, ExperimentCreateRequest, HistoryEventType,
+};
+use experimentation_platform::api::holdouts::{
+    helpers::validate_holdout_request, types::HoldoutPutRequest,
+};
+use experimentation_platform::api::webhooks::helpers::{
+    retry_backoff, sign_payload, validate_webhook_request,
+};
+use experimentation_platform::db::models::{
+    EventLog, Experiment, ExperimentStatusType, WebhookEvent,
+};
+use rand::{rngs::StdRng, SeedableRng};
 use serde_json::{json, Map, Value};
-use service_utils::helpers::extract_dimensions;
+use service_utils::helpers::{
+    extract_conditions, extract_dimensions, parse_context, ConditionOperator,
+    ContextExpression,
+};
 use service_utils::result::AppError;
 use service_utils::service::types::ExperimentationFlags;
+use std::time::Duration as StdDuration;
 
 enum Dimensions {
     OS(String),
@@ -64,6 +85,23 @@ fn experiment_gen(
         context: context.clone(),
         variants: variants.clone(),
         chosen_variant: None,
+
+        description: None,
+        hypothesis: None,
+        owner_team: None,
+        tags: vec![],
+        external_links: vec![],
+
+        start_at: None,
+        end_at: None,
+        start_traffic_percentage: None,
+        fallback_variant: None,
+
+        layer: None,
+        layer_offset: None,
+        layer_allocation: None,
+
+        bandit: false,
     }
 }
 
@@ -108,6 +146,107 @@ fn test_extract_dimensions() -> Result<(), AppError> {
     Ok(())
 }
 
+#[test]
+fn test_extract_conditions() -> Result<(), AppError> {
+    let context = json!({
+        "and": [
+            {">": [{"var": "amount"}, 1000]},
+            {">=": [10, {"var": "distance"}]},
+            {"<=": [8, {"var": "hour_of_day"}, 11]},
+            {"!=": [{"var": "os"}, "ios"]},
+            {"!": {"in": [{"var": "city"}, ["Delhi", "Chennai"]]}}
+        ]
+    });
+    let conditions = extract_conditions(&context)?;
+    let operators = conditions
+        .iter()
+        .map(|condition| (condition.dimension.as_str(), condition.operator))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        operators,
+        vec![
+            ("amount", ConditionOperator::GreaterThan),
+            ("distance", ConditionOperator::LessThanOrEqual),
+            ("hour_of_day", ConditionOperator::Between),
+            ("os", ConditionOperator::IsNot),
+            ("city", ConditionOperator::NotIn),
+        ]
+    );
+
+    let [amount, distance, hour_of_day, os, city] = conditions.as_slice() else {
+        panic!("expected five conditions");
+    };
+    assert!(amount.matches(&json!(1001)) && !amount.matches(&json!(1000)));
+    assert!(amount.matches(&json!("1500.5")));
+    assert!(distance.matches(&json!(10)) && !distance.matches(&json!(11)));
+    assert!(hour_of_day.matches(&json!(8)) && hour_of_day.matches(&json!(11)));
+    assert!(!hour_of_day.matches(&json!(12)));
+    assert!(os.matches(&json!("android")) && !os.matches(&json!("ios")));
+    assert!(city.matches(&json!("Bangalore")) && !city.matches(&json!("Delhi")));
+
+    let substring = extract_conditions(&json!({"in": [{"var": "os"}, "android,ios"]}))?;
+    assert_eq!(substring[0].operator, ConditionOperator::SubstringOf);
+    assert!(substring[0].matches(&json!("ios")) && !substring[0].matches(&json!("web")));
+
+    // exclusive ranges and other operators are rejected
+    assert!(extract_conditions(&json!({"<": [1, {"var": "amount"}, 5]})).is_err());
+    assert!(extract_conditions(&json!({"if": [{"var": "os"}, "ios"]})).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_parse_context() -> Result<(), AppError> {
+    let context = json!({
+        "or": [
+            {"in": [{"var": "city"}, ["Bangalore", "Chennai"]]},
+            {"and": [
+                {"==": [{"var": "country"}, "India"]},
+                {"!": {"==": [{"var": "os"}, "ios"]}}
+            ]}
+        ]
+    });
+    let expression = parse_context(&context)?;
+    let ContextExpression::Or(branches) = &expression else {
+        panic!("expected an or, got {expression:?}");
+    };
+    assert!(matches!(
+        branches.as_slice(),
+        [ContextExpression::Condition(_), ContextExpression::And(_)]
+    ));
+    let dimensions = expression
+        .conditions()
+        .into_iter()
+        .map(|condition| condition.dimension.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(dimensions, vec!["city", "country", "os"]);
+
+    let evaluate = |dimensions: Value| {
+        expression.evaluate(dimensions.as_object().expect("dimensions object"))
+    };
+    assert_eq!(evaluate(json!({"city": "Chennai"})), Some(true));
+    assert_eq!(
+        evaluate(json!({"city": "Delhi", "country": "India", "os": "android"})),
+        Some(true)
+    );
+    assert_eq!(
+        evaluate(json!({"city": "Delhi", "country": "India", "os": "ios"})),
+        Some(false)
+    );
+    assert_eq!(
+        evaluate(json!({"city": "Delhi", "country": "USA"})),
+        Some(false)
+    );
+    // the outcome depends on `os`, which is not given
+    assert_eq!(evaluate(json!({"city": "Delhi", "country": "India"})), None);
+    assert_eq!(
+        evaluate(json!({"city": ["Delhi", "Bangalore"]})),
+        Some(true)
+    );
+
+    assert!(parse_context(&json!({"or": []})).is_err());
+    Ok(())
+}
+
 #[test]
 fn test_are_overlapping_contexts() -> Result<(), AppError> {
     let context_a = multiple_dimension_ctx_gen(vec![
@@ -611,3 +750,403 @@ fn test_pass_context_without_variantIds_dimensions() {
 
     assert_eq!(helpers::validate_context(&experiment_context).is_ok(), true);
 }
+
+#[test]
+fn test_detect_drift() -> Result<(), AppError> {
+    let context = single_dimension_ctx_gen(Dimensions::OS("os1".to_string()));
+    let variants = json!([
+        {
+            "id": "123456789-control",
+            "variant_type": "CONTROL",
+            "context_id": "ctx-control",
+            "override_id": "override-control",
+            "overrides": { "key1": "value1" }
+        },
+        {
+            "id": "123456789-test",
+            "variant_type": "EXPERIMENTAL",
+            "context_id": "ctx-test",
+            "override_id": "override-test",
+            "overrides": { "key1": "value2" }
+        }
+    ]);
+    let experiment = experiment_gen(
+        &vec!["key1".to_string()],
+        &context,
+        ExperimentStatusType::INPROGRESS,
+        &variants,
+    );
+
+    let cac_context = |id: &str, variant_id: &str, overrides: Value| CacContext {
+        id: id.to_string(),
+        value: multiple_dimension_ctx_gen(vec![
+            Dimensions::OS("os1".to_string()),
+            Dimensions::VARIANTIDS(variant_id.to_string()),
+        ]),
+        override_id: format!("override-{id}"),
+        override_: overrides,
+    };
+
+    // every variant backed by an unchanged context
+    let in_sync = vec![
+        cac_context(
+            "ctx-control",
+            "123456789-control",
+            json!({ "key1": "value1" }),
+        ),
+        cac_context("ctx-test", "123456789-test", json!({ "key1": "value2" })),
+    ];
+    assert!(detect_drift(&[experiment.clone()], &in_sync)?.is_empty());
+
+    // control context edited, experimental context deleted and a context of
+    // an experiment which no longer exists left behind
+    let drifted = vec![
+        cac_context(
+            "ctx-control",
+            "123456789-control",
+            json!({ "key1": "edited" }),
+        ),
+        cac_context("ctx-stray", "987654321-test", json!({ "key1": "value3" })),
+    ];
+    let drift_types: Vec<(DriftType, String)> =
+        detect_drift(&[experiment.clone()], &drifted)?
+            .into_iter()
+            .map(|drift| (drift.drift_type, drift.variant_id))
+            .collect();
+    assert_eq!(
+        drift_types,
+        vec![
+            (DriftType::AlteredOverride, "123456789-control".to_string()),
+            (DriftType::MissingContext, "123456789-test".to_string()),
+            (DriftType::StrayContext, "987654321-test".to_string()),
+        ]
+    );
+
+    // experimental context moved to another condition, it is reported under
+    // its new id and not as a stray context
+    let mut moved_context =
+        cac_context("ctx-moved", "123456789-test", json!({ "key1": "value2" }));
+    moved_context.value = multiple_dimension_ctx_gen(vec![
+        Dimensions::OS("os2".to_string()),
+        Dimensions::VARIANTIDS("123456789-test".to_string()),
+    ]);
+    let moved = vec![in_sync[0].clone(), moved_context];
+    let drift_types: Vec<(DriftType, Option<String>)> =
+        detect_drift(&[experiment], &moved)?
+            .into_iter()
+            .map(|drift| (drift.drift_type, drift.context_id))
+            .collect();
+    assert_eq!(
+        drift_types,
+        vec![(DriftType::MovedContext, Some("ctx-moved".to_string()))]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_webhook_signature() -> Result<(), AppError> {
+    // RFC 4231, test case 2
+    assert_eq!(
+        sign_payload("Jefe", b"what do ya want for nothing?")?,
+        "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_validate_webhook_request() {
+    let url = String::from("https://hooks.example.com/experiments");
+    let events = vec![WebhookEvent::CREATED, WebhookEvent::CONCLUDED];
+    assert!(validate_webhook_request(Some(&url), Some(&events)).is_ok());
+    assert!(validate_webhook_request(None, None).is_ok());
+
+    let invalid_url = String::from("ftp://hooks.example.com");
+    assert!(validate_webhook_request(Some(&invalid_url), None).is_err());
+    assert!(validate_webhook_request(None, Some(&vec![])).is_err());
+    assert!(validate_webhook_request(None, Some(&vec![WebhookEvent::TEST])).is_err());
+}
+
+#[test]
+fn test_webhook_retry_backoff() {
+    assert_eq!(retry_backoff(2, 1), StdDuration::from_secs(2));
+    assert_eq!(retry_backoff(2, 2), StdDuration::from_secs(4));
+    assert_eq!(retry_backoff(2, 5), StdDuration::from_secs(32));
+    assert_eq!(retry_backoff(0, 10), StdDuration::ZERO);
+
+    // large attempt counts and backoffs are capped instead of overflowing
+    let max_backoff = retry_backoff(2, 13);
+    assert!(max_backoff < StdDuration::from_secs(2 * 4096));
+    assert_eq!(retry_backoff(2, 64), max_backoff);
+    assert_eq!(retry_backoff(2, u32::MAX), max_backoff);
+    assert_eq!(retry_backoff(u64::MAX, 2), max_backoff);
+}
+
+#[test]
+fn test_validate_experiment_schedule() {
+    let schedule_req = |start_at, end_at, start_traffic_percentage, fallback_variant| {
+        serde_json::from_value::<ExperimentCreateRequest>(json!({
+            "name": "experiment-test",
+            "context": single_dimension_ctx_gen(Dimensions::OS("android".to_string())),
+            "variants": [
+                {"id": "control", "variant_type": "CONTROL", "overrides": {"key1": 1}},
+                {"id": "test", "variant_type": "EXPERIMENTAL", "overrides": {"key1": 2}},
+            ],
+            "start_at": start_at,
+            "end_at": end_at,
+            "start_traffic_percentage": start_traffic_percentage,
+            "fallback_variant": fallback_variant,
+        }))
+        .unwrap()
+    };
+    let tomorrow = Some(Utc::now() + Duration::days(1));
+    let next_week = Some(Utc::now() + Duration::days(7));
+    let yesterday = Some(Utc::now() - Duration::days(1));
+
+    let valid = schedule_req(tomorrow, next_week, Some(20), Some("control"));
+    assert!(helpers::validate_experiment_schedule(&valid).is_ok());
+    let unscheduled = schedule_req(None, None, None, None);
+    assert!(helpers::validate_experiment_schedule(&unscheduled).is_ok());
+
+    // start_at without a traffic percentage to start with
+    let req = schedule_req(tomorrow, None, None, None);
+    assert!(helpers::validate_experiment_schedule(&req).is_err());
+    // 2 variants cannot get more than 50% traffic each
+    let req = schedule_req(tomorrow, None, Some(60), None);
+    assert!(helpers::validate_experiment_schedule(&req).is_err());
+    // ending before it starts, or in the past
+    let req = schedule_req(next_week, tomorrow, Some(20), None);
+    assert!(helpers::validate_experiment_schedule(&req).is_err());
+    let req = schedule_req(None, yesterday, None, None);
+    assert!(helpers::validate_experiment_schedule(&req).is_err());
+    // fallback variant without end_at, or not one of the variants
+    let req = schedule_req(None, None, None, Some("control"));
+    assert!(helpers::validate_experiment_schedule(&req).is_err());
+    let req = schedule_req(None, next_week, None, Some("unknown"));
+    assert!(helpers::validate_experiment_schedule(&req).is_err());
+}
+
+#[test]
+fn test_ramp_after_end() {
+    let variants = json!([
+        {"id": "123456789-control", "variant_type": "CONTROL", "overrides": {"key1": 1}},
+        {"id": "123456789-test", "variant_type": "EXPERIMENTAL", "overrides": {"key1": 2}},
+    ]);
+    let mut experiment = experiment_gen(
+        &vec!["key1".to_string()],
+        &single_dimension_ctx_gen(Dimensions::OS("android".to_string())),
+        ExperimentStatusType::INPROGRESS,
+        &variants,
+    );
+    experiment.traffic_percentage = 20;
+    experiment.end_at = Some(Utc::now() - Duration::hours(1));
+
+    assert!(helpers::validate_ramp_request(&experiment, 30).is_err());
+    assert!(helpers::validate_ramp_request(&experiment, 0).is_ok());
+}
+
+#[test]
+fn test_experiment_layers() {
+    let layer = String::from("checkout");
+    assert!(helpers::validate_experiment_layer(Some(&layer), Some(40)).is_ok());
+    assert!(helpers::validate_experiment_layer(None, None).is_ok());
+    assert!(helpers::validate_experiment_layer(Some(&layer), None).is_err());
+    assert!(helpers::validate_experiment_layer(None, Some(40)).is_err());
+    assert!(helpers::validate_experiment_layer(Some(&layer), Some(0)).is_err());
+    assert!(helpers::validate_experiment_layer(Some(&layer), Some(120)).is_err());
+
+    // 2 variants sharing the 40% of the layer allotted to the experiment
+    let variants = json!([
+        {"id": "123456789-control", "variant_type": "CONTROL", "overrides": {"key1": 1}},
+        {"id": "123456789-test", "variant_type": "EXPERIMENTAL", "overrides": {"key1": 2}},
+    ]);
+    let mut experiment = experiment_gen(
+        &vec!["key1".to_string()],
+        &single_dimension_ctx_gen(Dimensions::OS("android".to_string())),
+        ExperimentStatusType::CREATED,
+        &variants,
+    );
+    experiment.layer = Some(layer);
+    experiment.layer_offset = Some(20);
+    experiment.layer_allocation = Some(40);
+
+    assert!(helpers::validate_ramp_request(&experiment, 20).is_ok());
+    assert!(helpers::validate_ramp_request(&experiment, 21).is_err());
+}
+
+#[test]
+fn test_validate_holdout_request() {
+    let holdout_req = |percentage, layer: Option<&str>| HoldoutPutRequest {
+        percentage,
+        layer: layer.map(String::from),
+        enabled: None,
+    };
+    assert!(validate_holdout_request("global", &holdout_req(5, None)).is_ok());
+    assert!(
+        validate_holdout_request("checkout", &holdout_req(10, Some("checkout"))).is_ok()
+    );
+    assert!(validate_holdout_request("", &holdout_req(5, None)).is_err());
+    assert!(validate_holdout_request("global", &holdout_req(101, None)).is_err());
+    assert!(validate_holdout_request("checkout", &holdout_req(10, Some(" "))).is_err());
+}
+
+#[test]
+fn test_thompson_sampling_weights() {
+    let mut rng = StdRng::seed_from_u64(42);
+
+    // (trials, successes) of control and 2 experimental variants
+    let arms = [(1000, 100), (1000, 300), (1000, 110)];
+    let weights = thompson_sampling_weights(&arms, 1000, 5, &mut rng);
+    assert_eq!(weights.iter().map(|w| *w as u32).sum::<u32>(), 100);
+    assert_eq!(weights[1], 90);
+    assert_eq!(weights[0], 5);
+    assert_eq!(weights[2], 5);
+
+    // without any data every arm is equally likely to be the best
+    let weights = thompson_sampling_weights(&[(0, 0), (0, 0)], 1000, 5, &mut rng);
+    assert_eq!(weights.iter().map(|w| *w as u32).sum::<u32>(), 100);
+    assert!(weights.iter().all(|w| (40..=60).contains(w)));
+
+    // the floor is capped so that it can be given to every arm
+    let weights = thompson_sampling_weights(&[(10, 10); 3], 10, 50, &mut rng);
+    assert_eq!(weights.iter().map(|w| *w as u32).sum::<u32>(), 100);
+    assert!(weights.iter().all(|w| *w >= 33));
+}
+
+#[test]
+fn test_experiment_history_entry() {
+    let event_log =
+        |action: &str, original_data: Option<Value>, new_data: Option<Value>| EventLog {
+            id: uuid::Uuid::new_v4(),
+            table_name: "experiments".to_string(),
+            user_name: "postgres".to_string(),
+            timestamp: Utc::now().naive_utc(),
+            action: action.to_string(),
+            original_data,
+            new_data,
+            query: String::new(),
+        };
+    let variants = |test_value: i64| {
+        json!([
+            {"id": "123-control", "variant_type": "CONTROL", "overrides": {"key1": 1}},
+            {"id": "123-test", "variant_type": "EXPERIMENTAL", "overrides": {"key1": test_value}},
+        ])
+    };
+    let created = json!({
+        "id": 123,
+        "traffic_percentage": 0,
+        "status": "CREATED",
+        "variants": variants(2),
+        "description": null,
+        "last_modified": "2024-03-18T10:00:00",
+        "last_modified_by": "creator@example.com",
+    });
+    let mut ramped = created.clone();
+    ramped["traffic_percentage"] = json!(20);
+    ramped["status"] = json!("INPROGRESS");
+    ramped["last_modified"] = json!("2024-03-18T11:00:00");
+    ramped["last_modified_by"] = json!("ramper@example.com");
+    let mut edited = ramped.clone();
+    edited["variants"] = variants(3);
+    let mut concluded = edited.clone();
+    concluded["traffic_percentage"] = json!(0);
+    concluded["status"] = json!("CONCLUDED");
+
+    let entry = helpers::experiment_history_entry(event_log(
+        "INSERT",
+        None,
+        Some(created.clone()),
+    ));
+    assert_eq!(entry.event_type, HistoryEventType::Created);
+    assert_eq!(entry.changed_by, "creator@example.com");
+    let fields = entry
+        .diffs
+        .iter()
+        .map(|d| d.field.as_str())
+        .collect::<Vec<&str>>();
+    assert_eq!(
+        fields,
+        vec!["id", "status", "traffic_percentage", "variants"]
+    );
+
+    let entry = helpers::experiment_history_entry(event_log(
+        "UPDATE",
+        Some(created),
+        Some(ramped.clone()),
+    ));
+    assert_eq!(entry.event_type, HistoryEventType::Ramped);
+    assert_eq!(entry.changed_by, "ramper@example.com");
+    assert_eq!(entry.diffs.len(), 2);
+    assert_eq!(entry.diffs[1].field, "traffic_percentage");
+    assert_eq!(entry.diffs[1].old_value, Some(json!(0)));
+    assert_eq!(entry.diffs[1].new_value, Some(json!(20)));
+
+    let entry = helpers::experiment_history_entry(event_log(
+        "UPDATE",
+        Some(ramped),
+        Some(edited.clone()),
+    ));
+    assert_eq!(entry.event_type, HistoryEventType::OverridesUpdated);
+    assert_eq!(entry.diffs.len(), 1);
+    assert_eq!(entry.diffs[0].field, "variants[123-test].overrides.key1");
+
+    let entry = helpers::experiment_history_entry(event_log(
+        "UPDATE",
+        Some(edited),
+        Some(concluded),
+    ));
+    assert_eq!(entry.event_type, HistoryEventType::Concluded);
+}
+
+#[test]
+fn test_clone_create_request() {
+    let variants = json!([
+        {"id": "123456789-control", "variant_type": "CONTROL", "context_id": "ctx1", "override_id": "ovr1", "overrides": {"key1": 1}},
+        {"id": "123456789-test", "variant_type": "EXPERIMENTAL", "context_id": "ctx2", "override_id": "ovr2", "overrides": {"key1": 2}, "weight": 80},
+    ]);
+    let mut experiment = experiment_gen(
+        &vec!["key1".to_string()],
+        &single_dimension_ctx_gen(Dimensions::OS("android".to_string())),
+        ExperimentStatusType::CONCLUDED,
+        &variants,
+    );
+    experiment.end_at = Some(Utc::now() - Duration::days(1));
+    experiment.layer = Some("checkout".to_string());
+    experiment.layer_allocation = Some(40);
+
+    let req = helpers::clone_create_request(
+        experiment.clone(),
+        ExperimentCloneRequest {
+            name: None,
+            context: None,
+        },
+    )
+    .unwrap();
+    assert_eq!(req.name, "experiment-test (copy)");
+    assert_eq!(req.context, experiment.context);
+    assert_eq!(req.end_at, None);
+    assert_eq!(req.layer.as_deref(), Some("checkout"));
+    assert_eq!(req.layer_allocation, Some(40));
+    let ids = req
+        .variants
+        .iter()
+        .map(|v| v.id.as_str())
+        .collect::<Vec<&str>>();
+    assert_eq!(ids, vec!["control", "test"]);
+    assert!(req.variants.iter().all(|v| v.context_id.is_none()
+        && v.override_id.is_none()
+        && v.weight.is_none()));
+    assert!(helpers::validate_variants(&req.variants).is_ok());
+
+    let context = single_dimension_ctx_gen(Dimensions::OS("ios".to_string()));
+    let req = helpers::clone_create_request(
+        experiment,
+        ExperimentCloneRequest {
+            name: Some("rerun".to_string()),
+            context: Some(context.clone()),
+        },
+    )
+    .unwrap();
+    assert_eq!(req.name, "rerun");
+    assert_eq!(req.context, context);
+}
@@ -1,9 +1,10 @@
 use leptos::ServerFnError;
+use url::form_urlencoded;
 
 use crate::{
     types::{
-        Config, DefaultConfig, Dimension, Experiment, ExperimentsResponse,
-        FunctionResponse, ListFilters,
+        Config, DefaultConfig, Dimension, Experiment, ExperimentHistory,
+        ExperimentTemplate, ExperimentsResponse, FunctionResponse, ListFilters,
     },
     utils::use_host_server,
 };
@@ -94,6 +95,30 @@ pub async fn fetch_experiments(
     if let Some(to_date) = filters.to_date {
         query_params.push(format!("to_date={}", to_date));
     }
+    let encode = |value: &str| {
+        form_urlencoded::byte_serialize(value.as_bytes()).collect::<String>()
+    };
+    if let Some(name) = filters.experiment_name {
+        query_params.push(format!("experiment_name={}", encode(&name)));
+    }
+    if let Some(ids) = filters.experiment_ids {
+        query_params.push(format!("experiment_ids={}", encode(&ids.join(","))));
+    }
+    if let Some(created_by) = filters.created_by {
+        query_params.push(format!("created_by={}", encode(&created_by)));
+    }
+    if let Some(keys) = filters.override_keys {
+        query_params.push(format!("override_keys={}", encode(&keys.join(","))));
+    }
+    if let Some(dimensions) = filters.dimensions {
+        query_params.push(format!("dimensions={}", encode(&dimensions.join(","))));
+    }
+    if let Some(sort_on) = filters.sort_on {
+        query_params.push(format!("sort_on={}", sort_on));
+    }
+    if let Some(sort_by) = filters.sort_by {
+        query_params.push(format!("sort_by={}", sort_by));
+    }
     if let Some(page) = filters.page {
         query_params.push(format!("page={}", page));
     }
@@ -194,3 +219,42 @@ pub async fn fetch_experiment(
         Err(e) => Err(ServerFnError::ServerError(e.to_string())),
     }
 }
+
+pub async fn fetch_experiment_history(
+    exp_id: String,
+    tenant: String,
+) -> Result<ExperimentHistory, ServerFnError> {
+    let client = reqwest::Client::new();
+    let host = use_host_server();
+    let url = format!("{}/experiments/{}/history", host, exp_id);
+
+    match client.get(url).header("x-tenant", tenant).send().await {
+        Ok(history) => {
+            let history = history
+                .json()
+                .await
+                .map_err(|err| ServerFnError::ServerError(err.to_string()))?;
+            Ok(history)
+        }
+        Err(e) => Err(ServerFnError::ServerError(e.to_string())),
+    }
+}
+
+pub async fn fetch_experiment_templates(
+    tenant: String,
+) -> Result<Vec<ExperimentTemplate>, ServerFnError> {
+    let client = reqwest::Client::new();
+    let host = use_host_server();
+    let url = format!("{}/experiments/templates", host);
+
+    match client.get(url).header("x-tenant", tenant).send().await {
+        Ok(templates) => {
+            let templates = templates
+                .json()
+                .await
+                .map_err(|err| ServerFnError::ServerError(err.to_string()))?;
+            Ok(templates)
+        }
+        Err(e) => Err(ServerFnError::ServerError(e.to_string())),
+    }
+}
@@ -1,17 +1,27 @@
-use crate::components::condition_pills::types::ConditionOperator;
+use crate::components::condition_pills::types::{Combinator, ConditionOperator};
 
 use super::types::Condition;
 use leptos::*;
 
 #[component]
-pub fn condition_pills(#[prop(into)] conditions: Vec<Condition>) -> impl IntoView {
+pub fn condition_pills(
+    #[prop(into)] conditions: Vec<Condition>,
+    #[prop(default = Combinator::And)] combinator: Combinator,
+) -> impl IntoView {
+    let last_idx = conditions.len().max(1) - 1;
     view! {
         {conditions
             .into_iter()
-            .map(|condition| {
+            .enumerate()
+            .map(|(idx, condition)| {
                 let dimension = condition.left_operand;
                 let op = condition.operator;
                 let val = condition.right_operand;
+                let is_between = match &op {
+                    ConditionOperator::Between => true,
+                    ConditionOperator::Not(op) => **op == ConditionOperator::Between,
+                    _ => false,
+                };
                 view! {
                     <span class="inline-flex items-center rounded-md bg-gray-50 px-2 py-1 text-xs ring-1 ring-inset ring-purple-700/10 shadow-md gap-x-2">
                         <span class="font-mono font-medium context_condition text-gray-500">
@@ -21,8 +31,8 @@ pub fn condition_pills(#[prop(into)] conditions: Vec<Condition>) -> impl IntoVie
                             {op.to_string()}
                         </span>
 
-                        {match op {
-                            ConditionOperator::Between => {
+                        {match is_between {
+                            true => {
                                 let split_val: Vec<String> = val
                                     .clone()
                                     .split(",")
@@ -42,7 +52,7 @@ pub fn condition_pills(#[prop(into)] conditions: Vec<Condition>) -> impl IntoVie
                                     </>
                                 }
                             }
-                            _ => {
+                            false => {
                                 view! {
                                     <>
                                         <span class="font-mono font-semibold context_condition">
@@ -54,6 +64,13 @@ pub fn condition_pills(#[prop(into)] conditions: Vec<Condition>) -> impl IntoVie
                         }}
 
                     </span>
+                    // conditions are implicitly joined by `&&`, so only `||` is spelled out
+                    {(combinator == Combinator::Or && idx != last_idx)
+                        .then(|| {
+                            view! {
+                                <span class="font-mono text-xs mx-1">{combinator.to_string()}</span>
+                            }
+                        })}
                 }
             })
             .collect::<Vec<_>>()}
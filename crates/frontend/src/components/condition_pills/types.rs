@@ -4,12 +4,45 @@ use serde_json::{Map, Value};
 
 use crate::types::Context;
 
-#[derive(Debug, Clone)]
+/// How the conditions of a context are joined together.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Combinator {
+    #[default]
+    And,
+    Or,
+}
+
+impl Display for Combinator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::And => f.write_str("&&"),
+            Self::Or => f.write_str("||"),
+        }
+    }
+}
+
+impl From<&Value> for Combinator {
+    fn from(context: &Value) -> Self {
+        match context.as_object() {
+            Some(obj) if obj.contains_key("or") => Combinator::Or,
+            _ => Combinator::And,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum ConditionOperator {
     Is,
+    IsNot,
+    LessThan,
+    LessThanOrEqual,
+    GreaterThan,
+    GreaterThanOrEqual,
     In,
+    NotIn,
     Has,
     Between,
+    Not(Box<ConditionOperator>),
     Other(String),
 }
 
@@ -18,8 +51,15 @@ impl Display for ConditionOperator {
         match self {
             Self::Has => f.write_str("has"),
             Self::Is => f.write_str("is"),
+            Self::IsNot => f.write_str("is not"),
+            Self::LessThan => f.write_str("<"),
+            Self::LessThanOrEqual => f.write_str("<="),
+            Self::GreaterThan => f.write_str(">"),
+            Self::GreaterThanOrEqual => f.write_str(">="),
             Self::In => f.write_str("in"),
+            Self::NotIn => f.write_str("not in"),
             Self::Between => f.write_str("between"),
+            Self::Not(operator) => write!(f, "not {operator}"),
             Self::Other(o) => f.write_str(o),
         }
     }
@@ -34,6 +74,32 @@ impl From<(String, &Vec<Value>)> for ConditionOperator {
         match (operator.as_str(), operand_0, operand_1, operand_2) {
             // assuming there will be only two operands, one with the dimension name and other with the value
             ("==", _, _, None) => ConditionOperator::Is,
+            ("!=", _, _, None) => ConditionOperator::IsNot,
+            // comparisons with the dimension on the right are flipped around
+            ("<", Some(Value::Object(a)), Some(_), None)
+            | (">", Some(_), Some(Value::Object(a)), None)
+                if a.contains_key("var") =>
+            {
+                ConditionOperator::LessThan
+            }
+            ("<=", Some(Value::Object(a)), Some(_), None)
+            | (">=", Some(_), Some(Value::Object(a)), None)
+                if a.contains_key("var") =>
+            {
+                ConditionOperator::LessThanOrEqual
+            }
+            (">", Some(Value::Object(a)), Some(_), None)
+            | ("<", Some(_), Some(Value::Object(a)), None)
+                if a.contains_key("var") =>
+            {
+                ConditionOperator::GreaterThan
+            }
+            (">=", Some(Value::Object(a)), Some(_), None)
+            | ("<=", Some(_), Some(Value::Object(a)), None)
+                if a.contains_key("var") =>
+            {
+                ConditionOperator::GreaterThanOrEqual
+            }
             ("<=", Some(_), Some(Value::Object(a)), Some(_)) if a.contains_key("var") => {
                 ConditionOperator::Between
             }
@@ -60,6 +126,24 @@ pub struct Condition {
 impl TryFrom<&Map<String, Value>> for Condition {
     type Error = &'static str;
     fn try_from(source: &Map<String, Value>) -> Result<Self, Self::Error> {
+        if let Some(Value::Object(negated)) = source.get("!").map(|negated| {
+            // the negated condition may also be wrapped in an array
+            negated
+                .as_array()
+                .and_then(|args| args.first())
+                .unwrap_or(negated)
+        }) {
+            let condition = Condition::try_from(negated)?;
+            let operator = match condition.operator {
+                ConditionOperator::In => ConditionOperator::NotIn,
+                operator => ConditionOperator::Not(Box::new(operator)),
+            };
+            return Ok(Condition {
+                operator,
+                ..condition
+            });
+        }
+
         if let Some(operator) = source.keys().next() {
             let emty_vec = vec![];
             let operands = source[operator].as_array().unwrap_or(&emty_vec);
@@ -121,10 +205,10 @@ impl TryFrom<&Context> for Vec<Condition> {
             .condition
             .as_object()
             .ok_or("failed to parse context.condition as an object")
-            .and_then(|obj| match obj.get("and") {
+            .and_then(|obj| match obj.get("and").or_else(|| obj.get("or")) {
                 Some(v) => v
                     .as_array()
-                    .ok_or("failed to parse value of and as array")
+                    .ok_or("failed to parse value of and/or as array")
                     .and_then(|arr| {
                         arr.iter()
                             .map(|condition| Condition::try_from(condition))
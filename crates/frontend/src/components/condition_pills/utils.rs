@@ -6,6 +6,7 @@ pub fn extract_conditions(context: &Value) -> Vec<Condition> {
         .as_object()
         .and_then(|obj| {
             obj.get("and")
+                .or_else(|| obj.get("or"))
                 .and_then(|v| v.as_array())
                 .and_then(|arr| {
                     Some(
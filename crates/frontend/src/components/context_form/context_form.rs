@@ -1,3 +1,4 @@
+use crate::components::condition_pills::types::Combinator;
 use crate::components::dropdown::dropdown::{Dropdown, DropdownDirection};
 use crate::types::Dimension;
 use leptos::*;
@@ -14,6 +15,12 @@ pub fn context_form<NF>(
     #[prop(default = false)] disabled: bool,
     #[prop(default = DropdownDirection::Right)] dropdown_direction: DropdownDirection,
     #[prop(default = false)] resolve_mode: bool,
+    // lets conditions be negated and joined by `||` instead of `&&`
+    #[prop(default = false)] allow_combinators: bool,
+    #[prop(default = Combinator::And)] combinator: Combinator,
+    #[prop(into, default = None)] handle_combinator_change: Option<
+        Callback<Combinator, ()>,
+    >,
 ) -> impl IntoView
 where
     NF: Fn(Vec<(String, String, String)>) + 'static,
@@ -22,6 +29,7 @@ where
 
     let (context, set_context) = create_signal(context.clone());
     let (used_dimensions, set_used_dimensions) = create_signal(HashSet::new());
+    let (combinator, set_combinator) = create_signal(combinator);
 
     let dimensions = StoredValue::new(dimensions);
 
@@ -39,6 +47,13 @@ where
         handle_change(f_context.clone());
     });
 
+    create_effect(move |_| {
+        let f_combinator = combinator.get();
+        if let Some(handle_combinator_change) = handle_combinator_change {
+            handle_combinator_change.call(f_combinator);
+        }
+    });
+
     let handle_select_dropdown_option = move |selected_dimension: Dimension| {
         let dimension_name = selected_dimension.dimension;
         set_context.update(|value| {
@@ -61,6 +76,33 @@ where
                 </div>
                 <div class="card w-full bg-slate-50">
                     <div class="card-body">
+                        <Show when=move || allow_combinators>
+                            <div class="form-control w-60">
+                                <label class="label font-mono text-sm">
+                                    <span class="label-text">Match</span>
+                                </label>
+                                <select
+                                    disabled=disabled
+                                    on:input=move |event| {
+                                        let combinator = match event_target_value(&event).as_str() {
+                                            "or" => Combinator::Or,
+                                            _ => Combinator::And,
+                                        };
+                                        set_combinator.set(combinator);
+                                    }
+
+                                    name="context-combinator"
+                                    class="select select-bordered w-full max-w-xs text-sm rounded-lg h-10 px-4 appearance-none leading-tight focus:outline-none focus:shadow-outline"
+                                >
+                                    <option value="and" selected=combinator.get_untracked() == Combinator::And>
+                                        "ALL of the conditions"
+                                    </option>
+                                    <option value="or" selected=combinator.get_untracked() == Combinator::Or>
+                                        "ANY of the conditions"
+                                    </option>
+                                </select>
+                            </div>
+                        </Show>
                         <Show when=move || context.get().len() == 0>
                             <div class="flex justify-center">
                                 <Dropdown
@@ -134,6 +176,17 @@ where
                                                 <option value="<=" selected=operator.clone() == "<=">
                                                     "BETWEEN (inclusive)"
                                                 </option>
+                                                <Show when=move || allow_combinators>
+                                                    <option value="!=" selected=operator.clone() == "!=">
+                                                        "IS NOT"
+                                                    </option>
+                                                    <option value="!in" selected=operator.clone() == "!in">
+                                                        "NOT HAS"
+                                                    </option>
+                                                    <option value="!<=" selected=operator.clone() == "!<=">
+                                                        "NOT BETWEEN (inclusive)"
+                                                    </option>
+                                                </Show>
                                             </select>
 
                                         </div>
@@ -184,7 +237,9 @@ where
                                         if last_idx.get() != idx {
                                             view! {
                                                 <div class="my-3 ml-5 ml-6 ml-7">
-                                                    <span class="font-mono text-xs">"&&"</span>
+                                                    <span class="font-mono text-xs">
+                                                        {move || combinator.get().to_string()}
+                                                    </span>
                                                 </div>
                                             }
                                                 .into_view()
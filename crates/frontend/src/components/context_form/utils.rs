@@ -1,3 +1,4 @@
+use crate::components::condition_pills::types::Combinator;
 use crate::types::Dimension;
 use crate::utils::{
     construct_request_headers, get_config_value, get_host, request, ConfigType,
@@ -11,6 +12,12 @@ pub fn get_condition_schema(
     val: &str,
     dimensions: Vec<Dimension>,
 ) -> Result<Value, String> {
+    // negated operators, other than `!=`, are prefixed with a `!`
+    if let Some(negated_op) = op.strip_prefix('!').filter(|op| *op != "=") {
+        let condition = get_condition_schema(var, negated_op, val, dimensions)?;
+        return Ok(json!({ "!": condition }));
+    }
+
     match op {
         "<=" => {
             let mut split_value = val.split(',');
@@ -56,6 +63,7 @@ pub fn get_condition_schema(
 
 pub fn construct_context(
     conditions: Vec<(String, String, String)>,
+    combinator: Combinator,
     dimensions: Vec<Dimension>,
 ) -> Value {
     let condition_schemas = conditions
@@ -65,10 +73,10 @@ pub fn construct_context(
         })
         .collect::<Vec<Value>>();
 
-    let context = if condition_schemas.len() == 1 {
-        condition_schemas[0].clone()
-    } else {
-        json!({ "and": condition_schemas })
+    let context = match (condition_schemas.len(), combinator) {
+        (1, _) => condition_schemas[0].clone(),
+        (_, Combinator::And) => json!({ "and": condition_schemas }),
+        (_, Combinator::Or) => json!({ "or": condition_schemas }),
     };
 
     context
@@ -77,13 +85,14 @@ pub fn construct_context(
 pub fn construct_request_payload(
     overrides: Map<String, Value>,
     conditions: Vec<(String, String, String)>,
+    combinator: Combinator,
     dimensions: Vec<Dimension>,
 ) -> Value {
     // Construct the override section
     let override_section: Map<String, Value> = overrides;
 
     // Construct the context section
-    let context_section = construct_context(conditions, dimensions);
+    let context_section = construct_context(conditions, combinator, dimensions);
 
     // Construct the entire request payload
     let request_payload = json!({
@@ -98,11 +107,13 @@ pub async fn create_context(
     tenant: String,
     overrides: Map<String, Value>,
     conditions: Vec<(String, String, String)>,
+    combinator: Combinator,
     dimensions: Vec<Dimension>,
 ) -> Result<serde_json::Value, String> {
     let host = get_host();
     let url = format!("{host}/context");
-    let request_payload = construct_request_payload(overrides, conditions, dimensions);
+    let request_payload =
+        construct_request_payload(overrides, conditions, combinator, dimensions);
     request(
         url,
         reqwest::Method::PUT,
@@ -116,11 +127,13 @@ pub async fn update_context(
     tenant: String,
     overrides: Map<String, Value>,
     conditions: Vec<(String, String, String)>,
+    combinator: Combinator,
     dimensions: Vec<Dimension>,
 ) -> Result<serde_json::Value, String> {
     let host = get_host();
     let url = format!("{host}/context/overrides");
-    let request_payload = construct_request_payload(overrides, conditions, dimensions);
+    let request_payload =
+        construct_request_payload(overrides, conditions, combinator, dimensions);
     request(
         url,
         reqwest::Method::PUT,
@@ -1,5 +1,6 @@
 use std::rc::Rc;
 
+use chrono::{DateTime, Utc};
 use leptos::*;
 
 use crate::components::condition_pills::utils::extract_conditions;
@@ -8,6 +9,11 @@ use crate::components::table::table::Table;
 use super::utils::gen_variant_table;
 use crate::types::{Experiment, ExperimentStatusType};
 
+fn schedule_time(time: Option<DateTime<Utc>>) -> String {
+    time.map(|time| time.format("%v %R UTC").to_string())
+        .unwrap_or(String::from("-"))
+}
+
 #[component]
 pub fn experiment<HS, HR, HC, HE>(
     experiment: Experiment,
@@ -24,6 +30,7 @@ where
 {
     let experiment_rc = Rc::new(experiment.clone());
     let contexts = extract_conditions(&experiment_rc.clone().context);
+    let has_schedule = experiment.start_at.is_some() || experiment.end_at.is_some();
 
     view! {
         <div class="flex flex-col overflow-x-auto p-2 bg-transparent">
@@ -151,7 +158,99 @@ where
 
                     </div>
                 </div>
-            </div> <div class="card bg-base-100 max-w-screen shadow m-5">
+            </div>
+            <Show when=move || has_schedule>
+                <div class="flex bg-base-100 flex-row gap-2 flex-wrap shadow m-5">
+                    <div class="stat w-3/12">
+                        <div class="stat-title">Starts at</div>
+                        <div class="stat-value text-sm">
+                            {schedule_time(experiment.start_at)}
+                        </div>
+                    </div>
+                    <div class="stat w-3/12">
+                        <div class="stat-title">Ends at</div>
+                        <div class="stat-value text-sm">
+                            {schedule_time(experiment.end_at)}
+                        </div>
+                    </div>
+                    <div class="stat w-3/12">
+                        <div class="stat-title">Fallback Variant</div>
+                        <div class="stat-value text-sm">
+                            {experiment.fallback_variant.clone().unwrap_or(String::from("-"))}
+                        </div>
+                    </div>
+                </div>
+            </Show>
+            <div class="card bg-base-100 max-w-screen shadow m-5">
+                <div class="card-body">
+                    <h2 class="card-title">About</h2>
+                    <div class="flex flex-row flex-wrap gap-2">
+                        <div class="stat w-5/12">
+                            <div class="stat-title">Description</div>
+                            <div class="stat-value text-sm whitespace-pre-wrap">
+                                {experiment.description.clone().unwrap_or(String::from("-"))}
+                            </div>
+                        </div>
+                        <div class="stat w-5/12">
+                            <div class="stat-title">Hypothesis</div>
+                            <div class="stat-value text-sm whitespace-pre-wrap">
+                                {experiment.hypothesis.clone().unwrap_or(String::from("-"))}
+                            </div>
+                        </div>
+                        <div class="stat w-3/12">
+                            <div class="stat-title">Layer</div>
+                            <div class="stat-value text-sm">
+                                {match (&experiment.layer, experiment.layer_offset, experiment.layer_allocation) {
+                                    (Some(layer), Some(offset), Some(allocation)) => {
+                                        format!("{} ({}% - {}%)", layer, offset, offset + allocation)
+                                    }
+                                    _ => String::from("-"),
+                                }}
+
+                            </div>
+                        </div>
+                        <div class="stat w-3/12">
+                            <div class="stat-title">Owner Team</div>
+                            <div class="stat-value text-sm">
+                                {experiment.owner_team.clone().unwrap_or(String::from("-"))}
+                            </div>
+                        </div>
+                        <div class="stat w-4/12">
+                            <div class="stat-title">Tags</div>
+                            <div class="flex flex-row flex-wrap gap-1 pt-1">
+                                {experiment
+                                    .tags
+                                    .iter()
+                                    .map(|tag| {
+                                        view! { <span class="badge badge-ghost">{tag}</span> }
+                                    })
+                                    .collect_view()}
+                            </div>
+                        </div>
+                        <div class="stat w-4/12">
+                            <div class="stat-title">Links</div>
+                            <div class="flex flex-col gap-1 pt-1">
+                                {experiment
+                                    .external_links
+                                    .iter()
+                                    .map(|link| {
+                                        view! {
+                                            <a
+                                                href=link
+                                                target="_blank"
+                                                class="link link-primary text-sm truncate"
+                                            >
+                                                {link}
+                                            </a>
+                                        }
+                                    })
+                                    .collect_view()}
+                            </div>
+                        </div>
+                    </div>
+                </div>
+            </div>
+            <div class="card bg-base-100 max-w-screen shadow m-5">
                 <div class="card-body">
                     <h2 class="card-title">Context</h2>
                     <div class="flex flex-row flex-wrap gap-2">
@@ -0,0 +1,91 @@
+use leptos::*;
+use serde_json::Value;
+
+use crate::types::{HistoryEntry, HistoryEventType};
+
+fn diff_value(value: &Option<Value>) -> String {
+    match value {
+        Some(Value::String(s)) => s.clone(),
+        Some(value) => value.to_string(),
+        None => String::from("-"),
+    }
+}
+
+#[component]
+pub fn experiment_history(history: Vec<HistoryEntry>) -> impl IntoView {
+    view! {
+        <div class="card bg-base-100 max-w-screen shadow m-5">
+            <div class="card-body">
+                <h2 class="card-title">History</h2>
+                <Show
+                    when={
+                        let is_empty = history.is_empty();
+                        move || !is_empty
+                    }
+
+                    fallback=|| view! { <span class="text-sm">No changes recorded</span> }
+                >
+                    <ul class="flex flex-col gap-4">
+                        {history
+                            .iter()
+                            .rev()
+                            .map(|entry| {
+                                let badge_class = match entry.event_type {
+                                    HistoryEventType::Created => "badge badge-info text-white",
+                                    HistoryEventType::Ramped => "badge badge-warning text-white",
+                                    HistoryEventType::Concluded => "badge badge-success text-white",
+                                    HistoryEventType::Deleted => "badge badge-error text-white",
+                                    HistoryEventType::OverridesUpdated
+                                    | HistoryEventType::Updated => "badge badge-ghost",
+                                };
+                                view! {
+                                    <li class="border-l-2 border-purple-500 pl-4">
+                                        <div class="flex flex-row items-center gap-2">
+                                            <span class=badge_class>
+                                                {entry.event_type.to_string()}
+                                            </span>
+                                            <span class="text-sm font-semibold">
+                                                {entry.changed_by.clone()}
+                                            </span>
+                                            <span class="text-sm text-gray-500">
+                                                {format!("{}", entry.timestamp.format("%v %R UTC"))}
+                                            </span>
+                                        </div>
+                                        <table class="table table-xs mt-2">
+                                            <thead>
+                                                <tr>
+                                                    <th>Field</th>
+                                                    <th>Before</th>
+                                                    <th>After</th>
+                                                </tr>
+                                            </thead>
+                                            <tbody>
+                                                {entry
+                                                    .diffs
+                                                    .iter()
+                                                    .map(|diff| {
+                                                        view! {
+                                                            <tr>
+                                                                <td class="font-mono">{diff.field.clone()}</td>
+                                                                <td class="font-mono text-red-600 break-all">
+                                                                    {diff_value(&diff.old_value)}
+                                                                </td>
+                                                                <td class="font-mono text-green-600 break-all">
+                                                                    {diff_value(&diff.new_value)}
+                                                                </td>
+                                                            </tr>
+                                                        }
+                                                    })
+                                                    .collect_view()}
+                                            </tbody>
+                                        </table>
+                                    </li>
+                                }
+                            })
+                            .collect_view()}
+                    </ul>
+                </Show>
+            </div>
+        </div>
+    }
+}
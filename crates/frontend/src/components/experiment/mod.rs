@@ -1,2 +1,3 @@
 pub mod experiment;
+pub mod history;
 pub mod utils;
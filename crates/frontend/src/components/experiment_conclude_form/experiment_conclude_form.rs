@@ -14,6 +14,7 @@ where
 {
     let tenant_rs = use_context::<ReadSignal<String>>().unwrap();
     let experiment_rc = Rc::new(experiment);
+    let (promote_to_default_rs, promote_to_default_ws) = create_signal(false);
 
     let experiment_clone = experiment_rc.clone();
     let handle_conclude_experiment = move |variant_id: String| {
@@ -21,8 +22,13 @@ where
         spawn_local(async move {
             let experiment = experiment_clone.clone();
             let tenant = tenant_rs.get();
-            let _ =
-                conclude_experiment(experiment.id.to_string(), variant_id, &tenant).await;
+            let _ = conclude_experiment(
+                experiment.id.to_string(),
+                variant_id,
+                promote_to_default_rs.get_untracked(),
+                &tenant,
+            )
+            .await;
             handle_submit_clone();
         })
     };
@@ -33,6 +39,19 @@ where
             Choose a variant to conclude with, this variant becomes
             the new default that is served to requests that match this context
         </p>
+        <div class="form-control">
+            <label class="label cursor-pointer">
+                <span class="label-text">
+                    Promote the chosen variant to the default config for all requests
+                </span>
+                <input
+                    type="checkbox"
+                    class="toggle toggle-primary"
+                    prop:checked=move || promote_to_default_rs.get()
+                    on:change=move |ev| promote_to_default_ws.set(event_target_checked(&ev))
+                />
+            </label>
+        </div>
         <form method="dialog">
             <For
                 each=move || {
@@ -6,6 +6,7 @@ use crate::{types::Experiment, utils::get_host};
 pub async fn conclude_experiment(
     exp_id: String,
     variant_id: String,
+    promote_to_default: bool,
     tenant: &String,
 ) -> Result<Experiment, String> {
     let client = reqwest::Client::new();
@@ -13,7 +14,10 @@ pub async fn conclude_experiment(
     match client
         .patch(format!("{host}/experiments/{}/conclude", exp_id))
         .header("x-tenant", tenant)
-        .json(&json!({ "chosen_variant": variant_id }))
+        .json(&json!({
+            "chosen_variant": variant_id,
+            "promote_to_default": promote_to_default
+        }))
         .send()
         .await
     {
@@ -1,8 +1,10 @@
 use super::utils::{create_experiment, update_experiment};
+use crate::api::fetch_experiment_templates;
 use crate::components::button::button::Button;
 use crate::components::context_form::context_form::ContextForm;
+use crate::components::dropdown::dropdown::{Dropdown, DropdownDirection};
 use crate::components::variant_form::variant_form::VariantForm;
-use crate::types::{DefaultConfig, Dimension, Variant, VariantType};
+use crate::types::{DefaultConfig, Dimension, ExperimentTemplate, Variant, VariantType};
 use leptos::*;
 use serde_json::Map;
 use web_sys::MouseEvent;
@@ -75,6 +77,21 @@ where
         set_variants.set_untracked(updated_varaints);
     };
 
+    let templates = create_local_resource(
+        move || tenant_rs.get(),
+        |tenant| async move { fetch_experiment_templates(tenant).await.unwrap_or_default() },
+    );
+    // replaces the variants being edited with the ones saved in the template
+    let handle_template_select = move |template: ExperimentTemplate| {
+        set_variants.set(
+            template
+                .variants
+                .into_iter()
+                .map(|variant| (variant.id.to_string(), variant))
+                .collect(),
+        );
+    };
+
     let dimensions = StoredValue::new(dimensions);
     let on_submit = move |event: MouseEvent| {
         event.prevent_default();
@@ -164,6 +181,20 @@ where
 
             <div class="divider"></div>
 
+            <Show when=move || {
+                !edit && templates.get().is_some_and(|templates| !templates.is_empty())
+            }>
+                <div class="flex justify-end">
+                    <Dropdown
+                        dropdown_direction=DropdownDirection::Left
+                        dropdown_text=String::from("Start from a template")
+                        dropdown_icon=String::from("ri-file-copy-line")
+                        dropdown_options=templates.get().unwrap_or_default()
+                        on_select=Box::new(handle_template_select)
+                    />
+                </div>
+            </Show>
+
             {move || {
                 let variants = f_variants.get();
                 view! {
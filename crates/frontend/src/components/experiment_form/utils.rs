@@ -1,6 +1,7 @@
 use super::types::{
     ExperimentCreateRequest, ExperimentUpdateRequest, VariantUpdateRequest,
 };
+use crate::components::condition_pills::types::Combinator;
 use crate::components::context_form::utils::construct_context;
 use crate::types::{Dimension, Variant};
 use crate::utils::{construct_request_headers, get_host, request};
@@ -23,7 +24,8 @@ pub async fn create_experiment(
     let payload = ExperimentCreateRequest {
         name,
         variants,
-        context: construct_context(conditions, dimensions),
+        // experiments are scoped by a conjunction of dimensions
+        context: construct_context(conditions, Combinator::And, dimensions),
     };
 
     let _ = validate_experiment(&payload)?;
@@ -2,7 +2,7 @@ use crate::api::fetch_config;
 use crate::api::{delete_context, fetch_default_config, fetch_dimensions};
 use crate::components::button::button::Button;
 use crate::components::condition_pills::condition_pills::ConditionPills;
-use crate::components::condition_pills::types::Condition;
+use crate::components::condition_pills::types::{Combinator, Condition};
 use crate::components::context_form::context_form::ContextForm;
 use crate::components::context_form::utils::{create_context, update_context};
 use crate::components::drawer::drawer::{close_drawer, open_drawer, Drawer, DrawerBtn};
@@ -19,6 +19,7 @@ use serde_json::{Map, Value};
 #[derive(Clone, Debug, Default)]
 pub struct TableData {
     pub context: Vec<(String, String, String)>,
+    pub combinator: Combinator,
     pub overrides: Map<String, Value>,
 }
 
@@ -70,6 +71,7 @@ pub fn context_override() -> impl IntoView {
         edit_signal.set(false);
         form_data.set(Some(TableData {
             context: vec![],
+            combinator: Combinator::And,
             overrides: Map::new(),
         }));
     });
@@ -102,6 +104,7 @@ pub fn context_override() -> impl IntoView {
                                             tenant_rs.get().clone(),
                                             final_form_data.overrides.clone(), // Access by cloning the inner data
                                             final_form_data.context.clone(),  // Access by cloning the inner data
+                                            final_form_data.combinator,
                                             dimensions.unwrap_or_default()
                                         ).await
                                       } else {
@@ -109,6 +112,7 @@ pub fn context_override() -> impl IntoView {
                                             tenant_rs.get().clone(),
                                             final_form_data.overrides.clone(), // Access by cloning the inner data
                                             final_form_data.context.clone(),  // Access by cloning the inner data
+                                            final_form_data.combinator,
                                             dimensions.unwrap_or_default()
                                         ).await
                                       };
@@ -160,6 +164,15 @@ pub fn context_override() -> impl IntoView {
                                     <ContextForm
                                         dimensions=dimension_value.unwrap_or_default()
                                         context=form_data_untracked.context
+                                        allow_combinators=true
+                                        combinator=form_data_untracked.combinator
+                                        handle_combinator_change=Callback::new(move |new_combinator| {
+                                            form_data.update(|prev| {
+                                                if let Some(inner) = prev {
+                                                    inner.combinator = new_combinator;
+                                                }
+                                            })
+                                        })
                                         is_standalone=false
                                       handle_change=move |new_context| {
                                          form_data.update(|prev| {
@@ -196,6 +209,14 @@ pub fn context_override() -> impl IntoView {
                                     <ContextForm
                                       dimensions= dimension_value.unwrap_or_default()
                                       context=vec![]
+                                      allow_combinators=true
+                                      handle_combinator_change=Callback::new(move |new_combinator| {
+                                          form_data.update(|prev| {
+                                              if let Some(inner) = prev {
+                                                  inner.combinator = new_combinator;
+                                              }
+                                          })
+                                      })
                                       handle_change=
                                     move |new_context| {
                                         form_data.update(|prev| {
@@ -276,6 +297,7 @@ pub fn context_override() -> impl IntoView {
                                             let overrides_clone = overrides.clone();
                                             let context_data_clone_for_display = context.condition.clone();
                                             let conditions: Vec<Condition>  = context.try_into().unwrap_or(vec![]);
+                                            let combinator = Combinator::from(&context.condition);
                                             let context_data_clone_for_click = context.condition.clone();
                                             let context_id = context.id.clone();
 
@@ -292,7 +314,7 @@ pub fn context_override() -> impl IntoView {
                                                                             "Condition"
                                                                         </h3>
                                                                         <i class="ri-arrow-right-fill ri-xl text-blue-500"></i>
-                                                                        <ConditionPills conditions=conditions />
+                                                                        <ConditionPills conditions=conditions combinator=combinator />
                                                                     </div>
                                                                     <div class= "flex space-x-4">
                                                                     <i class="ri-pencil-line ri-xl text-blue-500 cursor-pointer" on:click = move |_| {
@@ -301,10 +323,12 @@ pub fn context_override() -> impl IntoView {
 
                                                                         form_data.set(Some(TableData {
                                                                             context: conditions,
+                                                                            combinator,
                                                                             overrides: override_data_for_edit.clone()
                                                                         }));
                                                                         selected_context_and_override.set(Some(TableData {
                                                                             context: vec![],
+                                                                            combinator: Combinator::And,
                                                                             overrides: override_data_for_edit.clone()
                                                                         }));
                                                                         open_drawer("context_and_override_drawer");
@@ -315,10 +339,12 @@ pub fn context_override() -> impl IntoView {
 
                                                                         form_data.set(Some(TableData {
                                                                             context: conditions,
+                                                                            combinator,
                                                                             overrides: override_data.clone()
                                                                         }));
                                                                         selected_context_and_override.set(Some(TableData {
                                                                             context: vec![],
+                                                                            combinator: Combinator::And,
                                                                             overrides: override_data.clone()
                                                                         }));
                                                                         open_drawer("context_and_override_drawer");
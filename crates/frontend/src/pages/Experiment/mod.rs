@@ -4,16 +4,19 @@ use leptos_router::use_params_map;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    api::{fetch_default_config, fetch_dimensions, fetch_experiment},
+    api::{
+        fetch_default_config, fetch_dimensions, fetch_experiment,
+        fetch_experiment_history,
+    },
     components::{
-        experiment::experiment::Experiment,
+        experiment::{experiment::Experiment, history::ExperimentHistory},
         experiment_conclude_form::experiment_conclude_form::ExperimentConcludeForm,
         experiment_form::experiment_form::ExperimentForm,
         experiment_ramp_form::utils::ramp_experiment,
         modal::modal::Modal,
         skeleton::{Skeleton, SkeletonVariant},
     },
-    types::{DefaultConfig, Dimension, Experiment},
+    types::{DefaultConfig, Dimension, Experiment, HistoryEntry},
     utils::{close_modal, extract_conditions, show_modal},
 };
 
@@ -24,6 +27,7 @@ struct CombinedResource {
     experiment: Option<Experiment>,
     dimensions: Vec<Dimension>,
     default_config: Vec<DefaultConfig>,
+    history: Vec<HistoryEntry>,
 }
 
 #[component]
@@ -44,9 +48,15 @@ pub fn experiment_page() -> impl IntoView {
                 fetch_experiment(exp_id.to_string(), tenant.to_string());
             let dimensions_future = fetch_dimensions(tenant.to_string());
             let config_future = fetch_default_config(tenant.to_string());
+            let history_future =
+                fetch_experiment_history(exp_id.to_string(), tenant.to_string());
 
-            let (experiments_result, dimensions_result, config_result) =
-                join!(experiments_future, dimensions_future, config_future);
+            let (experiments_result, dimensions_result, config_result, history_result) = join!(
+                experiments_future,
+                dimensions_future,
+                config_future,
+                history_future
+            );
 
             // Construct the combined result, handling errors as needed
             CombinedResource {
@@ -57,6 +67,9 @@ pub fn experiment_page() -> impl IntoView {
                     .filter(|d| d.dimension != "variantIds")
                     .collect(),
                 default_config: config_result.unwrap_or(vec![]),
+                history: history_result
+                    .map(|history| history.history)
+                    .unwrap_or(vec![]),
             }
         });
 
@@ -88,6 +101,7 @@ pub fn experiment_page() -> impl IntoView {
                 let experiment = resource.experiment;
                 let default_config = resource.default_config;
                 let dimensions = resource.dimensions;
+                let history = resource.history;
                 match experiment {
                     Some(experiment) => {
                         let experiment_rf = experiment.clone();
@@ -101,6 +115,7 @@ pub fn experiment_page() -> impl IntoView {
                                 handle_conclude=handle_conclude
                                 handle_edit=handle_edit
                             />
+                            <ExperimentHistory history=history/>
                             <Modal
                                 id="ramp_form_modal".to_string()
                                 handle_close=move || { close_modal("ramp_form_modal") }
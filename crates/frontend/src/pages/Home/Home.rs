@@ -1,7 +1,7 @@
 use std::time::Duration;
 
 use crate::components::condition_pills::condition_pills::ConditionPills;
-use crate::components::condition_pills::types::Condition;
+use crate::components::condition_pills::types::{Combinator, Condition};
 use crate::components::skeleton::{Skeleton, SkeletonVariant};
 use crate::{
     api::{fetch_config, fetch_dimensions},
@@ -406,11 +406,12 @@ pub fn home() -> impl IntoView {
                                                                 let conditions: Vec<Condition> = context
                                                                     .try_into()
                                                                     .unwrap_or(vec![]);
+                                                                let combinator = Combinator::from(&context.condition);
                                                                 view! {
                                                                     <div class="card bg-base-100 shadow m-6">
                                                                         <div class="card-body">
                                                                             <h2 class="card-title">
-                                                                                <ConditionPills conditions=conditions/>
+                                                                                <ConditionPills conditions=conditions combinator=combinator/>
                                                                             </h2>
                                                                             <table class="table table-zebra mt-10">
                                                                                 <thead>
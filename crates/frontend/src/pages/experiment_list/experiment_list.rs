@@ -14,12 +14,13 @@ use crate::components::{
 
 use crate::types::{ExperimentsResponse, ListFilters};
 
-use super::utils::experiment_table_columns;
+use super::utils::{comma_separated_input, experiment_table_columns, non_empty_input};
 use crate::{
     api::{fetch_default_config, fetch_dimensions, fetch_experiments},
-    types::{DefaultConfig, Dimension},
+    types::{DefaultConfig, Dimension, ExperimentSortOn, SortBy},
 };
 use serde_json::{json, Map, Value};
+use std::str::FromStr;
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 struct CombinedResource {
@@ -36,6 +37,13 @@ pub fn experiment_list() -> impl IntoView {
         status: None,
         from_date: Utc.timestamp_opt(0, 0).single(),
         to_date: Utc.timestamp_opt(4130561031, 0).single(),
+        experiment_name: None,
+        experiment_ids: None,
+        created_by: None,
+        override_keys: None,
+        dimensions: None,
+        sort_on: None,
+        sort_by: None,
         page: Some(1),
         count: Some(10),
     });
@@ -81,7 +89,6 @@ pub fn experiment_list() -> impl IntoView {
         close_drawer("create_exp_drawer");
     };
 
-    // TODO: Add filters
     view! {
         <div class="p-8">
             <Suspense fallback=move || view! { <Skeleton/> }>
@@ -114,6 +121,106 @@ pub fn experiment_list() -> impl IntoView {
                                 </DrawerBtn>
                             </div>
                         </div>
+                        <div class="flex flex-wrap gap-4 my-4">
+                            <input
+                                type="text"
+                                placeholder="Search by name"
+                                class="input input-bordered input-sm w-full max-w-xs"
+                                on:change=move |ev| {
+                                    set_filters
+                                        .update(|f| {
+                                            f.experiment_name = non_empty_input(event_target_value(&ev));
+                                            f.page = Some(1);
+                                        });
+                                }
+                            />
+
+                            <input
+                                type="text"
+                                placeholder="Created by"
+                                class="input input-bordered input-sm w-full max-w-xs"
+                                on:change=move |ev| {
+                                    set_filters
+                                        .update(|f| {
+                                            f.created_by = non_empty_input(event_target_value(&ev));
+                                            f.page = Some(1);
+                                        });
+                                }
+                            />
+
+                            <input
+                                type="text"
+                                placeholder="Override keys, ex: key1,key2"
+                                class="input input-bordered input-sm w-full max-w-xs"
+                                on:change=move |ev| {
+                                    set_filters
+                                        .update(|f| {
+                                            f.override_keys = comma_separated_input(event_target_value(&ev));
+                                            f.page = Some(1);
+                                        });
+                                }
+                            />
+
+                            <input
+                                type="text"
+                                placeholder="Dimensions, ex: city=Bangalore"
+                                class="input input-bordered input-sm w-full max-w-xs"
+                                on:change=move |ev| {
+                                    set_filters
+                                        .update(|f| {
+                                            f.dimensions = comma_separated_input(event_target_value(&ev));
+                                            f.page = Some(1);
+                                        });
+                                }
+                            />
+
+                            <input
+                                type="text"
+                                placeholder="Experiment ids, ex: 7123,7124"
+                                class="input input-bordered input-sm w-full max-w-xs"
+                                on:change=move |ev| {
+                                    set_filters
+                                        .update(|f| {
+                                            f.experiment_ids = comma_separated_input(event_target_value(&ev));
+                                            f.page = Some(1);
+                                        });
+                                }
+                            />
+
+                            <select
+                                class="select select-bordered select-sm"
+                                on:change=move |ev| {
+                                    set_filters
+                                        .update(|f| {
+                                            f.sort_on = ExperimentSortOn::from_str(&event_target_value(&ev)).ok();
+                                        });
+                                }
+                            >
+
+                                <option value=ExperimentSortOn::LastModified.to_string() selected>
+                                    Last Modified
+                                </option>
+                                <option value=ExperimentSortOn::CreatedAt.to_string()>
+                                    Created At
+                                </option>
+                                <option value=ExperimentSortOn::Name.to_string()>Name</option>
+                            </select>
+                            <select
+                                class="select select-bordered select-sm"
+                                on:change=move |ev| {
+                                    set_filters
+                                        .update(|f| {
+                                            f.sort_by = SortBy::from_str(&event_target_value(&ev)).ok();
+                                        });
+                                }
+                            >
+
+                                <option value=SortBy::Desc.to_string() selected>
+                                    Descending
+                                </option>
+                                <option value=SortBy::Asc.to_string()>Ascending</option>
+                            </select>
+                        </div>
                         <div>
 
                             {move || {
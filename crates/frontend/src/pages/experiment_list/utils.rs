@@ -129,3 +129,17 @@ pub fn experiment_table_columns() -> Vec<Column> {
         Column::default("last_modified".to_string()),
     ]
 }
+
+pub fn non_empty_input(value: String) -> Option<String> {
+    let value = value.trim().to_string();
+    (!value.is_empty()).then_some(value)
+}
+
+pub fn comma_separated_input(value: String) -> Option<Vec<String>> {
+    let values = value
+        .split(',')
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+        .collect::<Vec<String>>();
+    (!values.is_empty()).then_some(values)
+}
@@ -28,6 +28,13 @@ pub fn function_list() -> impl IntoView {
         status: None,
         from_date: Utc.timestamp_opt(0, 0).single(),
         to_date: Utc.timestamp_opt(4130561031, 0).single(),
+        experiment_name: None,
+        experiment_ids: None,
+        created_by: None,
+        override_keys: None,
+        dimensions: None,
+        sort_on: None,
+        sort_by: None,
         page: Some(1),
         count: Some(10),
     });
@@ -96,6 +96,22 @@ pub struct ExperimentResponse {
     pub context: Value,
     pub variants: Value,
     pub chosen_variant: Option<String>,
+
+    pub description: Option<String>,
+    pub hypothesis: Option<String>,
+    pub owner_team: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub external_links: Vec<String>,
+
+    pub start_at: Option<DateTime<Utc>>,
+    pub end_at: Option<DateTime<Utc>>,
+    pub fallback_variant: Option<String>,
+
+    pub layer: Option<String>,
+    pub layer_offset: Option<i32>,
+    pub layer_allocation: Option<i32>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -108,11 +124,57 @@ pub struct ExperimentsResponse {
 #[derive(Serialize, Deserialize, Debug, Clone, Deref, DerefMut, PartialEq)]
 pub struct StatusTypes(pub Vec<ExperimentStatusType>);
 
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Default,
+    Serialize,
+    Deserialize,
+    strum_macros::Display,
+    strum_macros::EnumString,
+)]
+#[strum(serialize_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum ExperimentSortOn {
+    CreatedAt,
+    #[default]
+    LastModified,
+    Name,
+}
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Default,
+    Serialize,
+    Deserialize,
+    strum_macros::Display,
+    strum_macros::EnumString,
+)]
+#[strum(serialize_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum SortBy {
+    Asc,
+    #[default]
+    Desc,
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ListFilters {
     pub status: Option<StatusTypes>,
     pub from_date: Option<DateTime<Utc>>,
     pub to_date: Option<DateTime<Utc>>,
+    pub experiment_name: Option<String>,
+    pub experiment_ids: Option<Vec<String>>,
+    pub created_by: Option<String>,
+    pub override_keys: Option<Vec<String>>,
+    pub dimensions: Option<Vec<String>>,
+    pub sort_on: Option<ExperimentSortOn>,
+    pub sort_by: Option<SortBy>,
     pub page: Option<i64>,
     pub count: Option<i64>,
 }
@@ -148,6 +210,70 @@ pub struct Experiment {
     pub(crate) created_at: DateTime<Utc>,
     pub(crate) last_modified: DateTime<Utc>,
     pub(crate) chosen_variant: Option<String>,
+    pub(crate) description: Option<String>,
+    pub(crate) hypothesis: Option<String>,
+    pub(crate) owner_team: Option<String>,
+    #[serde(default)]
+    pub(crate) tags: Vec<String>,
+    #[serde(default)]
+    pub(crate) external_links: Vec<String>,
+    pub(crate) start_at: Option<DateTime<Utc>>,
+    pub(crate) end_at: Option<DateTime<Utc>>,
+    pub(crate) fallback_variant: Option<String>,
+    pub(crate) layer: Option<String>,
+    pub(crate) layer_offset: Option<i32>,
+    pub(crate) layer_allocation: Option<i32>,
+}
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Deserialize, Serialize, strum_macros::Display,
+)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[strum(serialize_all = "title_case")]
+pub enum HistoryEventType {
+    Created,
+    Ramped,
+    OverridesUpdated,
+    Concluded,
+    Updated,
+    Deleted,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FieldDiff {
+    pub field: String,
+    pub old_value: Option<Value>,
+    pub new_value: Option<Value>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub event_type: HistoryEventType,
+    pub timestamp: NaiveDateTime,
+    pub changed_by: String,
+    pub diffs: Vec<FieldDiff>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ExperimentHistory {
+    pub experiment_id: String,
+    pub history: Vec<HistoryEntry>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ExperimentTemplate {
+    pub name: String,
+    pub description: Option<String>,
+    pub variants: Variants,
+}
+
+impl DropdownOption for ExperimentTemplate {
+    fn key(&self) -> String {
+        self.name.clone()
+    }
+    fn label(&self) -> String {
+        self.name.clone()
+    }
 }
 
 /*************************** Context-Override types ********************************/
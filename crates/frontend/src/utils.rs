@@ -240,7 +240,7 @@ pub fn extract_conditions(
             .to_string(),
     )?;
 
-    let conditions = match context.get("and") {
+    let conditions = match context.get("and").or_else(|| context.get("or")) {
         Some(conditions_json) => conditions_json
             .as_array()
             .ok_or("An error occurred while extracting dimensions: failed parsing conditions as an array".to_string())?
@@ -250,6 +250,12 @@ pub fn extract_conditions(
 
     let mut condition_tuples = Vec::new();
     for condition in &conditions {
+        // negated conditions are listed with their operator prefixed by a `!`
+        let (condition, negation) = match condition.get("!") {
+            Some(Value::Array(args)) if args.len() == 1 => (&args[0], "!"),
+            Some(negated) => (negated, "!"),
+            None => (condition, ""),
+        };
         let condition_obj = condition
             .as_object()
             .ok_or("failed to parse condition as an object".to_string())?;
@@ -264,7 +270,7 @@ pub fn extract_conditions(
 
             condition_tuples.push((
                 String::from(variable_name),
-                operator.to_owned(),
+                format!("{negation}{operator}"),
                 variable_value.to_owned(),
             ));
         }
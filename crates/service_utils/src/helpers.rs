@@ -200,6 +200,278 @@ pub fn get_variable_name_and_value(
     Ok((variable_name, variable_value))
 }
 
+/// The comparison a context condition makes on a dimension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConditionOperator {
+    Is,
+    IsNot,
+    LessThan,
+    LessThanOrEqual,
+    GreaterThan,
+    GreaterThanOrEqual,
+    /// inclusive of both bounds
+    Between,
+    In,
+    NotIn,
+    /// the dimension's value is a string or an array containing the value
+    Has,
+    /// the dimension's value is part of the string value
+    SubstringOf,
+}
+
+/// A single comparison of a context, `values` holding the bounds of
+/// `Between` and the members of `In`/`NotIn`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContextCondition {
+    pub dimension: String,
+    pub operator: ConditionOperator,
+    pub values: Vec<Value>,
+}
+
+fn compare_values(a: &Value, b: &Value) -> Option<std::cmp::Ordering> {
+    let as_number = |value: &Value| match value {
+        Value::Number(num) => num.as_f64(),
+        Value::String(s) => s.parse::<f64>().ok(),
+        _ => None,
+    };
+    match (a, b) {
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        _ => as_number(a)?.partial_cmp(&as_number(b)?),
+    }
+}
+
+impl ContextCondition {
+    /// Whether the condition holds when its dimension has the value `value`.
+    pub fn matches(&self, value: &Value) -> bool {
+        use std::cmp::Ordering::*;
+        use ConditionOperator::*;
+
+        match (self.operator, self.values.as_slice()) {
+            (Is, [expected]) => value == expected,
+            (IsNot, [expected]) => value != expected,
+            (LessThan, [bound]) => compare_values(value, bound) == Some(Less),
+            (LessThanOrEqual, [bound]) => {
+                matches!(compare_values(value, bound), Some(Less | Equal))
+            }
+            (GreaterThan, [bound]) => compare_values(value, bound) == Some(Greater),
+            (GreaterThanOrEqual, [bound]) => {
+                matches!(compare_values(value, bound), Some(Greater | Equal))
+            }
+            (Between, [low, high]) => {
+                matches!(compare_values(low, value), Some(Less | Equal))
+                    && matches!(compare_values(value, high), Some(Less | Equal))
+            }
+            (In, members) => members.contains(value),
+            (NotIn, members) => !members.contains(value),
+            (Has, [expected]) => match (value, expected) {
+                (Value::String(value), Value::String(expected)) => {
+                    value.contains(expected.as_str())
+                }
+                (Value::Array(items), _) => items.contains(expected),
+                _ => false,
+            },
+            (SubstringOf, [Value::String(expected)]) => {
+                value.as_str().is_some_and(|value| expected.contains(value))
+            }
+            _ => false,
+        }
+    }
+}
+
+fn parse_condition(operator: &str, operands: &Value) -> result::Result<ContextCondition> {
+    let unsupported = || {
+        result::AppError::BadArgument(format!(
+            "Unsupported condition {{\"{operator}\": {operands}}}. Supported operators are ==, !=, <, <=, >, >=, between and in, combined with and, or and !"
+        ))
+    };
+
+    let operands = operands.as_array().ok_or(result::AppError::BadArgument(
+        "Failed to parse operands as an arrays. Ensure the context provided obeys the rules of JSON logic"
+            .into(),
+    ))?;
+    let (variable_name, _) = get_variable_name_and_value(operands)?;
+    let var_pos = operands
+        .iter()
+        .position(|operand| operand.get("var").is_some())
+        .unwrap_or_default();
+    let mut values = operands
+        .iter()
+        .enumerate()
+        .filter(|(idx, _)| *idx != var_pos)
+        .map(|(_, operand)| operand.clone())
+        .collect::<Vec<Value>>();
+
+    // comparisons with the dimension on the right are flipped around
+    let operator = match (operator, var_pos, values.len()) {
+        ("==", _, 1) => ConditionOperator::Is,
+        ("!=", _, 1) => ConditionOperator::IsNot,
+        ("<", 0, 1) | (">", 1, 1) => ConditionOperator::LessThan,
+        ("<=", 0, 1) | (">=", 1, 1) => ConditionOperator::LessThanOrEqual,
+        (">", 0, 1) | ("<", 1, 1) => ConditionOperator::GreaterThan,
+        (">=", 0, 1) | ("<=", 1, 1) => ConditionOperator::GreaterThanOrEqual,
+        ("<=", 1, 2) => ConditionOperator::Between,
+        ("in", 0, 1) => match values.pop() {
+            Some(Value::Array(members)) => {
+                values = members;
+                ConditionOperator::In
+            }
+            Some(Value::String(value)) => {
+                values = vec![Value::String(value)];
+                ConditionOperator::SubstringOf
+            }
+            _ => return Err(unsupported()),
+        },
+        ("in", 1, 1) => ConditionOperator::Has,
+        _ => return Err(unsupported()),
+    };
+
+    Ok(ContextCondition {
+        dimension: variable_name.to_string(),
+        operator,
+        values,
+    })
+}
+
+/// A context broken down into its comparisons and the `and`, `or` and `!`
+/// combinators joining them.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ContextExpression {
+    Condition(ContextCondition),
+    And(Vec<ContextExpression>),
+    Or(Vec<ContextExpression>),
+    Not(Box<ContextExpression>),
+}
+
+impl ContextExpression {
+    /// Every comparison made, however they are combined.
+    pub fn conditions(&self) -> Vec<&ContextCondition> {
+        match self {
+            Self::Condition(condition) => vec![condition],
+            Self::And(expressions) | Self::Or(expressions) => expressions
+                .iter()
+                .flat_map(ContextExpression::conditions)
+                .collect(),
+            Self::Not(expression) => expression.conditions(),
+        }
+    }
+
+    /// Evaluates the context for the given dimension values, a dimension
+    /// given a list of values matching if any of them does. Returns `None`
+    /// when the outcome depends on dimensions that are not given.
+    pub fn evaluate(&self, dimensions: &Map<String, Value>) -> Option<bool> {
+        match self {
+            Self::Condition(condition) => {
+                dimensions
+                    .get(&condition.dimension)
+                    .map(|value| match value {
+                        Value::Array(values) => {
+                            values.iter().any(|value| condition.matches(value))
+                        }
+                        _ => condition.matches(value),
+                    })
+            }
+            Self::And(expressions) => {
+                let results = expressions
+                    .iter()
+                    .map(|expression| expression.evaluate(dimensions))
+                    .collect::<Vec<Option<bool>>>();
+                if results.contains(&Some(false)) {
+                    Some(false)
+                } else if results.iter().all(Option::is_some) {
+                    Some(true)
+                } else {
+                    None
+                }
+            }
+            Self::Or(expressions) => {
+                let results = expressions
+                    .iter()
+                    .map(|expression| expression.evaluate(dimensions))
+                    .collect::<Vec<Option<bool>>>();
+                if results.contains(&Some(true)) {
+                    Some(true)
+                } else if results.iter().all(Option::is_some) {
+                    Some(false)
+                } else {
+                    None
+                }
+            }
+            Self::Not(expression) => expression.evaluate(dimensions).map(|res| !res),
+        }
+    }
+}
+
+/// Parses a context into its comparisons and combinators. An object with more
+/// than one operator is treated as an `and` of them, and a negated `in` list
+/// is read as a `NotIn` comparison.
+pub fn parse_context(context_json: &Value) -> result::Result<ContextExpression> {
+    let context = context_json
+        .as_object()
+        .ok_or(
+            result::AppError::BadArgument("Failed to parse condition as an object. Ensure the context provided obeys the rules of JSON logic".into())
+            )?;
+
+    let mut expressions = context
+        .iter()
+        .map(|(operator, operands)| match operator.as_str() {
+            "and" | "or" => {
+                let operands = operands
+                    .as_array()
+                    .filter(|operands| !operands.is_empty())
+                    .ok_or(result::AppError::BadArgument(format!(
+                        "Failed parsing the operands of {operator} as a non-empty array. Ensure the context provided obeys the rules of JSON logic"
+                    )))?;
+                let expressions = operands
+                    .iter()
+                    .map(parse_context)
+                    .collect::<result::Result<Vec<ContextExpression>>>()?;
+                Ok(if operator == "and" {
+                    ContextExpression::And(expressions)
+                } else {
+                    ContextExpression::Or(expressions)
+                })
+            }
+            "!" => {
+                // the single argument may also be wrapped in an array
+                let negated = match operands {
+                    Value::Array(args) if args.len() == 1 => &args[0],
+                    _ => operands,
+                };
+                Ok(match parse_context(negated)? {
+                    ContextExpression::Condition(condition)
+                        if condition.operator == ConditionOperator::In =>
+                    {
+                        ContextExpression::Condition(ContextCondition {
+                            operator: ConditionOperator::NotIn,
+                            ..condition
+                        })
+                    }
+                    expression => ContextExpression::Not(Box::new(expression)),
+                })
+            }
+            _ => parse_condition(operator, operands).map(ContextExpression::Condition),
+        })
+        .collect::<result::Result<Vec<ContextExpression>>>()?;
+
+    match expressions.len() {
+        0 => Err(result::AppError::BadArgument(
+            "Empty condition. Ensure the context provided obeys the rules of JSON logic"
+                .into(),
+        )),
+        1 => Ok(expressions.remove(0)),
+        _ => Ok(ContextExpression::And(expressions)),
+    }
+}
+
+/// Lists the comparisons a context makes, however they are combined.
+pub fn extract_conditions(context_json: &Value) -> result::Result<Vec<ContextCondition>> {
+    Ok(parse_context(context_json)?
+        .conditions()
+        .into_iter()
+        .cloned()
+        .collect())
+}
+
 pub fn validation_err_to_str(errors: Vec<ValidationError>) -> Vec<String> {
     errors.into_iter().map(|error| {
         match error.kind {
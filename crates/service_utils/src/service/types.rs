@@ -20,6 +20,21 @@ pub struct ExperimentationFlags {
     pub allow_same_keys_non_overlapping_ctx: bool,
 }
 
+pub struct ExperimentApprovalConfig {
+    pub enabled: bool,
+    pub ramp_threshold: u8,
+}
+
+pub struct WebhookConfig {
+    pub max_attempts: u32,
+    pub retry_backoff_secs: u64,
+}
+
+pub struct BanditConfig {
+    pub min_weight: u8,
+    pub samples: u32,
+}
+
 #[derive(Copy, Clone, Debug)]
 pub enum AppEnv {
     PROD,
@@ -30,6 +45,7 @@ pub enum AppEnv {
 
 pub struct AppState {
     pub cac_host: String,
+    pub cac_in_process: bool,
     pub app_env: AppEnv,
     pub tenants: HashSet<String>,
     pub cac_version: String,
@@ -37,6 +53,9 @@ pub struct AppState {
     pub default_config_validation_schema: JSONSchema,
     pub meta_schema: JSONSchema,
     pub experimentation_flags: ExperimentationFlags,
+    pub experiment_approval: ExperimentApprovalConfig,
+    pub webhook: WebhookConfig,
+    pub bandit: BanditConfig,
     pub snowflake_generator: Mutex<SnowflakeIdGenerator>,
     pub enable_tenant_and_scope: bool,
     pub tenant_middleware_exclusion_list: HashSet<String>,
@@ -82,6 +101,14 @@ impl FromRequest for AppScope {
 #[derive(Deref, DerefMut, Clone, Debug)]
 pub struct AppExecutionNamespace(pub String);
 impl AppExecutionNamespace {
+    pub fn new(app_state: &AppState, tenant: &Tenant, scope: AppScope) -> Self {
+        if app_state.enable_tenant_and_scope {
+            AppExecutionNamespace(format!("{}_{}", tenant.as_str(), scope))
+        } else {
+            AppExecutionNamespace("cac_v1".to_string())
+        }
+    }
+
     pub fn from_request_sync(req: &actix_web::HttpRequest) -> Result<Self, Error> {
         let app_state = match req.app_data::<Data<AppState>>() {
             Some(val) => val,
@@ -25,7 +25,10 @@ use service_utils::{
     middlewares::{
         app_scope::AppExecutionScopeMiddlewareFactory, tenant::TenantMiddlewareFactory,
     },
-    service::types::{AppEnv, AppScope, AppState, ExperimentationFlags},
+    service::types::{
+        AppEnv, AppScope, AppState, BanditConfig, ExperimentApprovalConfig,
+        ExperimentationFlags, WebhookConfig,
+    },
 };
 
 #[actix_web::get("favicon.ico")]
@@ -64,6 +67,7 @@ async fn main() -> Result<()> {
     };
 
     let cac_host: String = get_from_env_unsafe("CAC_HOST").expect("CAC host is not set");
+    let cac_in_process: bool = get_from_env_or_default("CAC_IN_PROCESS", true);
     let cac_port: u16 = get_from_env_unsafe("PORT").unwrap_or(8080);
     let cac_version: String = get_from_env_unsafe("CONTEXT_AWARE_CONFIG_VERSION")
         .expect("CONTEXT_AWARE_CONFIG_VERSION is not set");
@@ -105,9 +109,93 @@ async fn main() -> Result<()> {
     let allow_same_keys_non_overlapping_ctx: bool =
         get_from_env_unsafe("ALLOW_SAME_KEYS_NON_OVERLAPPING_CTX")
             .expect("ALLOW_SAME_KEYS_NON_OVERLAPPING_CTX not set");
+    let enable_experiment_approval: bool =
+        get_from_env_or_default("ENABLE_EXPERIMENT_APPROVAL", false);
+    let experiment_ramp_approval_threshold: u8 =
+        get_from_env_or_default("EXPERIMENT_RAMP_APPROVAL_THRESHOLD", 0);
+    let experiment_drift_check_interval: u64 =
+        get_from_env_or_default("EXPERIMENT_DRIFT_CHECK_INTERVAL", 0);
+    let experiment_drift_auto_repair: bool =
+        get_from_env_or_default("EXPERIMENT_DRIFT_AUTO_REPAIR", false);
+    let experiment_scheduler_interval: u64 =
+        get_from_env_or_default("EXPERIMENT_SCHEDULER_INTERVAL", 60);
+    let webhook_max_attempts: u32 = get_from_env_or_default("WEBHOOK_MAX_ATTEMPTS", 5);
+    let webhook_retry_backoff: u64 = get_from_env_or_default("WEBHOOK_RETRY_BACKOFF", 2);
+    let webhook_retry_interval: u64 =
+        get_from_env_or_default("WEBHOOK_RETRY_INTERVAL", 30);
+    let experiment_bandit_interval: u64 =
+        get_from_env_or_default("EXPERIMENT_BANDIT_INTERVAL", 300);
+    let experiment_bandit_min_weight: u8 =
+        get_from_env_or_default("EXPERIMENT_BANDIT_MIN_WEIGHT", 5);
+    let experiment_bandit_samples: u32 =
+        get_from_env_or_default("EXPERIMENT_BANDIT_SAMPLES", 1000);
 
     /****** EXPERIMENTATION PLATFORM ENVs *********/
 
+    let app_state = Data::new(AppState {
+        db_pool: schema_manager.clone(),
+        default_config_validation_schema: get_default_config_validation_schema(),
+        cac_host: cac_host.to_owned() + base.as_str(),
+        cac_in_process,
+        cac_version: cac_version.to_owned(),
+
+        experimentation_flags: ExperimentationFlags {
+            allow_same_keys_overlapping_ctx: allow_same_keys_overlapping_ctx.to_owned(),
+            allow_diff_keys_overlapping_ctx: allow_diff_keys_overlapping_ctx.to_owned(),
+            allow_same_keys_non_overlapping_ctx: allow_same_keys_non_overlapping_ctx
+                .to_owned(),
+        },
+        experiment_approval: ExperimentApprovalConfig {
+            enabled: enable_experiment_approval,
+            ramp_threshold: experiment_ramp_approval_threshold,
+        },
+        webhook: WebhookConfig {
+            max_attempts: webhook_max_attempts,
+            retry_backoff_secs: webhook_retry_backoff,
+        },
+        bandit: BanditConfig {
+            min_weight: experiment_bandit_min_weight,
+            samples: experiment_bandit_samples,
+        },
+
+        snowflake_generator: Mutex::new(SnowflakeIdGenerator::new(1, 1)),
+        meta_schema: get_meta_schema(),
+        app_env: app_env.to_owned(),
+        enable_tenant_and_scope: enable_tenant_and_scope.to_owned(),
+        tenants: tenants.to_owned(),
+        tenant_middleware_exclusion_list: tenant_middleware_exclusion_list.to_owned(),
+        service_prefix: service_prefix_str.to_owned(),
+    });
+
+    if experiment_drift_check_interval > 0 {
+        actix_web::rt::spawn(drift::run_drift_check_job(
+            app_state.clone(),
+            Duration::from_secs(experiment_drift_check_interval),
+            experiment_drift_auto_repair,
+        ));
+    }
+
+    if experiment_scheduler_interval > 0 {
+        actix_web::rt::spawn(experiments::run_experiment_scheduler_job(
+            app_state.clone(),
+            Duration::from_secs(experiment_scheduler_interval),
+        ));
+    }
+
+    if webhook_retry_interval > 0 {
+        actix_web::rt::spawn(webhooks::run_webhook_retry_job(
+            app_state.clone(),
+            Duration::from_secs(webhook_retry_interval),
+        ));
+    }
+
+    if experiment_bandit_interval > 0 {
+        actix_web::rt::spawn(bandit::run_bandit_job(
+            app_state.clone(),
+            Duration::from_secs(experiment_bandit_interval),
+        ));
+    }
+
     /* Frontend configurations */
     let ui_redirect_path = match tenants.iter().next() {
         Some(tenant) => format!("{}/admin/{}/default-config", base, tenant),
@@ -132,7 +220,6 @@ async fn main() -> Result<()> {
         let leptos_options = &conf.leptos_options;
         let site_root = &leptos_options.site_root;
         let leptos_envs = ui_envs.clone();
-        let cac_host = cac_host.to_owned() + base.as_str();
         App::new()
             .wrap_fn(|req, srv| {
                 let user = User::default();
@@ -140,30 +227,7 @@ async fn main() -> Result<()> {
                 srv.call(req)
             })
             .wrap(TenantMiddlewareFactory)
-            .app_data(Data::new(AppState {
-                db_pool: schema_manager.clone(),
-                default_config_validation_schema: get_default_config_validation_schema(),
-                cac_host: cac_host.to_owned(),
-                cac_version: cac_version.to_owned(),
-
-                experimentation_flags: ExperimentationFlags {
-                    allow_same_keys_overlapping_ctx: allow_same_keys_overlapping_ctx
-                        .to_owned(),
-                    allow_diff_keys_overlapping_ctx: allow_diff_keys_overlapping_ctx
-                        .to_owned(),
-                    allow_same_keys_non_overlapping_ctx:
-                        allow_same_keys_non_overlapping_ctx.to_owned(),
-                },
-
-                snowflake_generator: Mutex::new(SnowflakeIdGenerator::new(1,1)),
-                meta_schema: get_meta_schema(),
-                app_env: app_env.to_owned(),
-                enable_tenant_and_scope: enable_tenant_and_scope.to_owned(),
-                tenants: tenants.to_owned(),
-                tenant_middleware_exclusion_list: tenant_middleware_exclusion_list
-                    .to_owned(),
-                service_prefix: service_prefix_str.to_owned(),
-            }))
+            .app_data(app_state.clone())
             .wrap(
                 actix_web::middleware::DefaultHeaders::new()
                     .add(("X-SERVER-VERSION", cac_version.to_string()))
@@ -214,6 +278,47 @@ async fn main() -> Result<()> {
                             .wrap(AppExecutionScopeMiddlewareFactory::new(AppScope::CAC))
                             .service(functions::endpoints()),
                     )
+                    .service(
+                        scope("/export")
+                            .wrap(AppExecutionScopeMiddlewareFactory::new(AppScope::CAC))
+                            .service(import_export::export_endpoints()),
+                    )
+                    .service(
+                        scope("/import")
+                            .wrap(AppExecutionScopeMiddlewareFactory::new(AppScope::CAC))
+                            .service(import_export::import_endpoints()),
+                    )
+                    .service(
+                        bandit::endpoints(scope("/experiments/bandit")).wrap(
+                            AppExecutionScopeMiddlewareFactory::new(AppScope::EXPERIMENTATION),
+                        ),
+                    )
+                    .service(
+                        change_requests::endpoints(scope("/experiments/change-requests"))
+                            .wrap(AppExecutionScopeMiddlewareFactory::new(
+                                AppScope::EXPERIMENTATION,
+                            )),
+                    )
+                    .service(
+                        drift::endpoints(scope("/experiments/drift")).wrap(
+                            AppExecutionScopeMiddlewareFactory::new(AppScope::EXPERIMENTATION),
+                        ),
+                    )
+                    .service(
+                        holdouts::endpoints(scope("/experiments/holdouts")).wrap(
+                            AppExecutionScopeMiddlewareFactory::new(AppScope::EXPERIMENTATION),
+                        ),
+                    )
+                    .service(
+                        templates::endpoints(scope("/experiments/templates")).wrap(
+                            AppExecutionScopeMiddlewareFactory::new(AppScope::EXPERIMENTATION),
+                        ),
+                    )
+                    .service(
+                        webhooks::endpoints(scope("/experiments/webhooks")).wrap(
+                            AppExecutionScopeMiddlewareFactory::new(AppScope::EXPERIMENTATION),
+                        ),
+                    )
                     .service(
                         experiments::endpoints(scope("/experiments")).wrap(
                             AppExecutionScopeMiddlewareFactory::new(AppScope::EXPERIMENTATION),
@@ -21,6 +21,10 @@ struct Arc_Client *get_client(const char *tenant);
 
 char *get_applicable_variant(struct Arc_Client *client, const char *c_context, short toss);
 
+char *get_variant_assignments(struct Arc_Client *client, const char *c_context, short toss);
+
+int set_forced_variants(struct Arc_Client *client, const char *c_forced_variants);
+
 char *get_satisfied_experiments(struct Arc_Client *client, const char *c_context);
 
 char *get_running_experiments(struct Arc_Client *client);
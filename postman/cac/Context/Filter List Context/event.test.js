@@ -0,0 +1,51 @@
+const host = pm.variables.get("host");
+const context_id = pm.environment.get("context_id");
+
+function listContextIds(query, callback) {
+    const getRequest = {
+        url: `${host}/context/list?${query}`,
+        method: 'GET',
+        header: {
+            'Content-Type': 'application/json',
+            'x-tenant': 'test',
+        }
+    };
+
+    pm.sendRequest(getRequest, (error, response) => {
+        if(error) {
+            console.log("Failed to list contexts");
+            throw error;
+        }
+        callback(response.json().data.map((context) => context.id));
+    });
+}
+
+pm.test("200 check", function() {
+    pm.response.to.have.status(200);
+})
+
+pm.test("Context matching every filter is listed", function() {
+    const response = pm.response.json();
+    const context_ids = response.data.map((context) => context.id);
+    pm.expect(context_ids).to.include(context_id);
+
+    for (const context of response.data) {
+        pm.expect(context.priority).to.be.eq(100);
+        pm.expect(context.override).to.have.property("key1");
+    }
+});
+
+pm.test("Context failing any filter is left out", function() {
+    const queries = [
+        "dimensions=clientId=piyaz",
+        "dimensions=clientId=tamatar&override_keys=key1,key2",
+        "dimensions=clientId=tamatar&min_priority=101",
+        "dimensions=clientId=tamatar&max_priority=99",
+    ];
+    for (const query of queries) {
+        listContextIds(query, (context_ids) => {
+            console.log(`Checking context=${context_id} is not listed for ${query}`);
+            pm.expect(context_ids).to.not.include(context_id);
+        });
+    }
+});
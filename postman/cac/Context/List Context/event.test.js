@@ -5,7 +5,7 @@ pm.test("200 check", function() {
 
 pm.test("Response validation", function() {
     const response = pm.response.json();
-    if (response.length == 0) {
+    if (response.data.length == 0) {
         throw "list context should return at least one context now"
     }
 });